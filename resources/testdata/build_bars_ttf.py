@@ -0,0 +1,175 @@
+"""Compile Bars/Bars.designspace, all lines, to Bars.ttf, a variable font.
+
+Regular is the default and Bold the only other master, so each glyph has a single
+gvar tuple peaking at wght 1.0 with a delta for every point. The designspace
+instances, and the masters, become fvar named instances. Rules are not compiled,
+there's no GSUB. Standard library only:
+
+    python3 build_bars_ttf.py
+"""
+
+import struct
+import xml.etree.ElementTree as ET
+from pathlib import Path
+
+from build_overlaps_ttf import checksum, simple_glyph
+
+HERE = Path(__file__).parent
+DESIGNSPACE = HERE / "Bars" / "Bars.designspace"
+GLYPHS = [".notdef", "bars", "dollar", "dollar.alt"]
+
+# Weight 400 to 700, default 400
+WGHT = (400, 400, 700)
+INSTANCES = [("Regular", 400), ("Book", 450), ("SemiBold", 600), ("Bold", 700)]
+AXIS_NAME_ID = 256
+
+DELTAS_ARE_WORDS = 0x40
+SHARED_POINT_NUMBERS = 0x8000
+
+
+# The masters have no .notdef, draw the usual box
+NOTDEF = [[(50, 0, True), (450, 0, True), (450, 700, True), (50, 700, True)]]
+
+
+def load_contours(ufo, name):
+    if name == ".notdef":
+        return NOTDEF
+    root = ET.parse(ufo / "glyphs" / (name + ".glif")).getroot()
+    return [
+        [
+            (int(p.get("x")), int(p.get("y")), p.get("type", "offcurve") != "offcurve")
+            for p in c.findall("point")
+        ]
+        for c in root.iter("contour")
+    ]
+
+
+def fixed(v):
+    return struct.pack(">i", round(v * 65536))
+
+
+def packed_deltas(deltas):
+    """Every delta as a word, in runs of at most 64."""
+    data = b""
+    for start in range(0, len(deltas), 64):
+        run = deltas[start : start + 64]
+        data += bytes([DELTAS_ARE_WORDS | (len(run) - 1)])
+        data += b"".join(struct.pack(">h", d) for d in run)
+    return data
+
+
+def glyph_variation_data(regular, bold):
+    """One tuple, peak wght 1.0 via shared tuple 0, deltas for all points."""
+    points = [(b[0] - r[0], b[1] - r[1]) for rc, bc in zip(regular, bold) for r, b in zip(rc, bc)]
+    if not any(x or y for x, y in points):
+        return b""
+    # Advances don't vary, the four phantom points don't move
+    points += [(0, 0)] * 4
+    # Shared point numbers: 0 means every point
+    serialized = b"\0"
+    tuple_data = packed_deltas([x for x, _ in points]) + packed_deltas([y for _, y in points])
+    header = struct.pack(">HH", len(tuple_data), 0)
+    data_offset = 4 + len(header)
+    return struct.pack(">HH", SHARED_POINT_NUMBERS | 1, data_offset) + header + serialized + tuple_data
+
+
+def name_table(names):
+    records = b""
+    strings = b""
+    for name_id, value in sorted(names.items()):
+        encoded = value.encode("utf-16-be")
+        records += struct.pack(">HHHHHH", 3, 1, 0x409, name_id, len(encoded), len(strings))
+        strings += encoded
+    return struct.pack(">HHH", 0, len(names), 6 + len(records)) + records + strings
+
+
+def build():
+    root = ET.parse(DESIGNSPACE).getroot()
+    sources = {s.get("stylename"): HERE / "Bars" / s.get("filename") for s in root.iter("source")}
+    regular = [load_contours(sources["Regular"], n) for n in GLYPHS]
+    bold = [load_contours(sources["Bold"], n) for n in GLYPHS]
+
+    glyf = b""
+    loca = [0]
+    for contours in regular:
+        if contours:
+            glyf += simple_glyph(contours)
+        glyf += b"\0" * (-len(glyf) % 4)
+        loca.append(len(glyf))
+
+    variations = [glyph_variation_data(r, b) for r, b in zip(regular, bold)]
+    shared_tuples = b"\x40\x00"  # wght 1.0 as F2Dot14
+    offsets_size = 4 * (len(GLYPHS) + 1)
+    shared_tuples_offset = 20 + offsets_size
+    data_offset = shared_tuples_offset + len(shared_tuples)
+    offsets = [0]
+    for v in variations:
+        offsets.append(offsets[-1] + len(v))
+    gvar = (
+        struct.pack(
+            ">HHHHIHHI", 1, 0, 1, 1, shared_tuples_offset, len(GLYPHS), 1, data_offset
+        )
+        + b"".join(struct.pack(">I", o) for o in offsets)
+        + shared_tuples
+        + b"".join(variations)
+    )
+
+    instance_name_ids = {name: AXIS_NAME_ID + 1 + i for i, (name, _) in enumerate(INSTANCES)}
+    fvar = (
+        struct.pack(">HHHHHHHH", 1, 0, 16, 2, 1, 20, len(INSTANCES), 8)
+        + b"wght"
+        + fixed(WGHT[0])
+        + fixed(WGHT[1])
+        + fixed(WGHT[2])
+        + struct.pack(">HH", 0, AXIS_NAME_ID)
+        + b"".join(
+            struct.pack(">HH", instance_name_ids[name], 0) + fixed(wght)
+            for name, wght in INSTANCES
+        )
+    )
+    names = {AXIS_NAME_ID: "Weight"}
+    names.update({i: name for name, i in instance_name_ids.items()})
+
+    num_glyphs = len(GLYPHS)
+    tables = {
+        b"head": struct.pack(
+            ">IIIIHHQQhhhhHHhhh",
+            0x00010000, 0x00010000, 0, 0x5F0F3CF5, 0, 1000, 0, 0,
+            0, 0, 0, 0, 0, 8, 2, 1, 0,
+        ),
+        b"hhea": struct.pack(
+            ">IhhhHhhhhhhhhhhhH",
+            0x00010000, 800, -200, 0, 500, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0,
+            num_glyphs,
+        ),
+        b"maxp": struct.pack(">IH", 0x00005000, num_glyphs),
+        b"hmtx": b"".join(struct.pack(">Hh", 500, 0) for _ in GLYPHS),
+        b"loca": b"".join(struct.pack(">I", o) for o in loca),
+        b"glyf": glyf,
+        b"gvar": gvar,
+        b"fvar": fvar,
+        b"name": name_table(names),
+        b"post": struct.pack(">IIhhIIIII", 0x00020000, 0, 0, 0, 0, 0, 0, 0, 0)
+        + struct.pack(">H", num_glyphs)
+        + b"".join(struct.pack(">H", 0 if i == 0 else 257 + i) for i in range(num_glyphs))
+        + b"".join(bytes([len(n)]) + n.encode() for n in GLYPHS[1:]),
+    }
+
+    num_tables = len(tables)
+    entry_selector = num_tables.bit_length() - 1
+    search_range = 16 * (1 << entry_selector)
+    font = struct.pack(
+        ">IHHHH", 0x00010000, num_tables, search_range, entry_selector,
+        num_tables * 16 - search_range,
+    )
+    offset = len(font) + 16 * num_tables
+    body = b""
+    for tag in sorted(tables):
+        data = tables[tag]
+        font += struct.pack(">4sIII", tag, checksum(data), offset + len(body), len(data))
+        body += data + b"\0" * (-len(data) % 4)
+    (HERE / "Bars.ttf").write_bytes(font + body)
+
+
+if __name__ == "__main__":
+    build()
@@ -0,0 +1,183 @@
+"""Compile Overlaps.ufo, which is all lines and quadratics, to Overlaps.ttf.
+
+Points are copied as-is, so runs of off-curve points keep their implied on-curves
+and composites stay composites. Only the tables this tool reads, and the few they
+depend on, are written. Standard library only:
+
+    python3 build_overlaps_ttf.py
+"""
+
+import plistlib
+import struct
+import xml.etree.ElementTree as ET
+from pathlib import Path
+
+HERE = Path(__file__).parent
+UFO = HERE / "Overlaps.ufo"
+
+ARG_1_AND_2_ARE_WORDS = 0x0001
+ARGS_ARE_XY_VALUES = 0x0002
+MORE_COMPONENTS = 0x0020
+WE_HAVE_AN_X_AND_Y_SCALE = 0x0040
+OVERLAP_COMPOUND = 0x0400
+OVERLAP_SIMPLE = 0x40
+
+# PUSHB[0] 0, MDAP[1]: round the first point to the grid. A hinting rasterizer would
+# move it, the checker should see the outline as drawn.
+INSTRUCTIONS = bytes([0xB0, 0x00, 0x2F])
+
+
+def load_glyphs():
+    contents = plistlib.loads((UFO / "glyphs" / "contents.plist").read_bytes())
+    # .notdef has to be glyph 0
+    names = sorted(contents, key=lambda n: (n != ".notdef", n))
+    glyphs = []
+    for name in names:
+        root = ET.parse(UFO / "glyphs" / contents[name]).getroot()
+        advance = int(root.find("advance").get("width", 0))
+        contours = [
+            [
+                (int(p.get("x")), int(p.get("y")), p.get("type", "offcurve") != "offcurve")
+                for p in c.findall("point")
+            ]
+            for c in root.iter("contour")
+        ]
+        components = [
+            (
+                c.get("base"),
+                int(c.get("xOffset", 0)),
+                int(c.get("yOffset", 0)),
+                float(c.get("xScale", 1)),
+                float(c.get("yScale", 1)),
+            )
+            for c in root.iter("component")
+        ]
+        unicodes = [int(u.get("hex"), 16) for u in root.iter("unicode")]
+        # Keys and values alternate in a plist dict
+        lib = root.find("lib/dict")
+        lib = list(lib) if lib is not None else []
+        overlap = any(
+            k.text == "public.truetype.overlap" and v.tag == "true"
+            for k, v in zip(lib[::2], lib[1::2])
+        )
+        glyphs.append((name, advance, contours, components, unicodes, overlap))
+    return glyphs
+
+
+def f2dot14(v):
+    return struct.pack(">h", round(v * 16384))
+
+
+def simple_glyph(contours, overlap=False, instructions=b""):
+    points = [p for c in contours for p in c]
+    xs = [p[0] for p in points]
+    ys = [p[1] for p in points]
+    data = struct.pack(">hhhhh", len(contours), min(xs), min(ys), max(xs), max(ys))
+    end = -1
+    for c in contours:
+        end += len(c)
+        data += struct.pack(">H", end)
+    data += struct.pack(">H", len(instructions)) + instructions
+    # Unpacked: one flag per point, every coordinate a 16-bit delta
+    flags = [1 if on else 0 for _, _, on in points]
+    if overlap:
+        flags[0] |= OVERLAP_SIMPLE
+    data += bytes(flags)
+    prev = 0
+    for x in xs:
+        data += struct.pack(">h", x - prev)
+        prev = x
+    prev = 0
+    for y in ys:
+        data += struct.pack(">h", y - prev)
+        prev = y
+    return data
+
+
+def composite_glyph(components, gids, overlap):
+    data = struct.pack(">hhhhh", -1, 0, 0, 0, 0)
+    for i, (base, dx, dy, sx, sy) in enumerate(components):
+        flags = ARG_1_AND_2_ARE_WORDS | ARGS_ARE_XY_VALUES
+        if overlap and i == 0:
+            flags |= OVERLAP_COMPOUND
+        if i < len(components) - 1:
+            flags |= MORE_COMPONENTS
+        scaled = (sx, sy) != (1, 1)
+        if scaled:
+            flags |= WE_HAVE_AN_X_AND_Y_SCALE
+        data += struct.pack(">HHhh", flags, gids[base], dx, dy)
+        if scaled:
+            data += f2dot14(sx) + f2dot14(sy)
+    return data
+
+
+def cmap(glyphs):
+    # A Windows Unicode full repertoire subtable, format 12, a group per codepoint
+    groups = sorted((u, gid) for gid, g in enumerate(glyphs) for u in g[4])
+    subtable = struct.pack(">HHIII", 12, 0, 16 + 12 * len(groups), 0, len(groups))
+    subtable += b"".join(struct.pack(">III", u, u, gid) for u, gid in groups)
+    return struct.pack(">HHHHI", 0, 1, 3, 10, 12) + subtable
+
+
+def checksum(data):
+    data += b"\0" * (-len(data) % 4)
+    return sum(struct.unpack(f">{len(data) // 4}I", data)) & 0xFFFFFFFF
+
+
+def build():
+    glyphs = load_glyphs()
+    gids = {name: i for i, (name, *_) in enumerate(glyphs)}
+
+    glyf = b""
+    loca = [0]
+    for _, _, contours, components, _, overlap in glyphs:
+        if components:
+            glyf += composite_glyph(components, gids, overlap)
+        elif contours:
+            glyf += simple_glyph(contours, overlap, INSTRUCTIONS)
+        glyf += b"\0" * (-len(glyf) % 4)
+        loca.append(len(glyf))
+
+    num_glyphs = len(glyphs)
+    max_advance = max(g[1] for g in glyphs)
+    tables = {
+        b"cmap": cmap(glyphs),
+        b"head": struct.pack(
+            ">IIIIHHQQhhhhHHhhh",
+            0x00010000, 0x00010000, 0, 0x5F0F3CF5, 0, 1000, 0, 0,
+            0, 0, 0, 0, 0, 8, 2, 1, 0,
+        ),
+        b"hhea": struct.pack(
+            ">IhhhHhhhhhhhhhhhH",
+            0x00010000, 800, -200, 0, max_advance, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0,
+            num_glyphs,
+        ),
+        b"maxp": struct.pack(">IH", 0x00005000, num_glyphs),
+        b"hmtx": b"".join(struct.pack(">Hh", g[1], 0) for g in glyphs),
+        b"loca": b"".join(struct.pack(">I", o) for o in loca),
+        b"glyf": glyf,
+        b"post": struct.pack(">IIhhIIIII", 0x00020000, 0, 0, 0, 0, 0, 0, 0, 0)
+        + struct.pack(">H", num_glyphs)
+        # 0 is .notdef in the standard Macintosh set, everything else is custom
+        + b"".join(struct.pack(">H", 0 if i == 0 else 257 + i) for i in range(num_glyphs))
+        + b"".join(bytes([len(n)]) + n.encode() for n, *_ in glyphs[1:]),
+    }
+
+    num_tables = len(tables)
+    entry_selector = num_tables.bit_length() - 1
+    search_range = 16 * (1 << entry_selector)
+    font = struct.pack(
+        ">IHHHH", 0x00010000, num_tables, search_range, entry_selector,
+        num_tables * 16 - search_range,
+    )
+    offset = len(font) + 16 * num_tables
+    body = b""
+    for tag in sorted(tables):
+        data = tables[tag]
+        font += struct.pack(">4sIII", tag, checksum(data), offset + len(body), len(data))
+        body += data + b"\0" * (-len(data) % 4)
+    (HERE / "Overlaps.ttf").write_bytes(font + body)
+
+
+if __name__ == "__main__":
+    build()
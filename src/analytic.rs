@@ -0,0 +1,387 @@
+//! Finding which contours overlap from their geometry, rather than by rendering.
+
+use kurbo::{Affine, BezPath, PathEl, Point, Rect, Shape};
+
+use crate::{check::is_inside, glyph::contours, CheckResult, Glyph, Method, Overlap, OverlapKind};
+
+/// How closely, in font units, the polylines we intersect follow the curves.
+const FLATTEN_TOLERANCE: f64 = 0.25;
+
+/// How many points, along each side of the overlap's bounds, we sample winding at to
+/// estimate its area.
+const AREA_SAMPLES: usize = 64;
+
+/// How many horizontal lines across the glyph's height [`inked_area`] measures along.
+const INK_SCANLINES: usize = 256;
+
+impl Glyph {
+    /// Check the glyph as it is, no trimming, rounding, or reorienting, from the geometry
+    /// of its contours alone.
+    #[cfg_attr(feature = "raster", allow(dead_code))]
+    pub(crate) fn check_analytic(&self, outline_hash: String) -> CheckResult {
+        CheckResult {
+            name: self.name.clone(),
+            source: self.source.clone(),
+            instance: self.instance.clone(),
+            designspace: self.designspace.clone(),
+            method: Method::Analytic,
+            overlap: analytic_overlap(&self.bezpath),
+            warnings: self.warnings.clone(),
+            outline_hash,
+            advance: self.advance,
+            codepoints: self.codepoints.clone(),
+            scale: None,
+        }
+    }
+}
+
+/// Where the fill rules disagree about `path`, y-down, if anywhere, without rendering.
+///
+/// Each group of contours that overlap one another is a region. The bounds are where
+/// the contours involved overlap, and the area is estimated by sampling winding
+/// numbers within them.
+fn analytic_overlap(path: &BezPath) -> Option<Overlap> {
+    let pairs = overlapping_pairs(path);
+    if pairs.is_empty() {
+        return None;
+    }
+    let contours = contours(path);
+    let bbox = pairs
+        .iter()
+        .map(|(i, j)| {
+            contours[*i]
+                .bounding_box()
+                .intersect(contours[*j].bounding_box())
+        })
+        .reduce(|a, b| a.union(b))
+        .unwrap_or_default();
+    let hole = pairs.iter().any(|(i, j)| {
+        i != j
+            && (is_inside(&contours[*i], &contours[*j]) || is_inside(&contours[*j], &contours[*i]))
+    });
+
+    // Even, nonzero, winding is where evenodd leaves a gap nonzero fills
+    let (step_x, step_y) = (
+        bbox.width() / AREA_SAMPLES as f64,
+        bbox.height() / AREA_SAMPLES as f64,
+    );
+    let disagree = (0..AREA_SAMPLES)
+        .flat_map(|y| (0..AREA_SAMPLES).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            Point::new(
+                bbox.x0 + (x as f64 + 0.5) * step_x,
+                bbox.y0 + (y as f64 + 0.5) * step_y,
+            )
+        })
+        .filter(|p| {
+            let winding = path.winding(*p);
+            winding != 0 && winding % 2 == 0
+        })
+        .count();
+
+    Some(Overlap {
+        kind: if hole {
+            OverlapKind::Hole
+        } else {
+            OverlapKind::OverlappingFills
+        },
+        bbox: Affine::FLIP_Y.transform_rect_bbox(bbox),
+        area: disagree as f64 * step_x * step_y,
+        inked_area: inked_area(path),
+        regions: groups(&pairs, contours.len()),
+        outlines: Vec::new(),
+        contours: involved(&pairs),
+    })
+}
+
+/// How many connected groups `pairs` of contours, out of `count`, form.
+fn groups(pairs: &[(usize, usize)], count: usize) -> usize {
+    let mut group = (0..count).collect::<Vec<_>>();
+    fn root(group: &mut [usize], mut i: usize) -> usize {
+        while group[i] != i {
+            group[i] = group[group[i]];
+            i = group[i];
+        }
+        i
+    }
+    for (i, j) in pairs.iter() {
+        let (a, b) = (root(&mut group, *i), root(&mut group, *j));
+        group[a] = b;
+    }
+    let mut roots = pairs
+        .iter()
+        .map(|(i, _)| root(&mut group, *i))
+        .collect::<Vec<_>>();
+    roots.sort_unstable();
+    roots.dedup();
+    roots.len()
+}
+
+/// Indices, in outline order, of the contours whose interiors overlap in a way the
+/// fill rules disagree about.
+///
+/// That's pairs of contours wound the same way where either boundary enters the
+/// other's interior, which includes nesting, and contours that cross themselves. Contours wound opposite ways cancel
+/// out where they overlap so every fill rule agrees there.
+#[cfg_attr(not(feature = "raster"), allow(dead_code))]
+pub(crate) fn overlapping_contours(path: &BezPath) -> Vec<usize> {
+    involved(&overlapping_pairs(path))
+}
+
+/// Every contour in any of `pairs`, in outline order.
+fn involved(pairs: &[(usize, usize)]) -> Vec<usize> {
+    let mut involved = pairs.iter().flat_map(|(i, j)| [*i, *j]).collect::<Vec<_>>();
+    involved.sort_unstable();
+    involved.dedup();
+    involved
+}
+
+/// The pairs of contours, by index with the lower first, that [`overlapping_contours`]
+/// finds overlap. A contour that crosses itself is paired with itself.
+fn overlapping_pairs(path: &BezPath) -> Vec<(usize, usize)> {
+    let contours = contours(path);
+    let polylines = contours.iter().map(polyline).collect::<Vec<_>>();
+    let bounds = polylines.iter().map(|p| bounds(p)).collect::<Vec<_>>();
+    let clockwise = contours.iter().map(|c| c.area() < 0.0).collect::<Vec<_>>();
+
+    let mut pairs = Vec::new();
+    for i in 0..contours.len() {
+        if crosses(&polylines[i], &polylines[i]) {
+            pairs.push((i, i));
+        }
+        for j in i + 1..contours.len() {
+            if clockwise[i] != clockwise[j] || bounds[i].intersect(bounds[j]).area() <= 0.0 {
+                continue;
+            }
+            // Comparing vertices and midpoints catches edges that meet end on, or run
+            // along each other, which never properly cross
+            let enters = |a: usize, b: usize| {
+                polylines[a]
+                    .windows(2)
+                    .flat_map(|s| [s[0], s[0].midpoint(s[1])])
+                    .any(|p| contours[b].winding(p) != 0)
+            };
+            if crosses(&polylines[i], &polylines[j]) || enters(i, j) || enters(j, i) {
+                pairs.push((i, j));
+            }
+        }
+    }
+    pairs
+}
+
+/// Roughly how much area `path` fills with the nonzero rule, overlaps counted once.
+///
+/// Exact along each of [`INK_SCANLINES`] lines through the flattened outline, which
+/// stand for the strip of the glyph either side of them.
+pub(crate) fn inked_area(path: &BezPath) -> f64 {
+    let bbox = path.bounding_box();
+    let edges = contours(path)
+        .iter()
+        .flat_map(|c| {
+            polyline(c)
+                .windows(2)
+                .map(|s| (s[0], s[1]))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let step = bbox.height() / INK_SCANLINES as f64;
+    let mut crossings = Vec::new();
+    let mut area = 0.0;
+    for i in 0..INK_SCANLINES {
+        let y = bbox.y0 + (i as f64 + 0.5) * step;
+        crossings.clear();
+        for (a, b) in edges.iter() {
+            let (low, high, direction) = if a.y < b.y { (a, b, 1) } else { (b, a, -1) };
+            // Half open, so where two edges meet counts once
+            if low.y <= y && y < high.y {
+                let x = low.x + (y - low.y) / (high.y - low.y) * (high.x - low.x);
+                crossings.push((x, direction));
+            }
+        }
+        crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut winding = 0;
+        for pair in crossings.windows(2) {
+            winding += pair[0].1;
+            if winding != 0 {
+                area += (pair[1].0 - pair[0].0) * step;
+            }
+        }
+    }
+    area
+}
+
+/// The contour as a closed sequence of points, first == last.
+fn polyline(contour: &BezPath) -> Vec<Point> {
+    let mut points: Vec<Point> = Vec::new();
+    kurbo::flatten(contour, FLATTEN_TOLERANCE, |el| match el {
+        PathEl::MoveTo(p) | PathEl::LineTo(p) => points.push(p),
+        _ => (),
+    });
+    if let (Some(first), Some(last)) = (points.first().copied(), points.last()) {
+        if first != *last {
+            points.push(first);
+        }
+    }
+    points
+}
+
+fn bounds(points: &[Point]) -> Rect {
+    match points.first() {
+        Some(first) => points
+            .iter()
+            .fold(Rect::from_points(*first, *first), |r, p| r.union_pt(*p)),
+        None => Rect::ZERO,
+    }
+}
+
+/// Whether any segment of `a` properly crosses any segment of `b`.
+///
+/// Segments that merely touch, such as neighbours in the same polyline, don't count.
+fn crosses(a: &[Point], b: &[Point]) -> bool {
+    a.windows(2).any(|s| {
+        b.windows(2)
+            .any(|t| segments_cross((s[0], s[1]), (t[0], t[1])))
+    })
+}
+
+fn segments_cross((a0, a1): (Point, Point), (b0, b1): (Point, Point)) -> bool {
+    let orient = |p: Point, q: Point, r: Point| (q - p).cross(r - p);
+    let opposite = |x: f64, y: f64| (x > 0.0 && y < 0.0) || (x < 0.0 && y > 0.0);
+    opposite(orient(a0, a1, b0), orient(a0, a1, b1))
+        && opposite(orient(b0, b1, a0), orient(b0, b1, a1))
+}
+
+#[cfg(test)]
+mod tests {
+    use kurbo::BezPath;
+
+    use crate::OverlapKind;
+
+    use super::{analytic_overlap, inked_area, overlapping_contours};
+
+    #[test]
+    fn crossing_same_direction() {
+        let plus = BezPath::from_svg(
+            "M250,0 L350,0 L350,600 L250,600 Z M0,250 L600,250 L600,350 L0,350 Z M700,0 L800,0 L800,100 L700,100 Z",
+        )
+        .unwrap();
+        assert_eq!(vec![0, 1], overlapping_contours(&plus));
+    }
+
+    #[test]
+    fn ink_counted_once() {
+        // Horizontal edges fall between scanlines, so to within a percent or so
+        let roughly = |path: &str, expected: f64| {
+            let area = inked_area(&BezPath::from_svg(path).unwrap());
+            assert!((area - expected).abs() < expected / 100.0, "{path} {area}");
+        };
+        // The crossbar adds 400x100 to the stem's 100x600, the middle is already inked
+        roughly(
+            "M250,0 L350,0 L350,600 L250,600 Z M0,250 L600,250 L600,350 L0,350 Z",
+            110000.0,
+        );
+        // A counter wound against its outline isn't inked
+        roughly(
+            "M0,0 L300,0 L300,300 L0,300 Z M100,100 L100,200 L200,200 L200,100 Z",
+            80000.0,
+        );
+        assert_eq!(0.0, inked_area(&BezPath::new()));
+    }
+
+    #[test]
+    fn crossing_opposite_directions_cancel() {
+        let plus = BezPath::from_svg(
+            "M250,0 L350,0 L350,600 L250,600 Z M0,250 L0,350 L600,350 L600,250 Z",
+        )
+        .unwrap();
+        assert!(overlapping_contours(&plus).is_empty());
+    }
+
+    #[test]
+    fn abutting_edges() {
+        let bars =
+            BezPath::from_svg("M0,0 L250,0 L250,700 L0,700 Z M150,0 L400,0 L400,700 L150,700 Z")
+                .unwrap();
+        assert_eq!(vec![0, 1], overlapping_contours(&bars));
+    }
+
+    #[test]
+    fn nested_same_direction() {
+        let o = BezPath::from_svg(
+            "M0,0 L500,0 L500,500 L0,500 Z M100,100 L400,100 L400,400 L100,400 Z",
+        )
+        .unwrap();
+        assert_eq!(vec![0, 1], overlapping_contours(&o));
+    }
+
+    #[test]
+    fn analytic_verdicts() {
+        // y-down, like a loaded glyph
+        let plus = BezPath::from_svg(
+            "M250,0 L350,0 L350,600 L250,600 Z M0,250 L600,250 L600,350 L0,350 Z M700,0 L800,0 L800,100 L700,100 Z",
+        )
+        .unwrap();
+        let overlap = analytic_overlap(&plus).unwrap();
+        assert_eq!(OverlapKind::OverlappingFills, overlap.kind);
+        assert_eq!((1, vec![0, 1]), (overlap.regions, overlap.contours));
+        assert_eq!(
+            (250.0, -350.0, 350.0, -250.0),
+            (
+                overlap.bbox.x0,
+                overlap.bbox.y0,
+                overlap.bbox.x1,
+                overlap.bbox.y1
+            )
+        );
+        assert_eq!(10000.0, overlap.area);
+
+        let o = BezPath::from_svg(
+            "M0,0 L500,0 L500,500 L0,500 Z M100,100 L400,100 L400,400 L100,400 Z",
+        )
+        .unwrap();
+        let overlap = analytic_overlap(&o).unwrap();
+        assert_eq!(OverlapKind::Hole, overlap.kind);
+        assert_eq!(90000.0, overlap.area);
+
+        let square = BezPath::from_svg("M0,0 L100,0 L100,100 L0,100 Z").unwrap();
+        assert!(analytic_overlap(&square).is_none());
+    }
+
+    /// How often the analytic verdict matches the raster one over every bundled font,
+    /// masters and instances. `cargo test agrees_with_raster -- --nocapture` prints the
+    /// agreement rate and the glyphs they disagree on.
+    #[cfg(feature = "raster")]
+    #[test]
+    fn agrees_with_raster() {
+        let testdata = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/testdata");
+        let config = crate::Config::default();
+        let mut checked = 0;
+        let mut disagree = Vec::new();
+        for file in [
+            "Overlaps.ufo",
+            "Marks.ufo",
+            "Overlaps.ttf",
+            "Bars/Bars.designspace",
+            "Bars.ttf",
+        ] {
+            for glyph in crate::Glyph::from_file(testdata.join(file)) {
+                let glyph = glyph.unwrap();
+                let raster = glyph.check(&config).unwrap().overlap.is_some();
+                let analytic = glyph.check_analytic(String::new()).overlap.is_some();
+                checked += 1;
+                if raster != analytic {
+                    disagree.push(format!(
+                        "{} raster {raster} analytic {analytic}",
+                        glyph.describe()
+                    ));
+                }
+            }
+        }
+        let agreement = 100.0 * (checked - disagree.len()) as f64 / checked as f64;
+        eprintln!("Analytic agrees with raster on {agreement:.1}% of {checked} glyphs");
+        for glyph in disagree.iter() {
+            eprintln!("  {glyph}");
+        }
+        assert!(disagree.is_empty());
+    }
+}
@@ -0,0 +1,71 @@
+//! Recording verdicts in the source.
+
+use crate::{CheckResult, Error};
+
+/// Where `--annotate` records the verdict in each glyph's lib.
+pub const NEEDS_FLAG_LIB_KEY: &str = "com.rsheeter.glyph_overlaps.needsFlag";
+
+/// How annotating a glyph would change its lib.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnnotationChange {
+    /// The value recorded now, None if there isn't one
+    pub before: Option<bool>,
+    /// The verdict that would be recorded
+    pub after: bool,
+}
+
+/// What [`annotate`] would change for `result`, None if the glif already records
+/// the verdict. Nothing is written.
+pub fn annotation_change(result: &CheckResult) -> Result<Option<AnnotationChange>, Error> {
+    let file = &result.source;
+    let glif = norad::Glyph::load(file).map_err(|e| Error::Annotate {
+        file: file.clone(),
+        reason: e.to_string(),
+    })?;
+    let before = glif
+        .lib
+        .get(NEEDS_FLAG_LIB_KEY)
+        .and_then(plist::Value::as_boolean);
+    let after = result.overlap.is_some();
+    Ok((before != Some(after)).then_some(AnnotationChange { before, after }))
+}
+
+/// Record the verdict in the lib of the glif the result came from, leaving the outline be.
+pub fn annotate(result: &CheckResult) -> Result<(), Error> {
+    let file = &result.source;
+    let fail = |reason: String| Error::Annotate {
+        file: file.clone(),
+        reason,
+    };
+    let mut glif = norad::Glyph::load(file).map_err(|e| fail(e.to_string()))?;
+    glif.lib.insert(
+        NEEDS_FLAG_LIB_KEY.to_string(),
+        plist::Value::Boolean(result.overlap.is_some()),
+    );
+    let xml = glif.encode_xml().map_err(|e| fail(e.to_string()))?;
+    std::fs::write(file, xml).map_err(|e| fail(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{Config, Glyph};
+
+    use super::{annotation_change, AnnotationChange};
+
+    #[test]
+    fn unannotated_glyph_would_change() {
+        let file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/testdata/Overlaps.ufo/glyphs/plus.glif");
+        let glyph = Glyph::from_file(file).pop().unwrap().unwrap();
+        let result = glyph.check(&Config::default()).unwrap();
+        assert_eq!(
+            Some(AnnotationChange {
+                before: None,
+                after: true
+            }),
+            annotation_change(&result).unwrap()
+        );
+    }
+}
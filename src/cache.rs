@@ -0,0 +1,257 @@
+//! Remembering verdicts between runs so unchanged glyphs aren't checked again.
+//!
+//! Entries are keyed on a hash of the outline together with everything in
+//! [`Config`] that can change the verdict, and the version of this crate, so
+//! editing a glyph, changing settings, or upgrading all miss the cache.
+
+use std::{collections::HashMap, path::Path};
+
+use kurbo::{BezPath, PathEl, Rect};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    analytic::inked_area, CheckResult, Config, Glyph, Method, Overlap, OverlapKind, Warning,
+};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Cache {
+    entries: HashMap<String, Verdict>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Verdict {
+    method: Method,
+    /// Present if the glyph needs the overlap flag
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    overlap: Option<CachedOverlap>,
+    /// Normalizing contour directions changed the verdict
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    direction_dependent: bool,
+    /// The anti-aliased second opinion disagreed with the verdict
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    methods_disagree: bool,
+    /// x, y pixels per font unit the verdict was rendered at, if it was rendered.
+    /// Missing from older caches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scale: Option<[f64; 2]>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedOverlap {
+    kind: OverlapKind,
+    /// x_min, y_min, x_max, y_max in font units, y-up
+    bbox: [f64; 4],
+    contours: Vec<usize>,
+    area: f64,
+    /// Missing from older caches
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    inked_area: Option<f64>,
+    regions: usize,
+    /// SVG path data for each region, font units, y-up
+    outlines: Vec<String>,
+}
+
+impl Cache {
+    /// Load the cache at `file`. A missing or unreadable cache is just empty.
+    pub fn load(file: &Path) -> Self {
+        let Ok(raw) = std::fs::read_to_string(file) else {
+            return Self::default();
+        };
+        serde_json::from_str(&raw).unwrap_or_else(|e| {
+            eprintln!("WARNING: ignoring unreadable cache {file:?}: {e}");
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, file: &Path) -> std::io::Result<()> {
+        let json = serde_json::to_string(self)
+            .unwrap_or_else(|e| panic!("Unable to serialize cache: {e}"));
+        std::fs::write(file, json)
+    }
+
+    /// The result of a previous check of the same outline with the same config.
+    pub fn get(&self, glyph: &Glyph, config: &Config) -> Option<CheckResult> {
+        let verdict = self.entries.get(&key(glyph, config))?;
+        // Without the scale we can't say how to reproduce the verdict, check again
+        if verdict.method == Method::Raster && verdict.scale.is_none() {
+            return None;
+        }
+        let mut warnings = glyph.warnings.clone();
+        if verdict.direction_dependent {
+            warnings.push(Warning::DirectionDependent {
+                file: glyph.source.clone(),
+                glyph: glyph.name.clone(),
+                as_drawn: verdict.overlap.is_none(),
+            });
+        }
+        if verdict.methods_disagree {
+            warnings.push(Warning::MethodsDisagree {
+                file: glyph.source.clone(),
+                glyph: glyph.name.clone(),
+                anti_aliased: verdict.overlap.is_none(),
+            });
+        }
+        warnings.extend(glyph.contour_warnings(config));
+        Some(CheckResult {
+            name: glyph.name.clone(),
+            source: glyph.source.clone(),
+            instance: glyph.instance.clone(),
+            designspace: glyph.designspace.clone(),
+            method: verdict.method,
+            overlap: verdict.overlap.as_ref().map(|o| Overlap {
+                kind: o.kind,
+                bbox: Rect::new(o.bbox[0], o.bbox[1], o.bbox[2], o.bbox[3]),
+                contours: o.contours.clone(),
+                area: o.area,
+                inked_area: o.inked_area.unwrap_or_else(|| inked_area(&glyph.bezpath)),
+                regions: o.regions,
+                outlines: o
+                    .outlines
+                    .iter()
+                    .map(|svg| BezPath::from_svg(svg).unwrap_or_default())
+                    .collect(),
+            }),
+            warnings,
+            outline_hash: glyph.outline_hash(),
+            advance: glyph.advance,
+            codepoints: glyph.codepoints.clone(),
+            scale: verdict.scale.map(|[x, y]| (x, y)),
+        })
+    }
+
+    pub fn insert(&mut self, glyph: &Glyph, config: &Config, result: &CheckResult) {
+        self.entries.insert(
+            key(glyph, config),
+            Verdict {
+                method: result.method,
+                overlap: result.overlap.as_ref().map(|o| CachedOverlap {
+                    kind: o.kind,
+                    bbox: [o.bbox.x0, o.bbox.y0, o.bbox.x1, o.bbox.y1],
+                    contours: o.contours.clone(),
+                    area: o.area,
+                    inked_area: Some(o.inked_area),
+                    regions: o.regions,
+                    outlines: o.outlines.iter().map(BezPath::to_svg).collect(),
+                }),
+                direction_dependent: result
+                    .warnings
+                    .iter()
+                    .any(|w| matches!(w, Warning::DirectionDependent { .. })),
+                methods_disagree: result
+                    .warnings
+                    .iter()
+                    .any(|w| matches!(w, Warning::MethodsDisagree { .. })),
+                scale: result.scale.map(|(x, y)| [x, y]),
+            },
+        );
+    }
+}
+
+/// Hex FNV-1a of the outline and config. Hand rolled because std's hashers
+/// aren't guaranteed to be stable between releases, and the cache outlives a build.
+fn key(glyph: &Glyph, config: &Config) -> String {
+    let mut hash = Fnv1a::default();
+    hash.write(env!("CARGO_PKG_VERSION").as_bytes());
+    // Raster and analytic builds may share a cache but not verdicts
+    hash.write(&[cfg!(feature = "raster") as u8]);
+    hash.write_f64(config.scale_x);
+    hash.write_f64(config.scale_y);
+    hash.write(&config.max_pixmap_size.to_le_bytes());
+    hash.write(&[config.round_coords as u8]);
+    hash.write(&config.pixel_threshold.to_le_bytes());
+    hash.write_f64(config.retry_scale);
+    hash.write_f64(config.margin);
+    hash.write(&[config.normalize_directions as u8]);
+    hash.write(&config.tile_size.unwrap_or(0).to_le_bytes());
+    hash.write_f64(config.min_contour_area);
+    hash.write(&[config.anti_aliased_second_opinion as u8]);
+    hash.write(&[config.include_advance as u8]);
+    hash.write(&config.edge_tolerance.to_le_bytes());
+    hash.write(&[config.rasterizer as u8]);
+    // A small em renders larger
+    hash.write_f64(glyph.units_per_em.unwrap_or(0.0));
+    hash.write_outline(&glyph.bezpath);
+    format!("{:016x}", hash.0)
+}
+
+/// Hex FNV-1a of just the outline, see [`Glyph::outline_hash`].
+pub(crate) fn outline_hash(path: &BezPath) -> String {
+    let mut hash = Fnv1a::default();
+    hash.write_outline(path);
+    format!("{:016x}", hash.0)
+}
+
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn write_f64(&mut self, v: f64) {
+        self.write(&v.to_le_bytes());
+    }
+
+    fn write_outline(&mut self, path: &BezPath) {
+        for el in path.elements() {
+            let (tag, points) = match el {
+                PathEl::MoveTo(p) => (b'M', vec![*p]),
+                PathEl::LineTo(p) => (b'L', vec![*p]),
+                PathEl::QuadTo(c, p) => (b'Q', vec![*c, *p]),
+                PathEl::CurveTo(c0, c1, p) => (b'C', vec![*c0, *c1, *p]),
+                PathEl::ClosePath => (b'Z', vec![]),
+            };
+            self.write(&[tag]);
+            // Adding 0 makes -0, which flipping y is prone to, plain 0
+            for p in points {
+                self.write_f64(p.x + 0.0);
+                self.write_f64(p.y + 0.0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{Config, Glyph};
+
+    use super::Cache;
+
+    fn plus() -> Glyph {
+        let file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/testdata/Overlaps.ufo/glyphs/plus.glif");
+        Glyph::from_file(file).pop().unwrap().unwrap()
+    }
+
+    #[test]
+    fn hit_only_with_same_config() {
+        let glyph = plus();
+        let config = Config::default();
+        let result = glyph.check(&config).unwrap();
+
+        let mut cache = Cache::default();
+        cache.insert(&glyph, &config, &result);
+
+        let cached = cache.get(&glyph, &config).unwrap();
+        assert_eq!(
+            result.overlap.map(|o| (o.kind, o.bbox)),
+            cached.overlap.map(|o| (o.kind, o.bbox))
+        );
+        let rescaled = Config {
+            scale_x: 2.0,
+            ..config
+        };
+        assert!(cache.get(&glyph, &rescaled).is_none());
+    }
+}
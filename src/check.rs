@@ -0,0 +1,906 @@
+//! Deciding whether a glyph needs the overlap flag.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use fontdrasil::types::GlyphName;
+use kurbo::{BezPath, PathEl, Point, Rect, Shape};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{glyph::contours, Error, Glyph, Warning};
+
+/// Why a glyph needs the overlap flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlapKind {
+    /// Separate filled shapes overlap, e.g. the crossbar of an A running into the stems
+    OverlappingFills,
+    /// A contour nested inside another is wound the same way, the classic backwards counter
+    Hole,
+}
+
+impl OverlapKind {
+    /// What this kind of overlap means and what to do about it, for someone new to overlaps.
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            OverlapKind::OverlappingFills => "separate shapes wound the same way are drawn over each other; remove the overlap or set the flag",
+            OverlapKind::Hole => "a contour inside another is wound the same way, so evenodd renders a hole nonzero fills; reverse it if it's meant to be a counter",
+        }
+    }
+}
+
+impl std::fmt::Display for OverlapKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OverlapKind::OverlappingFills => f.write_str("overlapping fills"),
+            OverlapKind::Hole => f.write_str("hole interaction"),
+        }
+    }
+}
+
+/// How the overlap was detected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Method {
+    /// Render with each fill rule and compare pixels
+    Raster,
+    /// Intersect the contours, for builds without the raster feature. Areas are
+    /// estimates and there are no region outlines.
+    Analytic,
+}
+
+/// What renders glyphs for [`Method::Raster`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rasterizer {
+    #[default]
+    TinySkia,
+    /// The system FreeType's monochrome rasterizer, to compare with what production
+    /// renderers see. Needs the freetype feature.
+    #[cfg(feature = "freetype")]
+    FreeType,
+}
+
+/// Where and why the fill rules disagree about a glyph.
+#[derive(Debug, Clone)]
+pub struct Overlap {
+    pub kind: OverlapKind,
+    /// Bounds of the discrepent pixels, in font units (y-up)
+    pub bbox: Rect,
+    /// Roughly how much area the fill rules disagree on, in square font units
+    pub area: f64,
+    /// Roughly how much area the glyph fills, overlaps counted once, in square font units
+    pub inked_area: f64,
+    /// How many separate places the fill rules disagree
+    pub regions: usize,
+    /// The outline of each of those places, traced around the discrepent pixels and
+    /// in font units (y-up), e.g. to draw them over the glyph. A region with holes in
+    /// has a subpath per hole, wound the other way.
+    pub outlines: Vec<BezPath>,
+    /// Indices of the contours involved, in outline order with components flattened in.
+    /// Found analytically, so may be empty for overlaps only a renderer sees.
+    pub contours: Vec<usize>,
+}
+
+impl Overlap {
+    /// [`Overlap::area`] as a percentage of [`Overlap::inked_area`]. A fraction of a
+    /// percent is likely a sliver, tens of percent shapes drawn over one another on purpose.
+    pub fn area_percent(&self) -> f64 {
+        if self.inked_area > 0.0 {
+            100.0 * self.area / self.inked_area
+        } else {
+            0.0
+        }
+    }
+}
+
+impl std::fmt::Display for Overlap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if !self.contours.is_empty() {
+            let contours = self
+                .contours
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>();
+            write!(f, ", contours {}", contours.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// The outcome of checking a single glyph.
+#[derive(Debug)]
+pub struct CheckResult {
+    pub name: GlyphName,
+    pub source: PathBuf,
+    /// The designspace instance checked, if the outline was interpolated
+    pub instance: Option<String>,
+    /// The designspace the glyph was loaded through, if any
+    pub designspace: Option<PathBuf>,
+    pub method: Method,
+    /// Present if the glyph needs the overlap flag
+    pub overlap: Option<Overlap>,
+    pub warnings: Vec<Warning>,
+    /// See [`Glyph::outline_hash`]
+    pub outline_hash: String,
+    /// See [`Glyph::advance`]
+    pub advance: Option<f64>,
+    /// See [`Glyph::codepoints`]
+    pub codepoints: Vec<char>,
+    /// The scale, x and y, of the renders that decided the verdict, finer than asked
+    /// for if it was a close call, coarser if the glyph had to fit
+    /// [`Config::max_pixmap_size`]. See [`Glyph::render_scale`]. None if nothing was
+    /// rendered.
+    pub scale: Option<(f64, f64)>,
+}
+
+impl CheckResult {
+    /// Identifies the glyph in messages: the source, the glyph name too if the source
+    /// holds more than one glyph, the instance if it was interpolated, and the
+    /// designspace if a master was loaded through one.
+    pub fn describe(&self) -> String {
+        describe(
+            &self.source,
+            &self.name,
+            self.instance.as_deref(),
+            self.designspace.as_deref(),
+        )
+    }
+
+    /// The codepoints as `U+` hex, space separated, e.g. `U+002B U+2795`. Empty if the
+    /// glyph has none.
+    pub fn codepoint_labels(&self) -> String {
+        self.codepoints
+            .iter()
+            .map(|c| format!("U+{:04X}", *c as u32))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+pub(crate) fn describe(
+    source: &Path,
+    name: &GlyphName,
+    instance: Option<&str>,
+    designspace: Option<&Path>,
+) -> String {
+    let mut description = format!("{source:?}");
+    if source.extension().and_then(|e| e.to_str()) != Some("glif") {
+        description.push_str(&format!(" {name}"));
+    }
+    if let Some(instance) = instance {
+        description.push_str(&format!(" at {instance}"));
+    }
+    // Instances already name the designspace as their source
+    if let Some(designspace) = designspace.filter(|d| *d != source) {
+        description.push_str(&format!(" in {designspace:?}"));
+    }
+    description
+}
+
+/// Knobs for how glyphs are rendered for comparison.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Pixels per font unit horizontally
+    pub scale_x: f64,
+    /// Pixels per font unit vertically
+    pub scale_y: f64,
+    /// The largest width or height, in pixels, we'll render at
+    pub max_pixmap_size: u32,
+    /// Snap coordinates to integers before checking, as compiling to a binary font does
+    pub round_coords: bool,
+    /// How many pixels the fill rules must disagree on before a glyph needs the flag.
+    /// Close calls are decided by area at a finer scale, so this is really an area in
+    /// pixels at `scale_x` by `scale_y`.
+    pub pixel_threshold: usize,
+    /// How much to multiply scale by to recheck glyphs whose discrepent pixel count is
+    /// close to the threshold. 1 to never recheck.
+    pub retry_scale: f64,
+    /// RGB to fill the glyph with. Only affects how debug images look, it's always opaque.
+    pub fill_color: (u8, u8, u8),
+    /// RGB to fill nonzero renders with instead, so they can be told from evenodd ones
+    /// at a glance. Renders are compared by coverage, so this only affects how they look.
+    pub nonzero_fill_color: Option<(u8, u8, u8)>,
+    /// Space left around the glyph's bounding box, as a fraction of its larger side
+    pub margin: f64,
+    /// Reorient contours, outers counter-clockwise and holes clockwise in font units,
+    /// before checking. A verdict that changes because of it gets a warning.
+    pub normalize_directions: bool,
+    /// Render and compare at most this many pixels square at a time, stopping as soon
+    /// as the verdict is certain, for glyphs too big or complex to render in one go.
+    /// The overlap then only describes the tiles rendered. None renders in one pass.
+    pub tile_size: Option<u32>,
+    /// Contours enclosing less than this area, in square font units, are dropped before
+    /// checking, e.g. specks left by auto-tracing. 0 keeps everything.
+    pub min_contour_area: f64,
+    /// Also compare anti-aliased renders of close calls, thresholding the difference in
+    /// coverage, and warn if that disagrees with the verdict. Aliased renders see hard
+    /// edges on the pixel grid that can tip a borderline glyph either way; the verdict
+    /// is still theirs.
+    pub anti_aliased_second_opinion: bool,
+    /// Widen the area rendered to take in the advance, from 0 to the advance width, so
+    /// overlaps near the sidebearings show in debug images
+    pub include_advance: bool,
+    /// Only write debug images for glyphs the fill rules disagree about at all, so
+    /// they're usable on a large font
+    pub only_differing_images: bool,
+    /// Write one debug image per glyph, evenodd, nonzero, and the difference side by
+    /// side, rather than three
+    pub side_by_side_images: bool,
+    /// Also write the coverage of each render, one byte per pixel, as a NumPy .npy
+    /// alongside the debug images, to analyze outside the tool
+    pub raw_buffers: bool,
+    /// Where debug images, and raw buffers, are written, if anywhere. It must exist.
+    pub image_dir: Option<PathBuf>,
+    /// Erode the pixels the fill rules disagree on by this many pixels before counting
+    /// them, so strips that thin, e.g. along edges contours share, don't count
+    pub edge_tolerance: u32,
+    /// What renders the glyph. Only tiny_skia renders in tiles or gives anti-aliased
+    /// second opinions, other rasterizers render in one pass.
+    pub rasterizer: Rasterizer,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            scale_x: 1.0,
+            scale_y: 1.0,
+            max_pixmap_size: 4096,
+            round_coords: false,
+            pixel_threshold: 1,
+            retry_scale: 4.0,
+            fill_color: (128, 128, 128),
+            nonzero_fill_color: None,
+            margin: 0.1,
+            normalize_directions: false,
+            tile_size: None,
+            min_contour_area: 0.0,
+            anti_aliased_second_opinion: false,
+            only_differing_images: false,
+            side_by_side_images: false,
+            raw_buffers: false,
+            image_dir: None,
+            include_advance: false,
+            edge_tolerance: 0,
+            rasterizer: Rasterizer::TinySkia,
+        }
+    }
+}
+
+/// Check `glyphs`, e.g. from [`Glyph::stream`], handing each result to `handler` as
+/// soon as it's ready rather than collecting them.
+///
+/// Glyphs are checked in parallel a few at a time, as many as rayon has threads, and
+/// `handler` is called on this thread in the order `glyphs` came in. Glyphs that
+/// failed to load are handed over as the error they are. Nothing more is loaded while
+/// `handler` runs, so a slow handler slows checking rather than results piling up.
+pub fn check_with(
+    glyphs: impl IntoIterator<Item = Result<Glyph, Error>>,
+    config: &Config,
+    mut handler: impl FnMut(Result<CheckResult, Error>),
+) {
+    let batch_size = rayon::current_num_threads().max(1);
+    let mut glyphs = glyphs.into_iter();
+    loop {
+        let batch = glyphs.by_ref().take(batch_size).collect::<Vec<_>>();
+        if batch.is_empty() {
+            return;
+        }
+        let results = batch
+            .into_par_iter()
+            .map(|g| g.and_then(|g| g.check(config)))
+            .collect::<Vec<_>>();
+        results.into_iter().for_each(&mut handler);
+    }
+}
+
+/// Whether each glyph in `file` needs the overlap flag, by name, for callers that don't
+/// need to know why.
+///
+/// A glyph loaded more than once, e.g. from each master of a designspace, needs the flag
+/// if any of them do. The first glyph that can't be loaded or checked is an error.
+pub fn needs_overlap_flag(
+    file: impl AsRef<Path>,
+    config: &Config,
+) -> Result<HashMap<GlyphName, bool>, Error> {
+    let mut verdicts = HashMap::new();
+    let mut error = None;
+    check_with(Glyph::stream([file]), config, |result| match result {
+        Ok(result) => *verdicts.entry(result.name).or_default() |= result.overlap.is_some(),
+        Err(e) => {
+            error.get_or_insert(e);
+        }
+    });
+    match error {
+        Some(e) => Err(e),
+        None => Ok(verdicts),
+    }
+}
+
+/// Check contours already in hand, e.g. mid-way through a norad pipeline, without
+/// building a glyph or writing a file. Font units, y-up, as in a UFO.
+///
+/// Components aren't contours, flatten them in first if they should be checked. The
+/// result, and any error, say the contours came from [`crate::MEMORY_SOURCE`].
+pub fn check_contours(
+    contours: &[norad::Contour],
+    name: GlyphName,
+    config: &Config,
+) -> Result<CheckResult, Error> {
+    Glyph::from_contours(Path::new(crate::MEMORY_SOURCE), name, contours)?.check(config)
+}
+
+impl Glyph {
+    /// Check whether the glyph needs the overlap flag, and if so why.
+    pub fn check(&self, config: &Config) -> Result<CheckResult, Error> {
+        let trimmed = self.without_small_contours(config.min_contour_area);
+        let kept = trimmed.as_ref().map_or(self, |(g, _)| g);
+        let rounded;
+        let glyph = if config.round_coords {
+            rounded = kept.rounded();
+            &rounded
+        } else {
+            kept
+        };
+        // Of the outline as loaded, rounding and reorienting are part of checking
+        let outline_hash = self.outline_hash();
+        if !config.normalize_directions {
+            let mut result = glyph.check_as_is(config, outline_hash)?;
+            result.warnings.extend(self.contour_warnings(config));
+            return Ok(result);
+        }
+
+        let mut result = glyph.normalized().check_as_is(config, outline_hash)?;
+        let as_drawn = glyph.check_as_is(config, String::new())?.overlap.is_some();
+        if as_drawn != result.overlap.is_some() {
+            result.warnings.push(Warning::DirectionDependent {
+                file: self.source.clone(),
+                glyph: self.name.clone(),
+                as_drawn,
+            });
+        }
+        result.warnings.extend(self.contour_warnings(config));
+        Ok(result)
+    }
+
+    /// Warnings about contours too small to matter: those dropped for being under
+    /// [`Config::min_contour_area`] and those too small to see among the rest.
+    pub(crate) fn contour_warnings(&self, config: &Config) -> Vec<Warning> {
+        let Some((kept, dropped)) = self.without_small_contours(config.min_contour_area) else {
+            return self.sub_pixel_contours(config).into_iter().collect();
+        };
+        let mut warnings = vec![Warning::SmallContourDropped {
+            file: self.source.clone(),
+            glyph: self.name.clone(),
+            count: dropped,
+            min_area: config.min_contour_area,
+        }];
+        warnings.extend(kept.sub_pixel_contours(config));
+        warnings
+    }
+
+    /// The glyph without contours enclosing less than `min_area` square font units,
+    /// and how many that dropped. None if none were.
+    fn without_small_contours(&self, min_area: f64) -> Option<(Glyph, usize)> {
+        if min_area <= 0.0 {
+            return None;
+        }
+        let (kept, dropped): (Vec<_>, Vec<_>) = contours(&self.bezpath)
+            .into_iter()
+            .partition(|c| c.area().abs() >= min_area);
+        if dropped.is_empty() {
+            return None;
+        }
+        let bezpath = kept.iter().flat_map(|c| c.iter()).collect();
+        Some((self.with_bezpath(bezpath), dropped.len()))
+    }
+
+    /// Contours too small to cover a pixel center at the scale we render at, so the
+    /// raster can't see them overlap anything.
+    fn sub_pixel_contours(&self, config: &Config) -> Option<Warning> {
+        let px_per_unit = self.render_transform(config).0.determinant().abs();
+        let count = contours(&self.bezpath)
+            .iter()
+            .filter(|c| c.area().abs() * px_per_unit < 1.0)
+            .count();
+        (count > 0).then(|| Warning::SubPixelContour {
+            file: self.source.clone(),
+            glyph: self.name.clone(),
+            count,
+        })
+    }
+
+    #[cfg(feature = "raster")]
+    fn check_as_is(&self, config: &Config, outline_hash: String) -> Result<CheckResult, Error> {
+        self.check_raster(config, outline_hash)
+    }
+
+    #[cfg(not(feature = "raster"))]
+    fn check_as_is(&self, _: &Config, outline_hash: String) -> Result<CheckResult, Error> {
+        Ok(self.check_analytic(outline_hash))
+    }
+
+    /// The glyph on the integer grid, halves rounded up like fontTools' otRound.
+    fn rounded(&self) -> Glyph {
+        // Round in font units, we're y-down
+        let round = |p: Point| Point::new((p.x + 0.5).floor(), -(0.5 - p.y).floor());
+        let bezpath = self
+            .bezpath
+            .elements()
+            .iter()
+            .map(|el| match *el {
+                PathEl::MoveTo(p) => PathEl::MoveTo(round(p)),
+                PathEl::LineTo(p) => PathEl::LineTo(round(p)),
+                PathEl::QuadTo(c, p) => PathEl::QuadTo(round(c), round(p)),
+                PathEl::CurveTo(c0, c1, p) => PathEl::CurveTo(round(c0), round(c1), round(p)),
+                PathEl::ClosePath => PathEl::ClosePath,
+            })
+            .collect();
+        self.with_bezpath(bezpath)
+    }
+
+    /// The glyph with outer contours counter-clockwise and holes clockwise, in font units.
+    ///
+    /// A contour inside an odd number of others is a hole. Contour order is kept.
+    fn normalized(&self) -> Glyph {
+        let contours = contours(&self.bezpath);
+        let mut bezpath = BezPath::new();
+        for (i, contour) in contours.iter().enumerate() {
+            let depth = contours
+                .iter()
+                .enumerate()
+                .filter(|(j, other)| i != *j && is_inside(contour, other))
+                .count();
+            // We're y-down, so counter-clockwise in font units has negative area
+            let counter_clockwise = contour.area() < 0.0;
+            if counter_clockwise != (depth % 2 == 0) {
+                bezpath.extend(contour.reverse_subpaths());
+            } else {
+                bezpath.extend(contour.iter());
+            }
+        }
+        self.with_bezpath(bezpath)
+    }
+
+    /// The same glyph with another outline, y-down.
+    pub(crate) fn with_bezpath(&self, bezpath: BezPath) -> Glyph {
+        Glyph {
+            name: self.name.clone(),
+            source: self.source.clone(),
+            bezpath,
+            instance: self.instance.clone(),
+            designspace: self.designspace.clone(),
+            advance: self.advance,
+            codepoints: self.codepoints.clone(),
+            overlap_flag: self.overlap_flag,
+            components: self.components.clone(),
+            italic_angle: self.italic_angle,
+            units_per_em: self.units_per_em,
+            warnings: self.warnings.clone(),
+        }
+    }
+}
+
+/// Whether every on-curve point of `inner` is inside `outer`.
+pub(crate) fn is_inside(inner: &BezPath, outer: &BezPath) -> bool {
+    inner
+        .elements()
+        .iter()
+        .filter_map(|el| el.end_point())
+        .all(|p| outer.winding(p) != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use fontdrasil::types::GlyphName;
+    use kurbo::{Affine, BezPath};
+
+    use crate::{CheckResult, Config, Glyph, OverlapKind, Warning};
+
+    fn testdata(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/testdata")
+            .join(name)
+    }
+
+    fn check(file: &str, glyph: &str) -> CheckResult {
+        check_with(file, glyph, &Config::default())
+    }
+
+    fn check_with(file: &str, glyph: &str, config: &Config) -> CheckResult {
+        Glyph::from_file(testdata(file))
+            .into_iter()
+            .map(|g| g.unwrap())
+            .find(|g| g.name.as_str() == glyph)
+            .unwrap_or_else(|| panic!("No {glyph} in {file}"))
+            .check(config)
+            .unwrap()
+    }
+
+    #[test]
+    fn lone_points_are_clean() {
+        let bezpath = BezPath::from_svg("M0,0 M100,100").unwrap();
+        let glyph = Glyph::new(GlyphName::new("dots"), Path::new("dots.json"), bezpath).unwrap();
+        assert!(glyph.check(&Config::default()).unwrap().overlap.is_none());
+    }
+
+    #[test]
+    fn notdef_is_checked() {
+        assert!(check("Overlaps.ufo", ".notdef").overlap.is_none());
+    }
+
+    #[test]
+    fn component_only_glyph_inherits_overlap() {
+        let result = check("Overlaps.ufo", "plus.comp");
+        assert_eq!(
+            Some(OverlapKind::OverlappingFills),
+            result.overlap.map(|o| o.kind)
+        );
+    }
+
+    #[test]
+    fn overlap_from_rounding() {
+        // The tip of the kite is just outside the triangle until it's rounded
+        let mut bezpath =
+            BezPath::from_svg("M0,0 L1000,0 L0,999 Z M500.4,499.3 L900,110 L700,700 L100,910 Z")
+                .unwrap();
+        bezpath.apply_affine(Affine::FLIP_Y);
+        let glyph = Glyph::new(GlyphName::new("kite"), Path::new("kite.json"), bezpath).unwrap();
+        // The sliver is too thin to cover pixel centers at 1:1
+        let config = Config {
+            scale_x: 2.0,
+            scale_y: 2.0,
+            ..Default::default()
+        };
+        assert!(glyph.check(&config).unwrap().overlap.is_none());
+        let rounded = Config {
+            round_coords: true,
+            ..config
+        };
+        assert!(glyph.check(&rounded).unwrap().overlap.is_some());
+    }
+
+    #[test]
+    #[cfg(feature = "raster")]
+    fn threshold_decided_at_finer_scale() {
+        // plus overlaps over 100x100 units, close enough to either threshold to recheck
+        let plus = Glyph::from_file(testdata("Overlaps.ufo/glyphs/plus.glif"))
+            .pop()
+            .unwrap()
+            .unwrap();
+        let with_threshold = |pixel_threshold| Config {
+            pixel_threshold,
+            ..Default::default()
+        };
+        assert!(plus.check(&with_threshold(5000)).unwrap().overlap.is_some());
+        assert!(plus
+            .check(&with_threshold(20000))
+            .unwrap()
+            .overlap
+            .is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "raster")]
+    fn separate_regions_and_area() {
+        let mut bezpath = BezPath::from_svg(
+            "M250,0 L350,0 L350,600 L250,600 Z M0,250 L600,250 L600,350 L0,350 Z \
+             M1250,0 L1350,0 L1350,600 L1250,600 Z M1000,250 L1600,250 L1600,350 L1000,350 Z",
+        )
+        .unwrap();
+        bezpath.apply_affine(Affine::FLIP_Y);
+        let glyph = Glyph::new(
+            GlyphName::new("plusplus"),
+            Path::new("plusplus.json"),
+            bezpath,
+        )
+        .unwrap();
+        let overlap = glyph.check(&Config::default()).unwrap().overlap.unwrap();
+        assert_eq!((2, 20000.0), (overlap.regions, overlap.area));
+    }
+
+    #[test]
+    #[cfg(feature = "raster")]
+    fn thin_strips_within_edge_tolerance() {
+        // Bars overlapping by 1 unit, and by 10
+        let glyph = |overlap: f64| {
+            let mut bezpath = BezPath::from_svg("M0,0 L100,0 L100,100 L0,100 Z").unwrap();
+            let mut right = bezpath.clone();
+            right.apply_affine(Affine::translate((100.0 - overlap, 0.0)));
+            bezpath.extend(right);
+            Glyph::new(GlyphName::new("bars"), Path::new("bars.json"), bezpath).unwrap()
+        };
+        let tolerant = Config {
+            edge_tolerance: 1,
+            retry_scale: 1.0,
+            ..Default::default()
+        };
+        assert!(glyph(1.0)
+            .check(&Config::default())
+            .unwrap()
+            .overlap
+            .is_some());
+        assert!(glyph(1.0).check(&tolerant).unwrap().overlap.is_none());
+        let overlap = glyph(10.0).check(&tolerant).unwrap().overlap.unwrap();
+        // Eroded a pixel all round
+        assert_eq!(8.0 * 98.0, overlap.area);
+    }
+
+    #[test]
+    fn tiling_keeps_verdicts() {
+        let tiled = Config {
+            tile_size: Some(64),
+            ..Default::default()
+        };
+        for glyph in Glyph::from_file(testdata("Overlaps.ufo")) {
+            let glyph = glyph.unwrap();
+            assert_eq!(
+                glyph.check(&Config::default()).unwrap().overlap.is_some(),
+                glyph.check(&tiled).unwrap().overlap.is_some(),
+                "{}",
+                glyph.name()
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "raster")]
+    fn region_outlines() {
+        use kurbo::Shape;
+
+        let overlap = check("Overlaps.ufo", "plus").overlap.unwrap();
+        let [outline] = overlap.outlines.as_slice() else {
+            panic!("Expected one outline, got {:?}", overlap.outlines);
+        };
+        // The square where the bars cross, traced in font units
+        let corners = outline.elements().iter().filter_map(|e| e.end_point());
+        assert_eq!(4, corners.count());
+        assert_eq!(10000.0, outline.area().abs());
+        assert_eq!(overlap.bbox, outline.bounding_box());
+    }
+
+    #[test]
+    fn normalizing_directions() {
+        let config = Config {
+            normalize_directions: true,
+            ..Default::default()
+        };
+        // The counter is wound the same way as the outside
+        let o = check_with("Overlaps.ufo", "o", &config);
+        assert!(o.overlap.is_none());
+        assert!(matches!(
+            o.warnings.as_slice(),
+            [Warning::DirectionDependent { as_drawn: true, .. }]
+        ));
+        // Overlapping outers still overlap whichever way they're wound
+        let plus = check_with("Overlaps.ufo", "plus", &config);
+        assert!(plus.overlap.is_some());
+        assert!(plus.warnings.is_empty());
+    }
+
+    #[test]
+    fn sub_pixel_contour() {
+        // A speck on the crossbar, big enough to see at 4x
+        let mut bezpath = BezPath::from_svg(
+            "M0,0 L600,0 L600,100 L0,100 Z M300,50 L300.5,50 L300.5,50.5 L300,50.5 Z",
+        )
+        .unwrap();
+        bezpath.apply_affine(Affine::FLIP_Y);
+        let glyph = Glyph::new(GlyphName::new("speck"), Path::new("speck.json"), bezpath).unwrap();
+        let warnings = |scale| {
+            let config = Config {
+                scale_x: scale,
+                scale_y: scale,
+                ..Default::default()
+            };
+            glyph.check(&config).unwrap().warnings
+        };
+        assert!(matches!(
+            warnings(1.0).as_slice(),
+            [Warning::SubPixelContour { count: 1, .. }]
+        ));
+        assert!(warnings(4.0).is_empty());
+    }
+
+    #[test]
+    fn small_contours_dropped() {
+        // A speck overlapping the crossbar
+        let mut bezpath =
+            BezPath::from_svg("M0,0 L600,0 L600,100 L0,100 Z M300,50 L305,50 L305,55 L300,55 Z")
+                .unwrap();
+        bezpath.apply_affine(Affine::FLIP_Y);
+        let glyph = Glyph::new(GlyphName::new("speck"), Path::new("speck.json"), bezpath).unwrap();
+        assert!(glyph.check(&Config::default()).unwrap().overlap.is_some());
+
+        let config = Config {
+            min_contour_area: 100.0,
+            ..Default::default()
+        };
+        let result = glyph.check(&config).unwrap();
+        assert!(result.overlap.is_none());
+        assert!(matches!(
+            result.warnings.as_slice(),
+            [Warning::SmallContourDropped { count: 1, .. }]
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "raster")]
+    fn anti_aliased_second_opinion() {
+        // Bars overlapping by 0.6 units, so aliased every pixel in the column sees
+        // overlap but anti-aliased each is only partly covered
+        let mut bezpath =
+            BezPath::from_svg("M0,0 L10.6,0 L10.6,100 L0,100 Z M10,0 L20,0 L20,100 L10,100 Z")
+                .unwrap();
+        bezpath.apply_affine(Affine::FLIP_Y);
+        let glyph = Glyph::new(GlyphName::new("bars"), Path::new("bars.json"), bezpath).unwrap();
+        let config = Config {
+            pixel_threshold: 80,
+            retry_scale: 1.0,
+            margin: 0.0,
+            ..Default::default()
+        };
+        let result = glyph.check(&config).unwrap();
+        assert!(result.overlap.is_some());
+        assert!(result.warnings.is_empty());
+
+        let config = Config {
+            anti_aliased_second_opinion: true,
+            ..config
+        };
+        let result = glyph.check(&config).unwrap();
+        assert!(result.overlap.is_some());
+        assert!(matches!(
+            result.warnings.as_slice(),
+            [Warning::MethodsDisagree {
+                anti_aliased: false,
+                ..
+            }]
+        ));
+        // Only close calls get a second opinion
+        let plus = check_with("Overlaps.ufo", "plus", &config);
+        assert!(plus.warnings.is_empty());
+    }
+
+    #[test]
+    fn overlapping_components_of_clean_glyph() {
+        assert!(check("Overlaps.ufo", "square").overlap.is_none());
+        assert!(check("Overlaps.ufo", "squares").overlap.is_some());
+    }
+
+    #[test]
+    fn area_as_percent_of_ink() {
+        // plus overlaps over 100x100 of the 110000 square units it inks
+        let overlap = check("Overlaps.ufo", "plus").overlap.unwrap();
+        assert!(
+            (overlap.area_percent() - 9.1).abs() < 0.2,
+            "{}",
+            overlap.area_percent()
+        );
+    }
+
+    #[test]
+    fn combining_marks() {
+        // Zero width, drawn left of the origin and well above the baseline, and small
+        let marks = Glyph::from_file(testdata("Marks.ufo"))
+            .into_iter()
+            .map(|g| g.unwrap())
+            .collect::<Vec<_>>();
+        assert!(marks.iter().all(|g| g.advance() == Some(0.0)));
+        for (scale, include_advance) in [(1.0, false), (1.0, true), (0.1, false)] {
+            let config = Config {
+                scale_x: scale,
+                scale_y: scale,
+                include_advance,
+                ..Default::default()
+            };
+            let flagged = marks
+                .iter()
+                .map(|g| g.check(&config).unwrap())
+                .filter_map(|r| r.overlap.map(|o| (r.name.to_string(), o.bbox)))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                vec!["caroncomb", "dieresisacutecomb"],
+                flagged.iter().map(|(n, _)| n).collect::<Vec<_>>(),
+                "scale {scale}"
+            );
+            for (name, bbox) in flagged {
+                assert!(
+                    bbox.max_x() <= 0.0 && bbox.min_y() >= 550.0,
+                    "{name} at scale {scale} overlaps at {bbox:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn contours_in_hand() {
+        let config = Config::default();
+        for name in ["plus", "square", "ring"] {
+            let file = testdata(&format!("Overlaps.ufo/glyphs/{name}.glif"));
+            let glif = norad::Glyph::load(&file).unwrap();
+            let result =
+                super::check_contours(&glif.contours, GlyphName::new(name), &config).unwrap();
+            let from_file = check("Overlaps.ufo", name);
+            assert_eq!(
+                (
+                    from_file.overlap.map(|o| (o.kind, o.bbox)),
+                    from_file.outline_hash
+                ),
+                (
+                    result.overlap.map(|o| (o.kind, o.bbox)),
+                    result.outline_hash
+                ),
+                "{name}"
+            );
+            assert_eq!(Path::new(crate::MEMORY_SOURCE), result.source);
+        }
+    }
+
+    #[test]
+    fn handler_sees_results_in_order() {
+        let files = [testdata("Overlaps.ufo"), testdata("Broken.glif")];
+        let mut seen = Vec::new();
+        super::check_with(Glyph::stream(&files), &Config::default(), |result| {
+            seen.push(result.map(|r| (r.name.to_string(), r.overlap.is_some())));
+        });
+
+        let expected = Glyph::from_file(&files[0])
+            .into_iter()
+            .map(|g| {
+                let result = g.unwrap().check(&Config::default()).unwrap();
+                (result.name.to_string(), result.overlap.is_some())
+            })
+            .collect::<Vec<_>>();
+        let (last, checked) = seen.split_last().unwrap();
+        assert_eq!(
+            expected,
+            checked
+                .iter()
+                .map(|r| r.as_ref().unwrap().clone())
+                .collect::<Vec<_>>()
+        );
+        // The glyph that wouldn't load is passed along, not dropped
+        assert!(last.is_err(), "{last:?}");
+    }
+
+    #[test]
+    fn same_report_on_any_thread_count() {
+        // Region outlines included, those are what could come out differently
+        let report = |threads| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            let mut seen = Vec::new();
+            pool.install(|| {
+                let glyphs = Glyph::stream([testdata("Overlaps.ufo")]);
+                super::check_with(glyphs, &Config::default(), |result| {
+                    seen.push(format!("{:?}", result.unwrap()));
+                });
+            });
+            seen
+        };
+        assert_eq!(report(1), report(4));
+    }
+
+    #[test]
+    fn verdicts_by_name() {
+        let verdicts = super::needs_overlap_flag(testdata("Overlaps.ufo"), &Config::default());
+        let verdicts = verdicts.unwrap();
+        assert_eq!(Some(&true), verdicts.get(&GlyphName::new("plus")));
+        assert_eq!(Some(&false), verdicts.get(&GlyphName::new("square")));
+
+        // Clean in the regular master, flagged in bold
+        let verdicts =
+            super::needs_overlap_flag(testdata("Bars/Bars.designspace"), &Config::default());
+        assert_eq!(Some(&true), verdicts.unwrap().get(&GlyphName::new("bars")));
+
+        assert!(super::needs_overlap_flag(testdata("Broken.glif"), &Config::default()).is_err());
+    }
+}
@@ -0,0 +1,86 @@
+//! Telling color layers apart from the base outlines of a UFO's color glyphs.
+
+use std::collections::HashSet;
+
+use plist::Value;
+
+/// ufo2ft: UFO layers to draw as color layers, an array of `[layer name, palette index]`
+const COLOR_LAYER_MAPPING: &str = "com.github.googlei18n.ufo2ft.colorLayerMapping";
+/// ufo2ft: COLRv0 color glyphs, base glyph name to an array of `[glyph name, palette index]`
+const COLOR_LAYERS: &str = "com.github.googlei18n.ufo2ft.colorLayers";
+/// ufo2ft: COLRv1 color glyphs, base glyph name to a paint
+const COLOR_GLYPHS: &str = "com.github.googlei18n.ufo2ft.colorGlyphs";
+
+/// What a UFO draws only as layers of its color glyphs, which we don't check. The
+/// monochrome outlines of the color glyphs themselves are checked as usual.
+#[derive(Debug, Default)]
+pub(crate) struct ColorLayers {
+    /// UFO layers holding color layers
+    layers: HashSet<String>,
+    /// Default layer glyphs used as color layers that aren't color glyphs themselves
+    glyphs: HashSet<String>,
+}
+
+impl ColorLayers {
+    pub(crate) fn new(ufo: &norad::Font) -> Self {
+        let mut layers = HashSet::new();
+        let glyph_libs = ufo.default_layer().iter().map(|g| &g.lib);
+        for lib in std::iter::once(&ufo.lib).chain(glyph_libs) {
+            if let Some(mapping) = lib.get(COLOR_LAYER_MAPPING).and_then(Value::as_array) {
+                layers.extend(mapping.iter().filter_map(first_string));
+            }
+        }
+
+        let mut glyphs = HashSet::new();
+        let mut bases = HashSet::new();
+        if let Some(color_layers) = ufo.lib.get(COLOR_LAYERS).and_then(Value::as_dictionary) {
+            for (base, color_layers) in color_layers.iter() {
+                bases.insert(base.clone());
+                if let Some(color_layers) = color_layers.as_array() {
+                    glyphs.extend(color_layers.iter().filter_map(first_string));
+                }
+            }
+        }
+        if let Some(color_glyphs) = ufo.lib.get(COLOR_GLYPHS).and_then(Value::as_dictionary) {
+            for (base, paint) in color_glyphs.iter() {
+                bases.insert(base.clone());
+                paint_glyphs(paint, &mut glyphs);
+            }
+        }
+        glyphs.retain(|g| !bases.contains(g));
+        Self { layers, glyphs }
+    }
+
+    pub(crate) fn is_color_layer(&self, layer: &norad::Layer) -> bool {
+        self.layers.contains(layer.name().as_str())
+    }
+
+    pub(crate) fn is_layer_glyph(&self, glyph: &norad::Glyph) -> bool {
+        self.glyphs.contains(glyph.name().as_str())
+    }
+}
+
+/// The name in a `[name, palette index]` pair.
+fn first_string(pair: &Value) -> Option<String> {
+    pair.as_array()?.first()?.as_string().map(str::to_string)
+}
+
+/// Adds the glyphs a ufo2ft paint draws to `glyphs`. Paints are nested dictionaries,
+/// PaintGlyph has the name under `Glyph`, and a list of paints is a PaintColrLayers.
+/// COLRv0 style `[name, palette index]` layers are accepted too.
+fn paint_glyphs(paint: &Value, glyphs: &mut HashSet<String>) {
+    if let Some(glyph) = first_string(paint) {
+        glyphs.insert(glyph);
+        return;
+    }
+    match paint {
+        Value::Array(paints) => paints.iter().for_each(|p| paint_glyphs(p, glyphs)),
+        Value::Dictionary(paint) => {
+            if let Some(glyph) = paint.get("Glyph").and_then(Value::as_string) {
+                glyphs.insert(glyph.to_string());
+            }
+            paint.values().for_each(|p| paint_glyphs(p, glyphs));
+        }
+        _ => (),
+    }
+}
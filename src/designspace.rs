@@ -0,0 +1,593 @@
+//! Masters, and the instances interpolated from them, from a designspace.
+//!
+//! Instances only exist as an interpolation of the masters, with any rule
+//! substitutions active at their location applied, so that's what we check.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use font_types::Tag;
+use fontdrasil::{
+    coords::{CoordConverter, DesignCoord, NormalizedLocation, UserCoord},
+    types::{Axis, GlyphName},
+};
+use fontir::variations::{VariationModel, VariationRegion};
+use kurbo::{BezPath, PathEl, Point, Vec2};
+use norad::designspace::{DesignSpaceDocument, Dimension, Instance, Rule};
+
+use crate::{color::ColorLayers, glyph::load_ufo, Error, Glyph, Sources};
+
+/// Design coordinates by axis name, which is how rules refer to axes.
+type DesignLocation = HashMap<String, f32>;
+
+/// Loads every glyph of every master, then every glyph of every instance, each
+/// recording that it came through `file`. `sources` can leave out either; masters are
+/// still read to interpolate instances from, and errors reading them reported.
+///
+/// With a user-space location `at`, see [`crate::Selection::location`], the glyphs
+/// there are interpolated instead of the instances, and one outside the designspace
+/// is an error.
+pub(crate) fn load(
+    file: &Path,
+    sources: Sources,
+    at: Option<&[(String, f64)]>,
+) -> Vec<Result<Glyph, Error>> {
+    let mut glyphs = load_glyphs(file, sources, at);
+    for glyph in glyphs.iter_mut().flatten() {
+        glyph.designspace = Some(file.to_path_buf());
+    }
+    glyphs
+}
+
+fn load_glyphs(
+    file: &Path,
+    sources: Sources,
+    at: Option<&[(String, f64)]>,
+) -> Vec<Result<Glyph, Error>> {
+    let doc =
+        DesignSpaceDocument::load(file).unwrap_or_else(|e| panic!("Unable to load {file:?}: {e}"));
+    let axes = doc
+        .axes
+        .iter()
+        .map(|a| to_ir_axis(file, a))
+        .collect::<Vec<_>>();
+    // Checked before loading anything, there's nothing to check at a location that isn't
+    // in the designspace
+    let at = match at.map(|at| (location_label(at), user_location(&axes, at))) {
+        Some((_, Err(reason))) => {
+            return vec![Err(Error::Invalid {
+                file: file.to_path_buf(),
+                reason,
+            })]
+        }
+        Some((label, Ok(location))) => Some((label, location)),
+        None => None,
+    };
+    let dir = file.parent().unwrap_or(Path::new("."));
+
+    let mut glyphs = Vec::new();
+    let mut ufos: HashMap<PathBuf, norad::Font> = HashMap::new();
+    let mut masters: Vec<(NormalizedLocation, HashMap<GlyphName, BezPath>)> = Vec::new();
+    // Instances map the codepoints, set the overlap flag, and use the components the
+    // default master does
+    let mut defaults: HashMap<GlyphName, (Vec<char>, bool, Vec<GlyphName>)> = HashMap::new();
+    // And have its em and lean
+    let mut default_metrics = (None, None);
+    for source in doc.sources.iter() {
+        let ufo_dir = dir.join(&source.filename);
+        if !ufos.contains_key(&ufo_dir) {
+            match load_ufo(&ufo_dir) {
+                Ok(ufo) => ufos.insert(ufo_dir.clone(), ufo),
+                // Instances can't be interpolated without every master
+                Err(e) => {
+                    glyphs.push(Err(e));
+                    return glyphs;
+                }
+            };
+        }
+        let ufo = &ufos[&ufo_dir];
+        let layer = match &source.layer {
+            Some(name) => ufo.layers.get(name).unwrap_or_else(|| {
+                panic!("{file:?} refers to missing layer {name} of {ufo_dir:?}")
+            }),
+            None => ufo.default_layer(),
+        };
+        let location = normalized(&axes, &design_location(&axes, &source.location));
+        let mut outlines = HashMap::new();
+        if location.is_default() {
+            default_metrics = (
+                ufo.font_info.italic_angle,
+                ufo.font_info.units_per_em.map(|u| *u),
+            );
+        }
+        for mut glyph in Glyph::from_layer(&ufo_dir, layer, &ColorLayers::new(ufo)) {
+            if let Ok(glyph) = &mut glyph {
+                glyph.italic_angle = ufo.font_info.italic_angle;
+                glyph.set_units_per_em(ufo.font_info.units_per_em.map(|u| *u));
+                outlines.insert(glyph.name.clone(), glyph.bezpath.clone());
+                if location.is_default() {
+                    defaults.insert(
+                        glyph.name.clone(),
+                        (
+                            glyph.codepoints.clone(),
+                            glyph.overlap_flag,
+                            glyph.components.clone(),
+                        ),
+                    );
+                }
+            }
+            if sources.masters() || glyph.is_err() {
+                glyphs.push(glyph);
+            }
+        }
+        masters.push((location, outlines));
+    }
+
+    let instances = match at {
+        Some(at) => vec![at],
+        None => doc
+            .instances
+            .iter()
+            .enumerate()
+            .map(|(i, instance)| {
+                (
+                    instance_label(instance, i),
+                    design_location(&axes, &instance.location),
+                )
+            })
+            .collect(),
+    };
+    if instances.is_empty() || !sources.instances() {
+        return glyphs;
+    }
+    let Some((_, default_master)) = masters.iter().find(|(loc, _)| loc.is_default()) else {
+        eprintln!("WARNING: {file:?} has no default master, not checking instances");
+        return glyphs;
+    };
+
+    // Point axes don't vary, and the model refuses them
+    let model_axes = axes
+        .iter()
+        .filter(|a| !a.is_point())
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut names = default_master.keys().cloned().collect::<Vec<_>>();
+    names.sort();
+    let variable = names
+        .iter()
+        .map(|name| {
+            let outlines = masters
+                .iter()
+                .filter_map(|(loc, outlines)| outlines.get(name).map(|o| (loc, o)))
+                .collect::<Vec<_>>();
+            (name, Variable::new(&outlines, &model_axes))
+        })
+        .collect::<HashMap<_, _>>();
+
+    for (i, (label, design_loc)) in instances.iter().enumerate() {
+        let loc = normalized(&axes, design_loc);
+        for name in names.iter() {
+            // The rules may have us ship some other glyph's outline under this name
+            let outline_name = substitute(&doc.rules.rules, design_loc, name);
+            let Some(outline) = variable.get(&outline_name) else {
+                glyphs.push(Err(Error::Interpolation {
+                    file: file.to_path_buf(),
+                    glyph: name.clone(),
+                    reason: format!(
+                        "rules substitute {outline_name}, which isn't in the default master"
+                    ),
+                }));
+                continue;
+            };
+            glyphs.push(match outline {
+                Ok(outline) => Glyph::new(name.clone(), file, outline.at(&loc)).map(|mut g| {
+                    g.instance = Some(label.clone());
+                    (g.codepoints, g.overlap_flag, g.components) =
+                        defaults.get(name).cloned().unwrap_or_default();
+                    g.italic_angle = default_metrics.0;
+                    g.set_units_per_em(default_metrics.1);
+                    g
+                }),
+                // Only report each glyph that won't interpolate once
+                Err(_) if i > 0 => continue,
+                Err(e) => Err(Error::Interpolation {
+                    file: file.to_path_buf(),
+                    glyph: name.clone(),
+                    reason: e.clone(),
+                }),
+            });
+        }
+    }
+    glyphs
+}
+
+/// An outline that can be produced at any location in the designspace.
+struct Variable {
+    /// The default master's outline; we move its points
+    default: BezPath,
+    deltas: Vec<(VariationRegion, Vec<Vec2>)>,
+}
+
+impl Variable {
+    fn new(outlines: &[(&NormalizedLocation, &BezPath)], axes: &[Axis]) -> Result<Self, String> {
+        let Some((_, default)) = outlines.iter().find(|(loc, _)| loc.is_default()) else {
+            return Err("not in the default master".to_string());
+        };
+        let mut point_seqs = HashMap::new();
+        for (loc, outline) in outlines {
+            if !compatible(default, outline) {
+                return Err("masters have incompatible outlines".to_string());
+            }
+            point_seqs
+                .entry((*loc).clone())
+                .or_insert_with(|| points(outline));
+        }
+        let model = VariationModel::new(
+            point_seqs.keys().cloned().collect::<HashSet<_>>(),
+            axes.to_vec(),
+        )
+        .map_err(|e| format!("{e:?}"))?;
+        let deltas = model
+            .deltas::<Point, Vec2>(&point_seqs)
+            .map_err(|e| format!("{e:?}"))?;
+        Ok(Self {
+            default: (*default).clone(),
+            deltas,
+        })
+    }
+
+    /// The outline at `loc`.
+    fn at(&self, loc: &NormalizedLocation) -> BezPath {
+        let scalars = self
+            .deltas
+            .iter()
+            .map(|(region, _)| region.scalar_at(loc).into_inner() as f64)
+            .collect::<Vec<_>>();
+        let mut i = 0;
+        let mut next = || {
+            let p = self
+                .deltas
+                .iter()
+                .zip(scalars.iter())
+                .fold(Point::ORIGIN, |p, ((_, deltas), s)| p + deltas[i] * *s);
+            i += 1;
+            p
+        };
+        self.default
+            .elements()
+            .iter()
+            .map(|el| match el {
+                PathEl::MoveTo(_) => PathEl::MoveTo(next()),
+                PathEl::LineTo(_) => PathEl::LineTo(next()),
+                PathEl::QuadTo(..) => PathEl::QuadTo(next(), next()),
+                PathEl::CurveTo(..) => PathEl::CurveTo(next(), next(), next()),
+                PathEl::ClosePath => PathEl::ClosePath,
+            })
+            .collect()
+    }
+}
+
+/// Same sequence of commands, so the points correspond.
+fn compatible(a: &BezPath, b: &BezPath) -> bool {
+    a.elements().len() == b.elements().len()
+        && a.elements()
+            .iter()
+            .zip(b.elements())
+            .all(|(a, b)| std::mem::discriminant(a) == std::mem::discriminant(b))
+}
+
+fn points(path: &BezPath) -> Vec<Point> {
+    path.elements()
+        .iter()
+        .flat_map(|el| match el {
+            PathEl::MoveTo(p) | PathEl::LineTo(p) => vec![*p],
+            PathEl::QuadTo(c, p) => vec![*c, *p],
+            PathEl::CurveTo(c0, c1, p) => vec![*c0, *c1, *p],
+            PathEl::ClosePath => vec![],
+        })
+        .collect()
+}
+
+/// The glyph whose outline `name` has at `loc`, following substitutions in rule order.
+fn substitute(rules: &[Rule], loc: &DesignLocation, name: &GlyphName) -> GlyphName {
+    let mut name = name.clone();
+    for rule in rules.iter().filter(|r| rule_applies(r, loc)) {
+        if let Some(sub) = rule
+            .substitutions
+            .iter()
+            .find(|s| s.name.as_str() == name.as_str())
+        {
+            name = sub.with.as_str().into();
+        }
+    }
+    name
+}
+
+/// A rule applies if any of its condition sets is entirely true, or it has none.
+fn rule_applies(rule: &Rule, loc: &DesignLocation) -> bool {
+    rule.condition_sets.is_empty()
+        || rule.condition_sets.iter().any(|set| {
+            set.conditions.iter().all(|c| {
+                let Some(v) = loc.get(&c.name) else {
+                    return false;
+                };
+                c.minimum.is_none_or(|min| *v >= min) && c.maximum.is_none_or(|max| *v <= max)
+            })
+        })
+}
+
+fn instance_label(instance: &Instance, i: usize) -> String {
+    match (&instance.name, &instance.familyname, &instance.stylename) {
+        (Some(name), _, _) => name.clone(),
+        (None, Some(family), Some(style)) => format!("{family} {style}"),
+        (None, None, Some(style)) => style.clone(),
+        _ => format!("instance {i}"),
+    }
+}
+
+/// Where `dims` puts us, axes that aren't mentioned being at their default.
+fn design_location(axes: &[Axis], dims: &[Dimension]) -> DesignLocation {
+    axes.iter()
+        .map(|axis| {
+            let dim = dims.iter().find(|d| d.name == axis.name);
+            let design = match dim.map(|d| (d.xvalue, d.uservalue)) {
+                Some((Some(x), _)) => DesignCoord::new(x),
+                Some((None, Some(user))) => UserCoord::new(user).to_design(&axis.converter),
+                _ => axis.default.to_design(&axis.converter),
+            };
+            (axis.name.clone(), design.to_f32())
+        })
+        .collect()
+}
+
+/// Where user-space coordinates `at`, by axis tag, put us, through the axes' maps.
+/// Axes that aren't mentioned are at their default. Says why if `at` isn't in the
+/// designspace.
+fn user_location(axes: &[Axis], at: &[(String, f64)]) -> Result<DesignLocation, String> {
+    for (tag, value) in at {
+        let Some(axis) = axes.iter().find(|a| a.tag.to_string() == *tag) else {
+            return Err(format!("has no {tag} axis"));
+        };
+        let (min, max) = (axis.min.to_f32() as f64, axis.max.to_f32() as f64);
+        if !(min..=max).contains(value) {
+            return Err(format!(
+                "{tag} axis runs from {min} to {max}, {tag}={value} is outside it"
+            ));
+        }
+    }
+    Ok(axes
+        .iter()
+        .map(|axis| {
+            let user = at
+                .iter()
+                .find(|(tag, _)| axis.tag.to_string() == *tag)
+                .map_or(axis.default, |(_, value)| UserCoord::new(*value as f32));
+            (axis.name.clone(), user.to_design(&axis.converter).to_f32())
+        })
+        .collect())
+}
+
+/// Names the glyphs at a user-space location, e.g. `wght=700,wdth=75`.
+pub(crate) fn location_label(at: &[(String, f64)]) -> String {
+    at.iter()
+        .map(|(tag, value)| format!("{tag}={value}"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn normalized(axes: &[Axis], loc: &DesignLocation) -> NormalizedLocation {
+    axes.iter()
+        .map(|axis| {
+            (
+                axis.tag,
+                DesignCoord::new(loc[&axis.name]).to_normalized(&axis.converter),
+            )
+        })
+        .collect()
+}
+
+fn to_ir_axis(file: &Path, axis: &norad::designspace::Axis) -> Axis {
+    let tag = Tag::new_checked(axis.tag.as_bytes())
+        .unwrap_or_else(|e| panic!("{file:?} axis {} has a bad tag: {e}", axis.name));
+    let default = UserCoord::new(axis.default);
+    let (min, max) = match (axis.minimum, axis.maximum, &axis.values) {
+        (Some(min), Some(max), _) => (UserCoord::new(min), UserCoord::new(max)),
+        // Discrete axes list their values instead
+        (_, _, Some(values)) if !values.is_empty() => (
+            UserCoord::new(values.iter().copied().fold(f32::INFINITY, f32::min)),
+            UserCoord::new(values.iter().copied().fold(f32::NEG_INFINITY, f32::max)),
+        ),
+        _ => (default, default),
+    };
+    let converter = match &axis.map {
+        Some(map) if !map.is_empty() => {
+            let mappings = map
+                .iter()
+                .map(|m| (UserCoord::new(m.input), DesignCoord::new(m.output)))
+                .collect::<Vec<_>>();
+            let default_idx = mappings
+                .iter()
+                .position(|(user, _)| *user == default)
+                .unwrap_or_else(|| {
+                    panic!(
+                        "{file:?} axis {} map doesn't include the default",
+                        axis.name
+                    )
+                });
+            CoordConverter::new(mappings, default_idx)
+        }
+        _ => CoordConverter::unmapped(min, default, max),
+    };
+    Axis {
+        name: axis.name.clone(),
+        tag,
+        min,
+        default,
+        max,
+        hidden: axis.hidden,
+        converter,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, path::PathBuf};
+
+    use crate::{Config, Error, Glyph, OverlapKind, Selection, Sources};
+
+    fn bars() -> Vec<Glyph> {
+        masters_and_instances("Bars.designspace")
+    }
+
+    fn masters_and_instances(designspace: &str) -> Vec<Glyph> {
+        let file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/testdata/Bars")
+            .join(designspace);
+        let selection = Selection {
+            sources: Sources::Both,
+            ..Default::default()
+        };
+        Glyph::stream_selected([file], selection)
+            .map(|g| g.unwrap())
+            .collect()
+    }
+
+    fn flagged(glyphs: &[Glyph], instance: Option<&str>, name: &str) -> bool {
+        let glyph = glyphs
+            .iter()
+            .find(|g| g.instance() == instance && g.name().as_str() == name)
+            .unwrap_or_else(|| panic!("No {name} for {instance:?}"));
+        glyph.check(&Config::default()).unwrap().overlap.is_some()
+    }
+
+    #[test]
+    fn instances_interpolate() {
+        let glyphs = bars();
+        assert!(!flagged(&glyphs, Some("Bars Book"), "bars"));
+        assert!(flagged(&glyphs, Some("Bars SemiBold"), "bars"));
+    }
+
+    #[test]
+    fn rules_substitute_outlines() {
+        let glyphs = bars();
+        assert!(!flagged(&glyphs, Some("Bars Book"), "dollar"));
+        let semibold = glyphs
+            .iter()
+            .find(|g| g.instance() == Some("Bars SemiBold") && g.name().as_str() == "dollar")
+            .unwrap();
+        assert_eq!(
+            Some(OverlapKind::OverlappingFills),
+            semibold
+                .check(&Config::default())
+                .unwrap()
+                .overlap
+                .map(|o| o.kind)
+        );
+    }
+
+    #[test]
+    fn instances_have_default_codepoints() {
+        let glyphs = bars();
+        for glyph in glyphs.iter().filter(|g| g.name().as_str() == "dollar") {
+            assert_eq!(['$'], glyph.codepoints(), "{}", glyph.describe());
+        }
+    }
+
+    #[test]
+    fn sources_selected() {
+        let file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/testdata/Bars/Bars.designspace");
+        let instances = |sources| {
+            let glyphs = super::load(&file, sources, None)
+                .into_iter()
+                .map(|g| g.unwrap())
+                .collect::<Vec<_>>();
+            assert!(!glyphs.is_empty());
+            glyphs
+                .iter()
+                .map(|g| g.instance().is_some())
+                .collect::<HashSet<_>>()
+        };
+        assert_eq!(HashSet::from([false]), instances(Sources::Masters));
+        assert_eq!(HashSet::from([true]), instances(Sources::Instances));
+        assert_eq!(HashSet::from([false, true]), instances(Sources::Both));
+    }
+
+    #[test]
+    fn user_location_mapped() {
+        let file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/testdata/Bars/Mapped.designspace");
+        let outline = |at: Option<&[(String, f64)]>| {
+            super::load(&file, Sources::Instances, at)
+                .into_iter()
+                .map(|g| g.unwrap())
+                .find(|g| g.name().as_str() == "bars")
+                .map(|g| (g.instance().map(str::to_string), g.bezpath().to_svg()))
+                .unwrap()
+        };
+        // wght 600 is 650 in design space, where the SemiBold instance is
+        let (instance, semibold) = outline(None);
+        assert_eq!(Some("Bars SemiBold"), instance.as_deref());
+        let (instance, at_600) = outline(Some(&[("wght".to_string(), 600.0)]));
+        assert_eq!(Some("wght=600"), instance.as_deref());
+        assert_eq!(semibold, at_600);
+        let (_, unmapped) = outline(Some(&[("wght".to_string(), 650.0)]));
+        assert_ne!(semibold, unmapped);
+    }
+
+    #[test]
+    fn user_location_outside_designspace() {
+        let file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/testdata/Bars/Mapped.designspace");
+        for (at, expected) in [
+            (("wdth", 100.0), "has no wdth axis"),
+            (("wght", 5000.0), "is outside it"),
+        ] {
+            let glyphs = super::load(&file, Sources::Both, Some(&[(at.0.to_string(), at.1)]));
+            assert!(
+                matches!(glyphs.as_slice(), [Err(Error::Invalid { reason, .. })] if reason.contains(expected)),
+                "{glyphs:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn sparse_masters() {
+        let glyphs = masters_and_instances("Sparse.designspace");
+        let sparse = glyphs
+            .iter()
+            .filter(|g| g.source().parent().unwrap().ends_with("glyphs.wght550"))
+            .map(|g| g.name().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["bars"], sparse);
+        // The sparse master pulls the bars apart, without it they'd overlap
+        assert!(!flagged(&glyphs, Some("Bars SemiBold"), "bars"));
+        // Glyphs it doesn't have interpolate from the masters that do
+        assert!(!flagged(&glyphs, Some("Bars SemiBold"), "dollar"));
+        assert!(flagged(&glyphs, Some("Bars SemiBold"), "dollar.alt"));
+    }
+
+    #[test]
+    fn masters_are_checked() {
+        let glyphs = bars();
+        assert!(!flagged(&glyphs, None, "dollar"));
+        assert!(glyphs
+            .iter()
+            .any(|g| g.instance().is_none() && g.name().as_str() == "dollar.alt"));
+    }
+
+    #[test]
+    fn attributed_to_the_designspace() {
+        let glyphs = bars();
+        assert!(glyphs
+            .iter()
+            .all(|g| g.designspace().and_then(|d| d.file_name())
+                == Some("Bars.designspace".as_ref())));
+        let master = glyphs.iter().find(|g| g.instance().is_none()).unwrap();
+        assert!(master
+            .describe()
+            .ends_with(&format!(" in {:?}", master.designspace().unwrap())));
+    }
+}
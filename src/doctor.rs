@@ -0,0 +1,246 @@
+//! Checking inputs are loadable before checking glyphs in them.
+//!
+//! Loading panics on inputs it can't make sense of, which is fine for a run but
+//! unhelpful for working out what's wrong. [`doctor`] looks for the same problems
+//! and reports them instead.
+
+use std::path::Path;
+
+use kurbo::BezPath;
+use norad::designspace::DesignSpaceDocument;
+use read_fonts::{FontRef, TableProvider};
+
+use crate::{
+    error::error_chain as describe,
+    glyph::{glif_error, glif_failure, JsonGlyph},
+    Error, Glyph, Warning,
+};
+
+/// What [`doctor`] found.
+#[derive(Debug, Default)]
+pub struct Diagnosis {
+    /// Problems that stop a file, or glyphs in it, being checked
+    pub errors: Vec<Error>,
+    /// Things that look off but won't stop a check
+    pub warnings: Vec<Warning>,
+}
+
+impl Diagnosis {
+    pub fn is_healthy(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    fn invalid(&mut self, file: &Path, reason: impl Into<String>) {
+        self.errors.push(Error::Invalid {
+            file: file.to_path_buf(),
+            reason: reason.into(),
+        });
+    }
+
+    /// Load every glyph in `file` as a real run would, keeping what goes wrong.
+    fn load_glyphs(&mut self, file: &Path) {
+        for glyph in Glyph::from_file(file) {
+            match glyph {
+                Ok(glyph) => self.warnings.extend(glyph.warnings),
+                Err(e) => self.errors.push(e),
+            }
+        }
+    }
+}
+
+/// Look for anything that would stop `file` being checked, without checking it.
+///
+/// Files are opened and parsed, UFOs and fonts must say how many units are in an
+/// em, and every glyph is loaded, so broken outlines, missing components, and
+/// non-finite coordinates are all found.
+pub fn doctor(file: &Path) -> Diagnosis {
+    let mut diagnosis = Diagnosis::default();
+    if !file.exists() {
+        diagnosis.invalid(file, "doesn't exist");
+        return diagnosis;
+    }
+    let healthy = match file.extension().and_then(|e| e.to_str()) {
+        Some("designspace") => designspace(file, &mut diagnosis),
+        Some("ufo") => ufo(file, &mut diagnosis),
+        Some("glif") => glif(file, &mut diagnosis),
+        Some("json") => json(file, &mut diagnosis),
+        Some("ttf") => ttf(file, &mut diagnosis),
+        _ => {
+            diagnosis.invalid(
+                file,
+                "isn't a .glif, .ufo, .designspace, .ttf, or .json file",
+            );
+            false
+        }
+    };
+    // Past here problems are per glyph, and loading reports rather than panics
+    if healthy {
+        diagnosis.load_glyphs(file);
+    }
+    diagnosis
+}
+
+fn designspace(file: &Path, diagnosis: &mut Diagnosis) -> bool {
+    let doc = match DesignSpaceDocument::load(file) {
+        Ok(doc) => doc,
+        Err(e) => {
+            diagnosis.invalid(file, format!("can't be read: {}", describe(&e)));
+            return false;
+        }
+    };
+    let dir = file.parent().unwrap_or(Path::new("."));
+    let mut healthy = true;
+    for source in doc.sources.iter() {
+        let ufo_dir = dir.join(&source.filename);
+        let mut source_diagnosis = Diagnosis::default();
+        let Some(font) = load_ufo(&ufo_dir, &mut source_diagnosis) else {
+            diagnosis.errors.extend(source_diagnosis.errors);
+            healthy = false;
+            continue;
+        };
+        diagnosis.errors.extend(source_diagnosis.errors);
+        if let Some(layer) = &source.layer {
+            if font.layers.get(layer).is_none() {
+                diagnosis.invalid(
+                    file,
+                    format!("source {} refers to missing layer {layer}", source.filename),
+                );
+                healthy = false;
+            }
+        }
+    }
+    healthy
+}
+
+fn ufo(file: &Path, diagnosis: &mut Diagnosis) -> bool {
+    load_ufo(file, diagnosis).is_some()
+}
+
+/// The UFO at `file`, if it loads. A missing unitsPerEm is reported but doesn't
+/// stop us loading glyphs.
+fn load_ufo(file: &Path, diagnosis: &mut Diagnosis) -> Option<norad::Font> {
+    if !file.is_dir() {
+        diagnosis.invalid(file, "doesn't exist or isn't a directory");
+        return None;
+    }
+    let font = match norad::Font::load(file) {
+        Ok(font) => font,
+        Err(e) => {
+            match glif_failure(&e) {
+                Some(glif) => diagnosis.errors.push(glif),
+                None => diagnosis.invalid(file, format!("can't be read: {}", describe(&e))),
+            }
+            return None;
+        }
+    };
+    if font.font_info.units_per_em.is_none() {
+        diagnosis.invalid(file, "has no unitsPerEm in fontinfo.plist");
+    }
+    Some(font)
+}
+
+fn glif(file: &Path, diagnosis: &mut Diagnosis) -> bool {
+    match norad::Glyph::load(file) {
+        Ok(_) => true,
+        Err(e) => {
+            diagnosis.errors.push(glif_error(file, &e));
+            false
+        }
+    }
+}
+
+fn json(file: &Path, diagnosis: &mut Diagnosis) -> bool {
+    let glyphs = std::fs::read_to_string(file)
+        .map_err(|e| describe(&e))
+        .and_then(|raw| serde_json::from_str::<Vec<JsonGlyph>>(&raw).map_err(|e| describe(&e)));
+    let glyphs = match glyphs {
+        Ok(glyphs) => glyphs,
+        Err(reason) => {
+            diagnosis.invalid(file, format!("can't be read: {reason}"));
+            return false;
+        }
+    };
+    let mut healthy = true;
+    for glyph in glyphs {
+        if let Err(e) = BezPath::from_svg(&glyph.path_commands) {
+            diagnosis.invalid(
+                file,
+                format!("{} has bad path_commands: {}", glyph.name, describe(&e)),
+            );
+            healthy = false;
+        }
+    }
+    healthy
+}
+
+fn ttf(file: &Path, diagnosis: &mut Diagnosis) -> bool {
+    let data = match std::fs::read(file) {
+        Ok(data) => data,
+        Err(e) => {
+            diagnosis.invalid(file, format!("can't be read: {}", describe(&e)));
+            return false;
+        }
+    };
+    let font = match FontRef::new(&data) {
+        Ok(font) => font,
+        Err(e) => {
+            diagnosis.invalid(file, format!("isn't a font: {e}"));
+            return false;
+        }
+    };
+    match font.head() {
+        Ok(head) if head.units_per_em() > 0 => (),
+        Ok(_) => diagnosis.invalid(file, "has unitsPerEm 0 in its head table"),
+        Err(e) => diagnosis.invalid(file, format!("has no readable head table: {e}")),
+    }
+    let mut healthy = true;
+    if font.maxp().is_err() {
+        diagnosis.invalid(file, "has no readable maxp table");
+        healthy = false;
+    }
+    if font.loca(None).is_err() || font.glyf().is_err() {
+        diagnosis.invalid(
+            file,
+            "has no glyf table, only TrueType outlines are supported",
+        );
+        healthy = false;
+    }
+    healthy
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::doctor;
+    use crate::Error;
+
+    fn testdata(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/testdata")
+            .join(name)
+    }
+
+    #[test]
+    fn healthy_inputs() {
+        for file in ["Overlaps.ufo", "Overlaps.ttf", "Bars/Bars.designspace"] {
+            let diagnosis = doctor(&testdata(file));
+            assert!(diagnosis.is_healthy(), "{file}: {:?}", diagnosis.errors);
+        }
+    }
+
+    #[test]
+    fn missing_units_per_em() {
+        // Layers.ufo has no fontinfo.plist
+        let diagnosis = doctor(&testdata("Layers.ufo"));
+        assert!(matches!(
+            diagnosis.errors.as_slice(),
+            [Error::Invalid { reason, .. }] if reason.contains("unitsPerEm")
+        ));
+    }
+
+    #[test]
+    fn missing_file() {
+        assert!(!doctor(&testdata("Nope.ufo")).is_healthy());
+    }
+}
@@ -0,0 +1,74 @@
+use std::path::PathBuf;
+
+use fontdrasil::types::GlyphName;
+use kurbo::Point;
+use thiserror::Error;
+
+/// Something wrong with a single glyph that stops us checking it.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{file:?} {glyph} uses {base} as a component but there's no such glyph")]
+    MissingComponent {
+        file: PathBuf,
+        glyph: GlyphName,
+        base: GlyphName,
+    },
+    #[error("{file:?} {glyph} is, through its components, a component of itself")]
+    ComponentCycle { file: PathBuf, glyph: GlyphName },
+    #[error("{file:?} {glyph} has a non-finite coordinate {point:?}")]
+    NonFiniteCoordinate {
+        file: PathBuf,
+        glyph: GlyphName,
+        point: Point,
+    },
+    #[error("{file:?} {glyph} has an outline we can't read: {reason}")]
+    Outline {
+        file: PathBuf,
+        glyph: GlyphName,
+        reason: String,
+    },
+    #[error("{file:?} unable to interpolate {glyph}: {reason}")]
+    Interpolation {
+        file: PathBuf,
+        glyph: GlyphName,
+        reason: String,
+    },
+    #[error("{file:?} {glyph} couldn't be rendered: {reason}")]
+    Render {
+        file: PathBuf,
+        glyph: GlyphName,
+        reason: String,
+    },
+    #[error("{file:?} unable to annotate: {reason}")]
+    Annotate { file: PathBuf, reason: String },
+    #[error("{file:?} {reason}")]
+    Invalid { file: PathBuf, reason: String },
+    #[error("{file:?} {glyph} has no advance width, so there's nowhere to set a glyph after it")]
+    NoAdvance { file: PathBuf, glyph: GlyphName },
+    #[error("{file:?} can't be loaded{}: {reason}", at(.position))]
+    Glif {
+        file: PathBuf,
+        /// Line and column, from 1, of the malformed XML, if that's the problem
+        position: Option<(usize, usize)>,
+        reason: String,
+    },
+}
+
+fn at(position: &Option<(usize, usize)>) -> String {
+    match position {
+        Some((line, column)) => format!(" (line {line}, column {column})"),
+        None => String::new(),
+    }
+}
+
+/// The message of `e` and everything that caused it, norad's errors in particular
+/// keep the useful part in the source.
+pub(crate) fn error_chain(e: &dyn std::error::Error) -> String {
+    let mut message = e.to_string();
+    let mut source = e.source();
+    while let Some(cause) = source {
+        message.push_str(&format!(": {cause}"));
+        source = cause.source();
+    }
+    message
+}
@@ -0,0 +1,245 @@
+//! Rendering with FreeType, to compare what we see with what production rasterizers see.
+//!
+//! There's no FreeType crate to hand so this binds just enough of the C API to fill
+//! an outline into a grayscale bitmap, which links against the system libfreetype.
+//! FreeType's monochrome rasterizer ignores the fill rule, so we use the anti-aliasing
+//! one text is normally drawn with and call pixels at least half covered inside.
+
+use std::os::raw::{c_char, c_int, c_long, c_short, c_uchar, c_uint, c_ushort, c_void};
+
+use kurbo::{PathEl, Point};
+use tiny_skia::{FillRule, Pixmap, PremultipliedColorU8};
+
+use crate::{glyph::ensure_finite, Config, Error, Glyph};
+
+type FtLibrary = *mut c_void;
+type FtError = c_int;
+
+#[repr(C)]
+struct FtVector {
+    x: c_long,
+    y: c_long,
+}
+
+#[repr(C)]
+struct FtOutline {
+    n_contours: c_short,
+    n_points: c_short,
+    points: *mut FtVector,
+    tags: *mut c_char,
+    contours: *mut c_short,
+    flags: c_int,
+}
+
+#[repr(C)]
+struct FtBitmap {
+    rows: c_uint,
+    width: c_uint,
+    pitch: c_int,
+    buffer: *mut c_uchar,
+    num_grays: c_ushort,
+    pixel_mode: c_uchar,
+    palette_mode: c_uchar,
+    palette: *mut c_void,
+}
+
+const FT_OUTLINE_EVEN_ODD_FILL: c_int = 0x2;
+const FT_CURVE_TAG_ON: c_char = 0x1;
+const FT_CURVE_TAG_CONIC: c_char = 0x0;
+const FT_CURVE_TAG_CUBIC: c_char = 0x2;
+const FT_PIXEL_MODE_GRAY: c_uchar = 2;
+
+#[link(name = "freetype")]
+extern "C" {
+    fn FT_Init_FreeType(library: *mut FtLibrary) -> FtError;
+    fn FT_Done_FreeType(library: FtLibrary) -> FtError;
+    fn FT_Outline_Get_Bitmap(
+        library: FtLibrary,
+        outline: *mut FtOutline,
+        bitmap: *const FtBitmap,
+    ) -> FtError;
+}
+
+/// An outline as FreeType wants it, points in 26.6 fixed point, y-up.
+#[derive(Default)]
+struct Outline {
+    points: Vec<FtVector>,
+    tags: Vec<c_char>,
+    /// The index of the last point of each contour
+    contours: Vec<c_short>,
+}
+
+impl Outline {
+    fn push(&mut self, p: Point, tag: c_char, height: u32) {
+        self.points.push(FtVector {
+            x: (p.x * 64.0).round() as c_long,
+            y: ((height as f64 - p.y) * 64.0).round() as c_long,
+        });
+        self.tags.push(tag);
+    }
+
+    fn end_contour(&mut self) {
+        let last = self.points.len() as c_short - 1;
+        if self.contours.last().map_or(last >= 0, |end| last > *end) {
+            self.contours.push(last);
+        }
+    }
+}
+
+impl Glyph {
+    /// Render the glyph without anti-aliasing like [`Glyph::render_no_aa`], but with
+    /// FreeType: pixels FreeType covers at least half of are filled, others left empty.
+    pub(crate) fn render_freetype(
+        &self,
+        config: &Config,
+        fill_rule: FillRule,
+    ) -> Result<Pixmap, Error> {
+        let render_error = |reason: String| Error::Render {
+            file: self.source.clone(),
+            glyph: self.name.clone(),
+            reason,
+        };
+        let (transform, _) = self.render_transform(config);
+        let (width, height) = self.pixmap_size(config);
+        let mut bez = self.bezpath.clone();
+        bez.apply_affine(transform);
+        ensure_finite(&self.source, &self.name, &bez)?;
+
+        // FreeType closes every contour itself, pixmap space is y-down and FreeType's y-up
+        let mut outline = Outline::default();
+        for el in bez.iter() {
+            match el {
+                PathEl::MoveTo(p) => {
+                    outline.end_contour();
+                    outline.push(p, FT_CURVE_TAG_ON, height);
+                }
+                PathEl::LineTo(p) => outline.push(p, FT_CURVE_TAG_ON, height),
+                PathEl::QuadTo(c, p) => {
+                    outline.push(c, FT_CURVE_TAG_CONIC, height);
+                    outline.push(p, FT_CURVE_TAG_ON, height);
+                }
+                PathEl::CurveTo(c0, c1, p) => {
+                    outline.push(c0, FT_CURVE_TAG_CUBIC, height);
+                    outline.push(c1, FT_CURVE_TAG_CUBIC, height);
+                    outline.push(p, FT_CURVE_TAG_ON, height);
+                }
+                PathEl::ClosePath => (),
+            }
+        }
+        outline.end_contour();
+        if outline.points.len() > c_short::MAX as usize {
+            return Err(render_error(format!(
+                "{} points is more than FreeType can render",
+                outline.points.len()
+            )));
+        }
+
+        let mut buffer = vec![0u8; (width * height) as usize];
+        let bitmap = FtBitmap {
+            rows: height,
+            width,
+            pitch: width as c_int,
+            buffer: buffer.as_mut_ptr(),
+            num_grays: 256,
+            pixel_mode: FT_PIXEL_MODE_GRAY,
+            palette_mode: 0,
+            palette: std::ptr::null_mut(),
+        };
+        let mut ft_outline = FtOutline {
+            n_contours: outline.contours.len() as c_short,
+            n_points: outline.points.len() as c_short,
+            points: outline.points.as_mut_ptr(),
+            tags: outline.tags.as_mut_ptr(),
+            contours: outline.contours.as_mut_ptr(),
+            flags: match fill_rule {
+                FillRule::EvenOdd => FT_OUTLINE_EVEN_ODD_FILL,
+                FillRule::Winding => 0,
+            },
+        };
+        // SAFETY: the outline and bitmap point into vecs that outlive the calls, sized
+        // as they say, and the library is ours alone
+        let error = unsafe {
+            let mut library: FtLibrary = std::ptr::null_mut();
+            match FT_Init_FreeType(&mut library) {
+                0 => {
+                    let error = FT_Outline_Get_Bitmap(library, &mut ft_outline, &bitmap);
+                    FT_Done_FreeType(library);
+                    error
+                }
+                error => error,
+            }
+        };
+        if error != 0 {
+            return Err(render_error(format!("FreeType error {error}")));
+        }
+
+        let mut pixmap =
+            Pixmap::new(width, height).unwrap_or_else(|| panic!("Unable to create pixmap"));
+        let (r, g, b) = config.fill_color_for(fill_rule);
+        let color = PremultipliedColorU8::from_rgba(r, g, b, 255).unwrap();
+        for (pixel, coverage) in pixmap.pixels_mut().iter_mut().zip(buffer) {
+            if coverage >= 128 {
+                *pixel = color;
+            }
+        }
+        Ok(pixmap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use tiny_skia::FillRule;
+
+    use crate::{check::Rasterizer, Config, Glyph};
+
+    fn glyphs() -> Vec<Glyph> {
+        let file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/testdata/Overlaps.ufo");
+        Glyph::from_file(file)
+            .into_iter()
+            .map(|g| g.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn renders_like_tiny_skia() {
+        let config = Config::default();
+        for glyph in glyphs() {
+            for fill_rule in [FillRule::EvenOdd, FillRule::Winding] {
+                let tiny_skia = glyph.render_with(&config, fill_rule, false).unwrap();
+                let freetype = glyph.render_freetype(&config, fill_rule).unwrap();
+                let differ = tiny_skia
+                    .pixels()
+                    .iter()
+                    .zip(freetype.pixels())
+                    .filter(|(a, b)| a != b)
+                    .count();
+                // Curved edges may land a pixel differently, nothing more
+                assert!(
+                    differ * 1000 < tiny_skia.pixels().len(),
+                    "{} {fill_rule:?} differs at {differ} pixels",
+                    glyph.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn same_verdicts() {
+        let tiny_skia = Config::default();
+        let freetype = Config {
+            rasterizer: Rasterizer::FreeType,
+            ..Default::default()
+        };
+        for glyph in glyphs() {
+            assert_eq!(
+                glyph.check(&tiny_skia).unwrap().overlap.is_some(),
+                glyph.check(&freetype).unwrap().overlap.is_some(),
+                "{}",
+                glyph.name()
+            );
+        }
+    }
+}
@@ -0,0 +1,1260 @@
+//! Loading glyph outlines from sources.
+
+use std::{
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+use fontdrasil::types::GlyphName;
+use fontir::ir::GlyphPathBuilder;
+use kurbo::{Affine, BezPath, PathEl, Point, Shape};
+use norad::error::{FontLoadError, GlifLoadError, LayerLoadError};
+use quick_xml::events::Event;
+use serde::{Deserialize, Serialize};
+
+use crate::{color::ColorLayers, error::error_chain, Error, Warning};
+
+/// The glyph lib key ufo2ft sets the TrueType overlap flag from.
+pub(crate) const OVERLAP_FLAG_LIB_KEY: &str = "public.truetype.overlap";
+
+/// How small a glyph's net area must be, as a fraction of the area its contours enclose
+/// ignoring direction, to count as none, allowing for floating point error.
+const ZERO_AREA_TOLERANCE: f64 = 1e-6;
+
+/// A glyph in a `.json` input, which is an array of these.
+///
+/// Lets anything that can produce SVG path data hand us outlines, e.g.
+/// `[{"name": "plus", "path_commands": "M250,0 L350,0 L350,600 L250,600 Z ..."}]`.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct JsonGlyph {
+    pub(crate) name: String,
+    /// SVG path data in font units, y-up
+    pub(crate) path_commands: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) advance_width: Option<f64>,
+}
+
+/// A glyph outline ready to check, in y-down (svg) coordinates.
+#[derive(Debug)]
+pub struct Glyph {
+    pub(crate) name: GlyphName,
+    pub(crate) source: PathBuf,
+    pub(crate) bezpath: BezPath,
+    /// The designspace instance we interpolated, if any
+    pub(crate) instance: Option<String>,
+    /// The designspace we loaded the glyph through, if any
+    pub(crate) designspace: Option<PathBuf>,
+    /// Font units, if the source says
+    pub(crate) advance: Option<f64>,
+    /// Unicode codepoints mapped to the glyph, ascending
+    pub(crate) codepoints: Vec<char>,
+    /// Whether the source already sets the overlap flag
+    pub(crate) overlap_flag: bool,
+    /// The glyphs the source uses as components, before flattening
+    pub(crate) components: Vec<GlyphName>,
+    /// Degrees counter-clockwise from vertical, if the source says
+    pub(crate) italic_angle: Option<f64>,
+    /// The font's em, if the source says
+    pub(crate) units_per_em: Option<f64>,
+    pub(crate) warnings: Vec<Warning>,
+}
+
+impl Glyph {
+    pub fn name(&self) -> &GlyphName {
+        &self.name
+    }
+
+    /// The file the glyph was loaded from.
+    pub fn source(&self) -> &Path {
+        &self.source
+    }
+
+    /// The outline, components flattened, y-down.
+    pub fn bezpath(&self) -> &BezPath {
+        &self.bezpath
+    }
+
+    /// A stable hash of the outline, components flattened, as 16 hex digits. The
+    /// same outline hashes the same in every run and on every platform, so it tells
+    /// a glyph that changed from one that's just been checked again.
+    pub fn outline_hash(&self) -> String {
+        crate::cache::outline_hash(&self.bezpath)
+    }
+
+    /// Identifies the glyph in messages, see [`crate::CheckResult::describe`].
+    pub fn describe(&self) -> String {
+        crate::check::describe(
+            &self.source,
+            &self.name,
+            self.instance.as_deref(),
+            self.designspace.as_deref(),
+        )
+    }
+
+    /// The advance width in font units. None if the source doesn't say, and for
+    /// interpolated designspace instances and variable font named instances.
+    pub fn advance(&self) -> Option<f64> {
+        self.advance
+    }
+
+    /// The codepoints the source maps to the glyph, ascending: a UFO's `unicode`
+    /// elements, a binary font's cmap. Interpolated instances have their default master's.
+    pub fn codepoints(&self) -> &[char] {
+        &self.codepoints
+    }
+
+    /// Whether the source already sets the overlap flag: `public.truetype.overlap` in a
+    /// UFO glyph's lib, OVERLAP_SIMPLE or OVERLAP_COMPOUND in a binary font's glyf.
+    /// Interpolated instances have their default master's.
+    pub fn has_overlap_flag(&self) -> bool {
+        self.overlap_flag
+    }
+
+    /// The names of the glyphs the source draws this one with as components, in the
+    /// order it lists them: only the direct ones, not their components in turn.
+    /// Interpolated instances have their default master's.
+    pub fn components(&self) -> &[GlyphName] {
+        &self.components
+    }
+
+    /// The italic angle of the font the glyph is from, in degrees counter-clockwise from
+    /// vertical so negative leans right: a UFO's `italicAngle`, a binary font's post
+    /// table. None if the source doesn't say. Interpolated instances have their default
+    /// master's.
+    pub fn italic_angle(&self) -> Option<f64> {
+        self.italic_angle
+    }
+
+    /// The units per em of the font the glyph is from: a UFO's `unitsPerEm`, a binary
+    /// font's head table. None if the source doesn't say. Interpolated instances have
+    /// their default master's.
+    pub fn units_per_em(&self) -> Option<f64> {
+        self.units_per_em
+    }
+
+    /// Record the units per em of the font the glyph is from, warning if it's far from
+    /// the usual.
+    pub(crate) fn set_units_per_em(&mut self, units_per_em: Option<f64>) {
+        self.units_per_em = units_per_em;
+        if let Some(units_per_em) = units_per_em.filter(|u| !USUAL_UNITS_PER_EM.contains(u)) {
+            self.warnings.push(Warning::UnusualUnitsPerEm {
+                file: self.source.clone(),
+                glyph: self.name.clone(),
+                units_per_em,
+            });
+        }
+    }
+
+    /// Problems with the outline that don't stop us checking it.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// The designspace instance the outline was interpolated for, None for masters
+    /// and anything loaded directly.
+    pub fn instance(&self) -> Option<&str> {
+        self.instance.as_deref()
+    }
+
+    /// The designspace the glyph was loaded through, for masters as well as instances.
+    pub fn designspace(&self) -> Option<&Path> {
+        self.designspace.as_deref()
+    }
+
+    /// The glyph with `transform`, in font units (y-up), applied to its outline, e.g. a
+    /// slant to check a synthesized italic.
+    pub fn transformed(&self, transform: Affine) -> Result<Self, Error> {
+        let mut bezpath = self.bezpath.clone();
+        bezpath.apply_affine(Affine::FLIP_Y * transform * Affine::FLIP_Y);
+        ensure_finite(&self.source, &self.name, &bezpath)?;
+        Ok(self.with_bezpath(bezpath))
+    }
+
+    /// The glyph sheared, about the baseline, to lean at `italic_angle` as in
+    /// [`Glyph::italic_angle`], e.g. to check an oblique derived from it. Shearing
+    /// alone can't make contours overlap, but rounding the sheared outline, see
+    /// [`crate::Config::round_coords`], can.
+    pub fn slanted(&self, italic_angle: f64) -> Result<Self, Error> {
+        self.transformed(Affine::skew((-italic_angle).to_radians().tan(), 0.0))
+    }
+
+    /// Loads every glyph in a file, or an error for each glyph we couldn't load. Only
+    /// the masters of a designspace or variable font, see [`Selection::sources`].
+    pub fn from_file(file: impl AsRef<Path>) -> Vec<Result<Self, Error>> {
+        Self::iter_file(file.as_ref(), &Selection::default()).collect()
+    }
+
+    /// Loads every glyph in `bytes`, which hold a file in `format`, e.g. a font received
+    /// over the network. Glyphs are reported as coming from [`MEMORY_SOURCE`].
+    ///
+    /// Unlike loading a file, bytes that can't be parsed at all are an error rather than
+    /// a panic.
+    pub fn from_bytes(bytes: &[u8], format: Format) -> Vec<Result<Self, Error>> {
+        let source = Path::new(MEMORY_SOURCE);
+        match format {
+            Format::Glif => vec![norad::Glyph::parse_raw(bytes)
+                .map_err(|e| glif_parse_error(source, Some(bytes), &e))
+                .and_then(|glif| Self::from_glif(source, &glif, None))],
+            Format::Json => match std::str::from_utf8(bytes) {
+                Ok(raw) => Self::from_json(source, raw),
+                Err(e) => vec![Err(Error::Invalid {
+                    file: source.to_path_buf(),
+                    reason: format!("isn't UTF-8: {e}"),
+                })],
+            },
+            Format::Ttf => {
+                match crate::ttf::load_data(source, bytes.to_vec(), None, Sources::default(), None)
+                {
+                    Ok(glyphs) => glyphs.collect(),
+                    Err(reason) => vec![Err(Error::Invalid {
+                        file: source.to_path_buf(),
+                        reason,
+                    })],
+                }
+            }
+        }
+    }
+
+    /// `glyphs` as a `.json` input, which loads back to the same outlines and advances,
+    /// e.g. to attach a minimal reproduction of a verdict to a bug report.
+    pub fn export_json<'a>(glyphs: impl IntoIterator<Item = &'a Glyph>) -> String {
+        let json_glyphs = glyphs
+            .into_iter()
+            .map(|g| {
+                let mut bezpath = g.bezpath.clone();
+                bezpath.apply_affine(Affine::FLIP_Y);
+                JsonGlyph {
+                    name: g.name.to_string(),
+                    path_commands: bezpath.to_svg(),
+                    advance_width: g.advance,
+                }
+            })
+            .collect::<Vec<_>>();
+        serde_json::to_string_pretty(&json_glyphs)
+            .unwrap_or_else(|e| panic!("Unable to serialize glyphs: {e}"))
+    }
+
+    /// Like [`Glyph::from_file`] for each of `files` in turn, but lazily.
+    ///
+    /// A file isn't read until every glyph before it has been taken, and a .ttf is
+    /// read a glyph at a time, so dropping glyphs once they're checked keeps memory
+    /// roughly flat however much there is to check.
+    pub fn stream<P: AsRef<Path>>(
+        files: impl IntoIterator<Item = P>,
+    ) -> impl Iterator<Item = Result<Self, Error>> {
+        files
+            .into_iter()
+            .flat_map(|f| Self::iter_file(f.as_ref(), &Selection::default()))
+    }
+
+    /// Like [`Glyph::stream`] but only the glyphs of each .ttf whose glyph ids are in
+    /// `gids`, e.g. to bisect a large font. Other files are loaded whole.
+    pub fn stream_gids<P: AsRef<Path>>(
+        files: impl IntoIterator<Item = P>,
+        gids: RangeInclusive<u16>,
+    ) -> impl Iterator<Item = Result<Self, Error>> {
+        Self::stream_selected(
+            files,
+            Selection {
+                gids: Some(gids),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Like [`Glyph::stream`] but only the glyphs `selection` picks out.
+    pub fn stream_selected<P: AsRef<Path>>(
+        files: impl IntoIterator<Item = P>,
+        selection: Selection,
+    ) -> impl Iterator<Item = Result<Self, Error>> {
+        files
+            .into_iter()
+            .flat_map(move |f| Self::iter_file(f.as_ref(), &selection))
+    }
+
+    fn iter_file(
+        file: &Path,
+        selection: &Selection,
+    ) -> Box<dyn Iterator<Item = Result<Self, Error>>> {
+        match file.extension().and_then(|e| e.to_str()) {
+            Some("designspace") => Box::new(
+                crate::designspace::load(file, selection.sources, selection.location.as_deref())
+                    .into_iter(),
+            ),
+            Some("ufo") => {
+                Box::new(Self::from_ufo_file(file, selection.layers.as_deref()).into_iter())
+            }
+            Some("glif") => Box::new(std::iter::once(Self::from_glif_file(file))),
+            Some("json") => Box::new(Self::from_json_file(file).into_iter()),
+            Some("ttf") => Box::new(crate::ttf::load(
+                file,
+                selection.gids.clone(),
+                selection.sources,
+                selection.location.as_deref(),
+            )),
+            _ => panic!("No handler for {file:?}"),
+        }
+    }
+
+    /// Every loader comes through here so we only ever check sane outlines.
+    pub(crate) fn new(name: GlyphName, source: &Path, bezpath: BezPath) -> Result<Self, Error> {
+        ensure_finite(source, &name, &bezpath)?;
+        let mut warnings = zero_length_segments(source, &name, &bezpath)
+            .into_iter()
+            .collect::<Vec<_>>();
+        if contours(&bezpath).is_empty() {
+            warnings.push(Warning::NoContours {
+                file: source.to_path_buf(),
+                glyph: name.clone(),
+            });
+        }
+        warnings.extend(zero_area(source, &name, &bezpath));
+        Ok(Self {
+            name,
+            source: source.to_path_buf(),
+            bezpath,
+            instance: None,
+            designspace: None,
+            advance: None,
+            codepoints: Vec::new(),
+            overlap_flag: false,
+            components: Vec::new(),
+            italic_angle: None,
+            units_per_em: None,
+            warnings,
+        })
+    }
+
+    /// Components are flattened in if we have the `layer` to find them in.
+    fn from_glif(
+        file: &Path,
+        glif: &norad::Glyph,
+        layer: Option<&norad::Layer>,
+    ) -> Result<Self, Error> {
+        let mut bezpath = match layer {
+            Some(layer) => flatten_components(file, layer, glif, &mut Vec::new())?,
+            None => {
+                if !glif.components.is_empty() {
+                    eprintln!("WARNING: {file:?} ignoring components, check the whole UFO to include them");
+                }
+                glif.contours.to_bezpath(file, glif.name().as_str())?
+            }
+        };
+        // Font units and svg units don't agree on y-up.
+        // It's very disconcerting to see all the glyphs upside down in test renders
+        bezpath.apply_affine(Affine::FLIP_Y);
+        let mut glyph = Self::new(glif.name().as_str().into(), file, bezpath)?;
+        glyph.advance = Some(glif.width);
+        glyph.codepoints = glif.codepoints.iter().collect();
+        glyph.codepoints.sort();
+        glyph.overlap_flag = glif
+            .lib
+            .get(OVERLAP_FLAG_LIB_KEY)
+            .and_then(|v| v.as_boolean())
+            .unwrap_or_default();
+        glyph.components = glif
+            .components
+            .iter()
+            .map(|c| c.base.as_str().into())
+            .collect();
+
+        glyph
+            .warnings
+            .extend(off_curve_only(file, &glyph.name, &glif.contours));
+        Ok(glyph)
+    }
+
+    /// Contours from a UFO, font units and y-up, without the glyph they belong to.
+    pub(crate) fn from_contours(
+        file: &Path,
+        name: GlyphName,
+        contours: &[norad::Contour],
+    ) -> Result<Self, Error> {
+        let mut bezpath = contours.to_bezpath(file, name.as_str())?;
+        bezpath.apply_affine(Affine::FLIP_Y);
+        let mut glyph = Self::new(name, file, bezpath)?;
+        glyph
+            .warnings
+            .extend(off_curve_only(file, &glyph.name, contours));
+        Ok(glyph)
+    }
+
+    fn from_glif_file(file: &Path) -> Result<Self, Error> {
+        let glif = norad::Glyph::load(file).map_err(|e| glif_error(file, &e))?;
+        Self::from_glif(file, &glif, None)
+    }
+
+    /// Load pre-flattened outlines, see [`JsonGlyph`] for the format.
+    fn from_json_file(file: &Path) -> Vec<Result<Self, Error>> {
+        match std::fs::read_to_string(file) {
+            Ok(raw) => Self::from_json(file, &raw),
+            Err(e) => vec![Err(Error::Invalid {
+                file: file.to_path_buf(),
+                reason: format!("can't be read: {e}"),
+            })],
+        }
+    }
+
+    /// Pre-flattened outlines, read from `file`, see [`JsonGlyph`] for the format.
+    fn from_json(file: &Path, raw: &str) -> Vec<Result<Self, Error>> {
+        let json_glyphs: Vec<JsonGlyph> = match serde_json::from_str(raw) {
+            Ok(glyphs) => glyphs,
+            Err(e) => {
+                return vec![Err(Error::Invalid {
+                    file: file.to_path_buf(),
+                    reason: format!("can't be read: {}", error_chain(&e)),
+                })]
+            }
+        };
+        json_glyphs
+            .into_iter()
+            .map(|g| {
+                let mut bezpath =
+                    BezPath::from_svg(&g.path_commands).map_err(|e| Error::Outline {
+                        file: file.to_path_buf(),
+                        glyph: g.name.as_str().into(),
+                        reason: format!("bad path_commands: {}", error_chain(&e)),
+                    })?;
+                // Same y-up font units as a glif
+                bezpath.apply_affine(Affine::FLIP_Y);
+                Self::new(g.name.as_str().into(), file, bezpath).map(|mut glyph| {
+                    glyph.advance = g.advance_width;
+                    glyph
+                })
+            })
+            .collect()
+    }
+
+    /// Every glyph in every layer of the UFO at `ufo_dir`, or only in those whose names
+    /// match the glob `layers`.
+    fn from_ufo_file(ufo_dir: &Path, layers: Option<&str>) -> Vec<Result<Self, Error>> {
+        let ufo = match load_ufo(ufo_dir) {
+            Ok(ufo) => ufo,
+            Err(e) => return vec![Err(e)],
+        };
+        let color = ColorLayers::new(&ufo);
+        ufo.iter_layers()
+            .filter(|l| !color.is_color_layer(l))
+            .filter(|l| layers.is_none_or(|p| glob_matches(p, l.name())))
+            .flat_map(|l| Self::from_layer(ufo_dir, l, &color))
+            .map(|g| {
+                g.map(|mut g| {
+                    g.italic_angle = ufo.font_info.italic_angle;
+                    g.set_units_per_em(ufo.font_info.units_per_em.map(|u| *u));
+                    g
+                })
+            })
+            .collect()
+    }
+
+    /// Every glyph in one layer of the UFO at `ufo_dir`.
+    ///
+    /// Glyphs the layer has no .glif for are errors. Glyphs `color` says are only drawn
+    /// as layers of color glyphs are skipped.
+    pub(crate) fn from_layer(
+        ufo_dir: &Path,
+        layer: &norad::Layer,
+        color: &ColorLayers,
+    ) -> Vec<Result<Self, Error>> {
+        layer
+            .iter()
+            .filter(|g| !color.is_layer_glyph(g))
+            .map(|g| {
+                let Some(glif_file) = layer.get_path(g.name()) else {
+                    return Err(Error::Invalid {
+                        file: ufo_dir.to_path_buf(),
+                        reason: format!("layer {} has no file for {}", layer.name(), g.name()),
+                    });
+                };
+                Self::from_glif(&ufo_dir.join(layer.path()).join(glif_file), g, Some(layer))
+            })
+            .collect()
+    }
+}
+
+/// Where glyphs loaded by [`Glyph::from_bytes`] say they came from.
+pub const MEMORY_SOURCE: &str = "<memory>";
+
+/// A kind of file whose glyphs can be loaded from memory, see [`Glyph::from_bytes`].
+///
+/// UFOs and designspaces are directories and files referring to other files, so they
+/// can't be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Glif,
+    Json,
+    Ttf,
+}
+
+/// Which glyphs to load, where the input has more than we want to check.
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    /// Only glyphs of a .ttf with ids in this range
+    pub gids: Option<RangeInclusive<u16>>,
+    /// Only layers of a .ufo whose names match this glob, `*` for any run of
+    /// characters and `?` for any one, e.g. `public.background*`
+    pub layers: Option<String>,
+    /// The masters, the instances, or both, of a .designspace or variable .ttf. Only the
+    /// masters by default.
+    pub sources: Sources,
+    /// Check a .designspace or variable .ttf here rather than at its instances: values
+    /// by axis tag in user space, as a designer gives them, e.g. `("wght", 700.0)`.
+    /// Axes left out are at their default. The values are mapped as the font maps them,
+    /// by a designspace's axis maps or a font's avar.
+    pub location: Option<Vec<(String, f64)>>,
+}
+
+/// Which sources of a designspace, or locations of a variable font, to load.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Sources {
+    /// The masters, or a variable font's default location. Nothing is interpolated, as
+    /// interpolating every instance is expensive.
+    #[default]
+    Masters,
+    /// The instances interpolated from the masters, or a variable font's named instances
+    Instances,
+    /// Masters, then instances
+    Both,
+}
+
+impl Sources {
+    pub(crate) fn masters(&self) -> bool {
+        *self != Sources::Instances
+    }
+
+    pub(crate) fn instances(&self) -> bool {
+        *self != Sources::Masters
+    }
+}
+
+/// Whether all of `name` matches the glob `pattern`.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (pattern.as_bytes(), name.as_bytes());
+    // Where to resume if what follows the last * stops matching
+    let mut backtrack = None;
+    let (mut p, mut n) = (0, 0);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == b'?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, from)) => {
+                    p = star + 1;
+                    n = from + 1;
+                    backtrack = Some((star, from + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == b'*')
+}
+
+/// Load the UFO at `ufo_dir`. A glif that won't load is an error saying where it went
+/// wrong, anything else wrong with the UFO is fatal.
+pub(crate) fn load_ufo(ufo_dir: &Path) -> Result<norad::Font, Error> {
+    let ufo = norad::Font::load(ufo_dir).map_err(|e| {
+        glif_failure(&e).unwrap_or_else(|| panic!("Error loading {ufo_dir:?}: {e}"))
+    })?;
+    Ok(ufo)
+}
+
+/// Fonts are almost always 1000 or 2048 units per em, this far either side of them
+/// the source is probably misconfigured.
+const USUAL_UNITS_PER_EM: RangeInclusive<f64> = 256.0..=8192.0;
+
+/// The glif, if any, that stopped a UFO loading.
+pub(crate) fn glif_failure(e: &FontLoadError) -> Option<Error> {
+    let FontLoadError::Layer { source, .. } = e else {
+        return None;
+    };
+    match source.as_ref() {
+        LayerLoadError::Glyph { path, source, .. } => Some(glif_error(path, source)),
+        _ => None,
+    }
+}
+
+/// Why norad couldn't load the glif at `file`, and where if the XML is malformed.
+pub(crate) fn glif_error(file: &Path, e: &GlifLoadError) -> Error {
+    glif_parse_error(file, std::fs::read(file).ok().as_deref(), e)
+}
+
+/// Why norad couldn't parse `xml`, from `file`, and where if we have the XML to look at.
+fn glif_parse_error(file: &Path, xml: Option<&[u8]>, e: &GlifLoadError) -> Error {
+    Error::Glif {
+        file: file.to_path_buf(),
+        position: xml.and_then(|xml| xml_error_offset(xml).map(|offset| line_column(xml, offset))),
+        reason: error_chain(e),
+    }
+}
+
+/// The byte offset of the first thing in `xml` that isn't well formed, if anything.
+///
+/// norad doesn't say where it gave up, so read the file again to find out.
+fn xml_error_offset(xml: &[u8]) -> Option<usize> {
+    let mut reader = quick_xml::Reader::from_reader(xml);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Eof) => return None,
+            Ok(Event::Start(e) | Event::Empty(e)) => {
+                if e.attributes().any(|a| a.is_err()) {
+                    return Some(reader.buffer_position() as usize);
+                }
+            }
+            Ok(_) => (),
+            Err(_) => return Some(reader.error_position() as usize),
+        }
+    }
+}
+
+/// Line and column, from 1, of `offset` into `text`. Columns count bytes.
+fn line_column(text: &[u8], offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .map_or(0, |i| i + 1);
+    (
+        before.iter().filter(|b| **b == b'\n').count() + 1,
+        before.len() - line_start + 1,
+    )
+}
+
+/// Corrupt sources can contain NaN or infinite coordinates, which rendering can't cope with.
+pub(crate) fn ensure_finite(file: &Path, glyph: &GlyphName, path: &BezPath) -> Result<(), Error> {
+    match path
+        .elements()
+        .iter()
+        .flat_map(|el| match el {
+            PathEl::MoveTo(p) | PathEl::LineTo(p) => vec![*p],
+            PathEl::QuadTo(c, p) => vec![*c, *p],
+            PathEl::CurveTo(c0, c1, p) => vec![*c0, *c1, *p],
+            PathEl::ClosePath => vec![],
+        })
+        .find(|p| !p.is_finite())
+    {
+        Some(point) => Err(Error::NonFiniteCoordinate {
+            file: file.to_path_buf(),
+            glyph: glyph.clone(),
+            point,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Coincident consecutive points make segments with no length, which are untidy and
+/// can confuse overlap detection.
+fn zero_length_segments(file: &Path, glyph: &GlyphName, path: &BezPath) -> Option<Warning> {
+    let mut current = Point::ORIGIN;
+    let mut zero_length = Vec::new();
+    for el in path.elements() {
+        let (points, end) = match el {
+            PathEl::MoveTo(p) => {
+                current = *p;
+                continue;
+            }
+            PathEl::LineTo(p) => (vec![*p], *p),
+            PathEl::QuadTo(c, p) => (vec![*c, *p], *p),
+            PathEl::CurveTo(c0, c1, p) => (vec![*c0, *c1, *p], *p),
+            PathEl::ClosePath => continue,
+        };
+        if points.iter().all(|p| *p == current) {
+            zero_length.push(current);
+        }
+        current = end;
+    }
+    let first = *zero_length.first()?;
+    Some(Warning::ZeroLengthSegment {
+        file: file.to_path_buf(),
+        glyph: glyph.clone(),
+        count: zero_length.len(),
+        // Subtracting avoids a confusing -0
+        point: Point::new(first.x, 0.0 - first.y),
+    })
+}
+
+/// Contours wound opposite ways that enclose the same area in all leave a glyph with
+/// no area, e.g. a shape drawn again, reversed, to subtract it.
+fn zero_area(file: &Path, glyph: &GlyphName, path: &BezPath) -> Option<Warning> {
+    let areas = contours(path).iter().map(|c| c.area()).collect::<Vec<_>>();
+    let total = areas.iter().map(|a| a.abs()).sum::<f64>();
+    let net = areas.iter().sum::<f64>();
+    (total > 0.0 && net.abs() <= total * ZERO_AREA_TOLERANCE).then(|| Warning::ZeroArea {
+        file: file.to_path_buf(),
+        glyph: glyph.clone(),
+        count: areas.len(),
+    })
+}
+
+/// The outline of `glif` with components, recursively, drawn in place. Font units, y-up.
+///
+/// `visiting` is the chain of glyphs whose components led us here, to catch cycles.
+fn flatten_components(
+    file: &Path,
+    layer: &norad::Layer,
+    glif: &norad::Glyph,
+    visiting: &mut Vec<norad::Name>,
+) -> Result<BezPath, Error> {
+    if visiting.contains(glif.name()) {
+        return Err(Error::ComponentCycle {
+            file: file.to_path_buf(),
+            glyph: glif.name().as_str().into(),
+        });
+    }
+    visiting.push(glif.name().clone());
+
+    let mut bezpath = glif.contours.to_bezpath(file, glif.name().as_str())?;
+    for component in glif.components.iter() {
+        let Some(base) = layer.get_glyph(&component.base) else {
+            return Err(Error::MissingComponent {
+                file: file.to_path_buf(),
+                glyph: glif.name().as_str().into(),
+                base: component.base.as_str().into(),
+            });
+        };
+        let t = component.transform;
+        let mut component_path = flatten_components(file, layer, base, visiting)?;
+        component_path.apply_affine(Affine::new([
+            t.x_scale, t.xy_scale, t.yx_scale, t.y_scale, t.x_offset, t.y_offset,
+        ]));
+        bezpath.extend(component_path);
+    }
+
+    visiting.pop();
+    Ok(bezpath)
+}
+
+/// Split a path into one path per contour.
+pub(crate) fn contours(path: &BezPath) -> Vec<BezPath> {
+    let mut contours: Vec<BezPath> = Vec::new();
+    for el in path.elements() {
+        match (el, contours.last_mut()) {
+            (PathEl::MoveTo(..), _) | (_, None) => contours.push(BezPath::from_vec(vec![*el])),
+            (_, Some(contour)) => contour.push(*el),
+        }
+    }
+    contours
+}
+
+/// Contours made only of off-curves: legal, but rarely what was meant.
+fn off_curve_only(file: &Path, glyph: &GlyphName, contours: &[norad::Contour]) -> Option<Warning> {
+    let count = contours
+        .iter()
+        .filter(|c| {
+            !c.points.is_empty() && c.points.iter().all(|p| p.typ == norad::PointType::OffCurve)
+        })
+        .count();
+    (count > 0).then(|| Warning::OffCurveOnlyContour {
+        file: file.to_path_buf(),
+        glyph: glyph.clone(),
+        count,
+    })
+}
+
+trait ToBezPath {
+    fn to_bezpath(&self, file: &Path, glyph_name: &str) -> Result<BezPath, Error>;
+}
+
+impl ToBezPath for [norad::Contour] {
+    /// Basically copied from <https://github.com/googlefonts/fontc/blob/9b7a5634dc0487d52af7a1528520306fc2c6941b/ufo2fontir/src/toir.rs#L31C1-L59C2>
+    ///
+    /// Contours made only of off-curves are drawn as TrueType quadratics with every
+    /// on-curve implied, as the UFO spec says.
+    ///
+    /// Quadratic and cubic segments may mix freely within a contour, each segment
+    /// takes its type from the on-curve that ends it, wrapping around for the one
+    /// that closes the contour. A `qcurve` takes any number of off-curves, implying
+    /// on-curves between them; a `curve` takes at most two, drawing a line or a
+    /// quadratic for fewer, and more is an [`Error::Outline`].
+    fn to_bezpath(&self, file: &Path, glyph_name: &str) -> Result<BezPath, Error> {
+        let bad_outline = |e: fontir::error::PathConversionError| Error::Outline {
+            file: file.to_path_buf(),
+            glyph: glyph_name.into(),
+            reason: e.to_string(),
+        };
+        // The builder can't be reset, and its buffer becomes the BezPath, so there's
+        // nothing to reuse between glyphs. Sizing the buffer up front saves regrowing it:
+        // a segment per point at most, plus a move and a close per contour.
+        let estimated_elements = self.iter().map(|contour| contour.points.len() + 2).sum();
+        let mut path_builder = GlyphPathBuilder::new(glyph_name.into(), estimated_elements);
+
+        for contour in self {
+            for node in contour.points.iter() {
+                match node.typ {
+                    norad::PointType::Move => path_builder.move_to((node.x, node.y)),
+                    norad::PointType::Line => path_builder.line_to((node.x, node.y)),
+                    norad::PointType::QCurve => path_builder.qcurve_to((node.x, node.y)),
+                    norad::PointType::Curve => path_builder.curve_to((node.x, node.y)),
+                    norad::PointType::OffCurve => path_builder.offcurve((node.x, node.y)),
+                }
+                .map_err(bad_outline)?;
+            }
+            path_builder.end_path().map_err(bad_outline)?;
+        }
+
+        path_builder.build().map_err(bad_outline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use fontdrasil::types::GlyphName;
+    use kurbo::{Affine, BezPath, Point};
+    use norad::{Contour, ContourPoint, PointType};
+
+    use super::{glob_matches, zero_length_segments, Format, Selection};
+    use crate::{Error, Glyph, Warning};
+
+    fn glif(points: &[(f64, f64, PointType)]) -> norad::Glyph {
+        let mut glif = norad::Glyph::new("test");
+        glif.contours.push(Contour::new(
+            points
+                .iter()
+                .map(|(x, y, typ)| ContourPoint::new(*x, *y, typ.clone(), false, None, None, None))
+                .collect(),
+            None,
+            None,
+        ));
+        glif
+    }
+
+    #[test]
+    fn duplicate_points() {
+        let mut path =
+            BezPath::from_svg("M0,0 L100,0 L100,0 L100,100 L0,100 Z M200,0 L300,0 L300,100 Z")
+                .unwrap();
+        path.apply_affine(Affine::FLIP_Y);
+        let Some(Warning::ZeroLengthSegment { count, point, .. }) =
+            zero_length_segments(Path::new("dup.json"), &GlyphName::new("dup"), &path)
+        else {
+            panic!("Duplicate point not found");
+        };
+        assert_eq!((1, Point::new(100.0, 0.0)), (count, point));
+    }
+
+    #[test]
+    fn no_contours() {
+        let glyph = Glyph::new(
+            GlyphName::new("space"),
+            Path::new("space.json"),
+            BezPath::new(),
+        )
+        .unwrap();
+        assert!(matches!(glyph.warnings(), [Warning::NoContours { .. }]));
+        let result = glyph.check(&crate::Config::default()).unwrap();
+        assert!(result.overlap.is_none());
+    }
+
+    #[test]
+    fn zero_area() {
+        // Drawn, then drawn again reversed
+        let path = BezPath::from_svg("M0,0 L100,0 L100,100 L0,100 Z M0,0 L0,100 L100,100 L100,0 Z")
+            .unwrap();
+        let glyph = Glyph::new(GlyphName::new("gone"), Path::new("gone.json"), path).unwrap();
+        assert!(matches!(
+            glyph.warnings(),
+            [Warning::ZeroArea { count: 2, .. }]
+        ));
+
+        let path = BezPath::from_svg("M0,0 L100,0 L100,100 L0,100 Z M25,25 L25,75 L75,75 L75,25 Z")
+            .unwrap();
+        let o = Glyph::new(GlyphName::new("o"), Path::new("o.json"), path).unwrap();
+        assert!(o.warnings().is_empty());
+    }
+
+    #[test]
+    fn no_duplicate_points() {
+        let path = BezPath::from_svg("M0,0 L100,0 L100,100 Z").unwrap();
+        assert!(zero_length_segments(Path::new("ok.json"), &GlyphName::new("ok"), &path).is_none());
+    }
+
+    #[test]
+    fn off_curve_only_contour() {
+        let glif = glif(&[
+            (0.0, 0.0, PointType::OffCurve),
+            (600.0, 0.0, PointType::OffCurve),
+            (600.0, 600.0, PointType::OffCurve),
+            (0.0, 600.0, PointType::OffCurve),
+        ]);
+        let glyph = Glyph::from_glif(Path::new("test.glif"), &glif, None).unwrap();
+        assert!(!glyph.bezpath().is_empty());
+        assert!(matches!(
+            glyph.warnings(),
+            [Warning::OffCurveOnlyContour { count: 1, .. }]
+        ));
+    }
+
+    #[test]
+    fn unreadable_contour_is_an_error() {
+        // Three off-curves can't make a cubic
+        let glif = glif(&[
+            (0.0, 0.0, PointType::Move),
+            (100.0, 0.0, PointType::OffCurve),
+            (200.0, 0.0, PointType::OffCurve),
+            (300.0, 0.0, PointType::OffCurve),
+            (300.0, 300.0, PointType::Curve),
+        ]);
+        assert!(matches!(
+            Glyph::from_glif(Path::new("test.glif"), &glif, None),
+            Err(Error::Outline { .. })
+        ));
+    }
+
+    #[test]
+    fn outline_hash_follows_the_outline() {
+        let testdata =
+            std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/testdata");
+        let hash = |file: &str| {
+            Glyph::from_file(testdata.join(file))
+                .pop()
+                .unwrap()
+                .unwrap()
+                .outline_hash()
+        };
+        assert_eq!(
+            hash("Overlaps.ufo/glyphs/plus.glif"),
+            hash("Layers.ufo/glyphs/plus.glif")
+        );
+        assert_ne!(
+            hash("Layers.ufo/glyphs/plus.glif"),
+            hash("Layers.ufo/glyphs.public.background/plus.glif")
+        );
+    }
+
+    #[test]
+    fn layers_selected_by_glob() {
+        let ufo = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/testdata/Layers.ufo");
+        let layers = |pattern: &str| {
+            let mut sources = Glyph::stream_selected(
+                [&ufo],
+                Selection {
+                    layers: Some(pattern.to_string()),
+                    ..Default::default()
+                },
+            )
+            .map(|g| {
+                let source = g.unwrap().source.clone();
+                let layer = source.parent().unwrap().file_name().unwrap();
+                layer.to_str().unwrap().to_string()
+            })
+            .collect::<Vec<_>>();
+            sources.dedup();
+            sources
+        };
+        assert_eq!(vec!["glyphs.public.background"], layers("public.back*"));
+        assert_eq!(vec!["glyphs"], layers("public.default"));
+        assert_eq!(
+            vec!["glyphs", "glyphs.public.background"],
+            layers("public.*")
+        );
+        assert!(layers("background*").is_empty());
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("a*c", "abbc"));
+        assert!(glob_matches("a?c*", "abcde"));
+        assert!(glob_matches("*.background", "com.x.background"));
+        assert!(!glob_matches("a*c", "abcd"));
+        assert!(!glob_matches("?", ""));
+    }
+
+    #[test]
+    fn color_layers_skipped() {
+        let ufo = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/testdata/Color.ufo");
+        let mut names = Glyph::from_file(ufo)
+            .into_iter()
+            .map(|g| g.unwrap().name().as_str().to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        // plus.horizontal and plus.vertical are plus's color layers, color.1 is a layer of bar
+        assert_eq!(vec!["bar", "plus"], names);
+    }
+
+    #[test]
+    fn transformed_in_font_units() {
+        let mut path = BezPath::from_svg(
+            "M250,0 L350,0 L350,600 L250,600 Z M0,250 L600,250 L600,350 L0,350 Z",
+        )
+        .unwrap();
+        path.apply_affine(Affine::FLIP_Y);
+        let plus = Glyph::new(GlyphName::new("plus"), Path::new("plus.json"), path).unwrap();
+
+        let slanted = plus
+            .transformed(Affine::new([1.0, 0.0, 0.2, 1.0, 0.0, 0.0]))
+            .unwrap();
+        // 350,600 leans right by a fifth of its height, still y-down
+        assert_eq!(
+            Some(Point::new(470.0, -600.0)),
+            slanted.bezpath.elements()[2].end_point()
+        );
+        // Mirroring reverses every contour, the overlap is still there
+        let mirrored = plus.transformed(Affine::FLIP_X).unwrap();
+        let config = crate::Config::default();
+        assert!(mirrored.check(&config).unwrap().overlap.is_some());
+    }
+
+    #[test]
+    fn malformed_glif_located() {
+        let file = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/testdata/Broken.glif");
+        let glyphs = Glyph::from_file(file);
+        // The point on line 6 is never closed, which shows when contour ends on line 8
+        let [Err(Error::Glif { position, .. })] = glyphs.as_slice() else {
+            panic!("Expected a glif error, got {glyphs:?}");
+        };
+        assert_eq!(Some(8), position.map(|(line, _)| line));
+    }
+
+    #[test]
+    fn unusual_units_per_em_reported() {
+        let ufo_dir = std::env::temp_dir().join("glyph_overlaps_small_em.ufo");
+        let _ = std::fs::remove_dir_all(&ufo_dir);
+        let mut font = norad::Font::new();
+        font.font_info.units_per_em = Some(16.into());
+        font.default_layer_mut()
+            .insert_glyph(norad::Glyph::new("space"));
+        font.save(&ufo_dir).unwrap();
+
+        let glyphs = Glyph::from_file(&ufo_dir);
+        let [Ok(glyph)] = glyphs.as_slice() else {
+            panic!("Expected one glyph, got {glyphs:?}");
+        };
+        assert!(
+            glyph.warnings().iter().any(|w| matches!(
+                w,
+                Warning::UnusualUnitsPerEm { units_per_em, .. } if *units_per_em == 16.0
+            )),
+            "{:?}",
+            glyph.warnings()
+        );
+    }
+
+    #[test]
+    fn advance_widths() {
+        let testdata =
+            std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/testdata");
+        for file in ["Overlaps.ufo", "Overlaps.ttf"] {
+            let plus = Glyph::from_file(testdata.join(file))
+                .into_iter()
+                .map(|g| g.unwrap())
+                .find(|g| g.name.as_str() == "plus")
+                .unwrap();
+            assert_eq!(Some(600.0), plus.advance(), "{file}");
+        }
+    }
+
+    #[test]
+    fn codepoints() {
+        let testdata =
+            std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/testdata");
+        for file in ["Overlaps.ufo", "Overlaps.ttf"] {
+            let glyphs = Glyph::from_file(testdata.join(file))
+                .into_iter()
+                .map(|g| g.unwrap())
+                .collect::<Vec<_>>();
+            let codepoints = |name: &str| {
+                glyphs
+                    .iter()
+                    .find(|g| g.name.as_str() == name)
+                    .unwrap()
+                    .codepoints()
+                    .to_vec()
+            };
+            assert_eq!(vec!['+'], codepoints("plus"), "{file}");
+            assert_eq!(Vec::<char>::new(), codepoints(".notdef"), "{file}");
+        }
+    }
+
+    #[test]
+    fn slanted_by_italic_angle() {
+        let testdata =
+            std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/testdata");
+        let bar = Glyph::from_file(testdata.join("Oblique.ufo"))
+            .pop()
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some(-12.0), bar.italic_angle());
+        let slanted = bar.slanted(-12.0).unwrap();
+        // Leaning right, the top moves right and the baseline stays put, y-down
+        let bbox = kurbo::Shape::bounding_box(slanted.bezpath());
+        let lean = 500.0 * 12f64.to_radians().tan();
+        assert!((bbox.x1 - (200.0 + lean)).abs() < 1e-9, "{bbox:?}");
+        assert_eq!((100.0, -500.0, 0.0), (bbox.x0, bbox.y0, bbox.y1));
+
+        let plus = Glyph::from_file(testdata.join("Overlaps.ttf"))
+            .into_iter()
+            .map(|g| g.unwrap())
+            .find(|g| g.name.as_str() == "plus")
+            .unwrap();
+        assert_eq!(Some(0.0), plus.italic_angle());
+    }
+
+    #[test]
+    fn components() {
+        let testdata =
+            std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/testdata");
+        for file in ["Overlaps.ufo", "Overlaps.ttf"] {
+            let glyphs = Glyph::from_file(testdata.join(file))
+                .into_iter()
+                .map(|g| g.unwrap())
+                .collect::<Vec<_>>();
+            let components = |name: &str| {
+                glyphs
+                    .iter()
+                    .find(|g| g.name.as_str() == name)
+                    .unwrap()
+                    .components()
+                    .iter()
+                    .map(|c| c.as_str())
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(vec!["plus"], components("plus.comp"), "{file}");
+            assert_eq!(vec!["square", "square"], components("squares"), "{file}");
+            assert!(components("plus").is_empty(), "{file}");
+        }
+    }
+
+    #[test]
+    fn overlap_flag_read() {
+        let testdata =
+            std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/testdata");
+        for file in ["Overlaps.ufo", "Overlaps.ttf"] {
+            let flagged = Glyph::from_file(testdata.join(file))
+                .into_iter()
+                .map(|g| g.unwrap())
+                .filter(|g| g.has_overlap_flag())
+                .map(|g| g.name.to_string())
+                .collect::<Vec<_>>();
+            // A simple glyph and a composite
+            assert_eq!(vec!["plus", "squares"], flagged, "{file}");
+        }
+    }
+
+    #[test]
+    fn stream_matches_from_file() {
+        let testdata =
+            std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/testdata");
+        let files = [testdata.join("Overlaps.ufo"), testdata.join("Overlaps.ttf")];
+        let names = |glyphs: Vec<Result<Glyph, Error>>| {
+            glyphs
+                .into_iter()
+                .map(|g| g.unwrap().describe())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(files.iter().flat_map(Glyph::from_file).collect()),
+            names(Glyph::stream(&files).collect())
+        );
+    }
+
+    #[test]
+    fn from_bytes() {
+        let testdata =
+            std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/testdata");
+        let read = |name: &str| std::fs::read(testdata.join(name)).unwrap();
+        let names = |glyphs: Vec<Result<Glyph, Error>>| {
+            glyphs
+                .into_iter()
+                .map(|g| g.unwrap().name().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(Glyph::from_file(testdata.join("Overlaps.ttf"))),
+            names(Glyph::from_bytes(&read("Overlaps.ttf"), Format::Ttf))
+        );
+        let plus = Glyph::from_bytes(&read("Overlaps.ufo/glyphs/plus.glif"), Format::Glif)
+            .pop()
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            ("plus", Path::new(super::MEMORY_SOURCE)),
+            (plus.name().as_str(), plus.source())
+        );
+        let json = br#"[{"name": "bar", "path_commands": "M0,0 L10,0 L10,10 Z"}]"#;
+        assert_eq!(vec!["bar"], names(Glyph::from_bytes(json, Format::Json)));
+
+        assert!(matches!(
+            Glyph::from_bytes(b"not a font", Format::Ttf).as_slice(),
+            [Err(Error::Invalid { .. })]
+        ));
+        assert!(matches!(
+            Glyph::from_bytes(&read("Broken.glif"), Format::Glif).as_slice(),
+            [Err(Error::Glif {
+                position: Some((8, _)),
+                ..
+            })]
+        ));
+    }
+
+    #[test]
+    fn mixed_curves() {
+        let file = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/testdata/Mixed.glif");
+        let mixed = Glyph::from_file(file).pop().unwrap().unwrap();
+        // The leading off-curve belongs to the closing qcurve, the qcurve with two
+        // off-curves implies an on-curve between them
+        assert_eq!(
+            "M0,0 Q100,0 150,-50 Q200,-100 200,-200 C200,-300 100,-400 0,-400 \
+             L-100,-400 Q-100,-200 0,0 Z",
+            mixed.bezpath.to_svg()
+        );
+
+        let too_many = glif(&[
+            (0.0, 0.0, PointType::Line),
+            (100.0, 0.0, PointType::OffCurve),
+            (200.0, 100.0, PointType::OffCurve),
+            (200.0, 200.0, PointType::OffCurve),
+            (100.0, 300.0, PointType::Curve),
+        ]);
+        assert!(matches!(
+            Glyph::from_glif(Path::new("mixed.glif"), &too_many, None),
+            Err(Error::Outline { .. })
+        ));
+    }
+
+    #[test]
+    fn unreadable_json_is_an_error() {
+        let glyphs = Glyph::from_file("no/such/outlines.json");
+        assert!(
+            matches!(glyphs.as_slice(), [Err(Error::Invalid { reason, .. })] if reason.contains("can't be read")),
+            "{glyphs:?}"
+        );
+    }
+
+    #[test]
+    fn exported_json_loads_back() {
+        let testdata =
+            std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/testdata");
+        let glyphs = [testdata.join("Overlaps.ufo"), testdata.join("Mixed.glif")]
+            .iter()
+            .flat_map(Glyph::from_file)
+            .map(|g| g.unwrap())
+            .collect::<Vec<_>>();
+        let json = Glyph::export_json(&glyphs);
+        let loaded = Glyph::from_bytes(json.as_bytes(), Format::Json)
+            .into_iter()
+            .map(|g| g.unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(glyphs.len(), loaded.len());
+        for (glyph, loaded) in glyphs.iter().zip(loaded.iter()) {
+            assert_eq!(
+                (glyph.name(), glyph.bezpath().to_svg(), glyph.advance()),
+                (loaded.name(), loaded.bezpath().to_svg(), loaded.advance())
+            );
+        }
+    }
+}
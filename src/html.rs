@@ -0,0 +1,216 @@
+//! A self-contained HTML page of the glyphs that need the overlap flag, for review.
+//!
+//! Diff thumbnails are embedded as base64 PNGs so the page can be shared as one file.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::{report::Report, CheckResult, Config, Error, Glyph, MasterMatrix};
+
+/// The largest width or height, in pixels, of a thumbnail.
+const THUMBNAIL_SIZE: u32 = 256;
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; } \
+    table { border-collapse: collapse; } \
+    td, th { border: 1px solid #ccc; padding: 0.5em; text-align: left; vertical-align: top; } \
+    img { background: white; border: 1px solid #eee; } \
+    .warning { color: #a60; } .error { color: #c00; } \
+    .flagged { background: #fdd; } .differs th { color: #c00; }";
+
+/// A PNG of the glyph's diff image scaled to fit a thumbnail, see [`Glyph::diff_image`].
+pub fn thumbnail(glyph: &Glyph, config: &Config) -> Result<Vec<u8>, Error> {
+    let config = Config {
+        max_pixmap_size: THUMBNAIL_SIZE,
+        // Scale is only ever reduced to fit, ask for more than fits
+        scale_x: f64::MAX,
+        scale_y: f64::MAX,
+        ..config.clone()
+    };
+    let image = glyph.diff_image(&config)?;
+    Ok(image
+        .encode_png()
+        .unwrap_or_else(|e| panic!("Failed to encode png for {}: {e}", glyph.name())))
+}
+
+/// A page listing every result that needs the overlap flag, with its thumbnail if
+/// there is one, then every error.
+///
+/// `thumbnails` are PNGs, see [`thumbnail`], in the same order as `results`.
+pub fn to_html(
+    results: &[CheckResult],
+    thumbnails: &[Option<Vec<u8>>],
+    errors: &[Error],
+    truncated: bool,
+) -> String {
+    let mut html = header(results, errors, truncated);
+
+    let flagged = results
+        .iter()
+        .zip(thumbnails.iter().map(Some).chain(std::iter::repeat(None)))
+        .filter(|(r, _)| r.overlap.is_some())
+        .collect::<Vec<_>>();
+    if !flagged.is_empty() {
+        html.push_str("<table>\n<tr><th>Glyph</th><th>Why</th><th>Diff</th></tr>\n");
+        for (result, thumbnail) in flagged {
+            let Some(overlap) = &result.overlap else {
+                continue;
+            };
+            let mut glyph = escape(&result.describe());
+            if !result.codepoints.is_empty() {
+                glyph.push_str(&format!("<br>{}", result.codepoint_labels()));
+            }
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}<br>{} region(s), about {:.0} square units, {:.1}% of the glyph's ink",
+                glyph,
+                escape(&overlap.to_string()),
+                overlap.regions,
+                overlap.area,
+                overlap.area_percent()
+            ));
+            for warning in result.warnings.iter() {
+                html.push_str(&format!(
+                    "<br><span class=\"warning\">{}</span>",
+                    escape(&warning.to_string())
+                ));
+            }
+            html.push_str("</td><td>");
+            if let Some(Some(png)) = thumbnail {
+                html.push_str(&format!(
+                    "<img src=\"data:image/png;base64,{}\" alt=\"{}\">",
+                    STANDARD.encode(png),
+                    escape(result.name.as_str())
+                ));
+            }
+            html.push_str("</td></tr>\n");
+        }
+        html.push_str("</table>\n");
+    }
+
+    if !errors.is_empty() {
+        html.push_str("<h2>Errors</h2>\n<ul>\n");
+        for error in errors.iter() {
+            html.push_str(&format!(
+                "<li class=\"error\">{}</li>\n",
+                escape(&error.to_string())
+            ));
+        }
+        html.push_str("</ul>\n");
+    }
+    html.push_str(FOOTER);
+    html
+}
+
+/// Just the counts, as a page.
+pub fn summary_to_html(results: &[CheckResult], errors: &[Error], truncated: bool) -> String {
+    header(results, errors, truncated) + FOOTER
+}
+
+/// A table of which masters flag each glyph, glyphs the masters disagree on
+/// highlighted.
+pub fn master_matrix_to_html(matrix: &MasterMatrix) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>glyph_overlaps</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+         <h1>Glyphs that need the overlap flag, by master</h1>\n<p>{} glyph(s), {} \
+         flagged in some masters but not others</p>\n",
+        matrix.glyphs.len(),
+        matrix.glyphs.iter().filter(|g| g.differs()).count()
+    );
+    html.push_str("<table>\n<tr><th>Glyph</th>");
+    for master in matrix.masters.iter() {
+        html.push_str(&format!("<th>{}</th>", escape(master)));
+    }
+    html.push_str("</tr>\n");
+    for row in matrix.glyphs.iter() {
+        html.push_str(&format!(
+            "<tr{}><th>{}</th>",
+            if row.differs() {
+                " class=\"differs\""
+            } else {
+                ""
+            },
+            escape(row.name.as_str())
+        ));
+        for flagged in row.flagged.iter() {
+            html.push_str(match flagged {
+                Some(true) => "<td class=\"flagged\">flagged</td>",
+                Some(false) => "<td>clean</td>",
+                None => "<td></td>",
+            });
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+    html.push_str(FOOTER);
+    html
+}
+
+const FOOTER: &str = "</body>\n</html>\n";
+
+/// The start of the page, up to and including the summary.
+fn header(results: &[CheckResult], errors: &[Error], truncated: bool) -> String {
+    let summary = Report::new(results, errors)
+        .truncated(truncated)
+        .summary()
+        .to_string();
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>glyph_overlaps</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+         <h1>Glyphs that need the overlap flag</h1>\n<p>{}</p>\n",
+        escape(&summary)
+    )
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{Config, Glyph};
+
+    use super::{escape, thumbnail, to_html};
+
+    #[test]
+    fn flagged_glyphs_get_thumbnails() {
+        let file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/testdata/Overlaps.ufo");
+        let config = Config::default();
+        let glyphs = Glyph::from_file(file)
+            .into_iter()
+            .map(|g| g.unwrap())
+            .collect::<Vec<_>>();
+        let results = glyphs
+            .iter()
+            .map(|g| g.check(&config).unwrap())
+            .collect::<Vec<_>>();
+        let thumbnails = glyphs
+            .iter()
+            .zip(results.iter())
+            .map(|(g, r)| r.overlap.as_ref().map(|_| thumbnail(g, &config).unwrap()))
+            .collect::<Vec<_>>();
+        let html = to_html(&results, &thumbnails, &[], false);
+
+        let flagged = results.iter().filter(|r| r.overlap.is_some()).count();
+        assert!(flagged > 0);
+        assert_eq!(flagged, html.matches("data:image/png;base64,").count());
+        assert!(!html.contains("square.glif"), "{html}");
+    }
+
+    #[test]
+    fn escaped() {
+        assert_eq!("a&lt;b&gt; &amp; &quot;c&quot;", escape("a<b> & \"c\""));
+    }
+}
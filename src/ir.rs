@@ -0,0 +1,205 @@
+//! Checking glyphs already in fontir's IR, for builds such as fontc that have them in hand.
+
+use std::{collections::HashMap, path::Path};
+
+use fontdrasil::{coords::NormalizedLocation, types::GlyphName};
+use kurbo::{Affine, BezPath};
+use rayon::prelude::*;
+
+use crate::{CheckResult, Config, Error, Glyph};
+
+/// Check every source of every exported glyph, without reloading anything from disk.
+///
+/// Components are flattened using the other glyphs in `glyphs`, at the same location.
+/// `source` is what results and errors say the glyphs came from, typically the file
+/// the IR was built from. Results for sources other than the default carry the
+/// location, e.g. `wght=1`, as their instance.
+pub fn check_ir_glyphs(
+    source: &Path,
+    glyphs: &[fontir::ir::Glyph],
+    config: &Config,
+) -> Vec<Result<CheckResult, Error>> {
+    let by_name = glyphs
+        .iter()
+        .map(|g| (&g.name, g))
+        .collect::<HashMap<_, _>>();
+    let work = glyphs
+        .iter()
+        .filter(|g| g.emit_to_binary)
+        .flat_map(|g| {
+            // Default first, then in a stable order
+            let mut locations = g.sources().keys().collect::<Vec<_>>();
+            locations.sort_by(|a, b| (!a.is_default(), a).cmp(&(!b.is_default(), b)));
+            locations.into_iter().map(move |loc| (g, loc))
+        })
+        .collect::<Vec<_>>();
+    work.par_iter()
+        .map(|(glyph, loc)| {
+            let mut bezpath = flatten(source, &by_name, &glyph.name, loc, &mut Vec::new())?;
+            // Font units and svg units don't agree on y-up
+            bezpath.apply_affine(Affine::FLIP_Y);
+            let advance = glyph.sources()[*loc].width;
+            let mut codepoints = glyph
+                .codepoints
+                .iter()
+                .filter_map(|c| char::from_u32(*c))
+                .collect::<Vec<_>>();
+            codepoints.sort();
+            let mut glyph = Glyph::new(glyph.name.clone(), source, bezpath)?;
+            glyph.advance = Some(advance);
+            glyph.codepoints = codepoints;
+            if !loc.is_default() {
+                glyph.instance = Some(describe(loc));
+            }
+            glyph.check(config)
+        })
+        .collect()
+}
+
+/// The outline of `name` at `loc` with components, recursively, drawn in place. Font units, y-up.
+///
+/// `visiting` is the chain of glyphs whose components led us here, to catch cycles.
+fn flatten(
+    source: &Path,
+    glyphs: &HashMap<&GlyphName, &fontir::ir::Glyph>,
+    name: &GlyphName,
+    loc: &NormalizedLocation,
+    visiting: &mut Vec<GlyphName>,
+) -> Result<BezPath, Error> {
+    if visiting.contains(name) {
+        return Err(Error::ComponentCycle {
+            file: source.to_path_buf(),
+            glyph: name.clone(),
+        });
+    }
+    let Some(instance) = glyphs[name].sources().get(loc) else {
+        return Err(Error::Outline {
+            file: source.to_path_buf(),
+            glyph: visiting.last().unwrap_or(name).clone(),
+            reason: format!("component {name} has no source at {}", describe(loc)),
+        });
+    };
+    visiting.push(name.clone());
+
+    let mut bezpath = BezPath::new();
+    for contour in instance.contours.iter() {
+        bezpath.extend(contour.iter());
+    }
+    for component in instance.components.iter() {
+        if !glyphs.contains_key(&component.base) {
+            return Err(Error::MissingComponent {
+                file: source.to_path_buf(),
+                glyph: name.clone(),
+                base: component.base.clone(),
+            });
+        }
+        let mut component_path = flatten(source, glyphs, &component.base, loc, visiting)?;
+        component_path.apply_affine(component.transform);
+        bezpath.extend(component_path);
+    }
+
+    visiting.pop();
+    Ok(bezpath)
+}
+
+/// E.g. `wdth=-0.5, wght=1`
+fn describe(loc: &NormalizedLocation) -> String {
+    loc.iter()
+        .map(|(tag, coord)| format!("{tag}={}", coord.to_f32()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        path::Path,
+    };
+
+    use font_types::Tag;
+    use fontdrasil::{
+        coords::{NormalizedCoord, NormalizedLocation},
+        types::GlyphName,
+    };
+    use fontir::ir::{Component, GlyphInstance};
+    use kurbo::{Affine, BezPath};
+
+    use super::check_ir_glyphs;
+    use crate::Config;
+
+    fn rect(x0: f64, y0: f64, x1: f64, y1: f64) -> BezPath {
+        BezPath::from_svg(&format!("M{x0},{y0} L{x1},{y0} L{x1},{y1} L{x0},{y1} Z")).unwrap()
+    }
+
+    fn glyph(
+        name: &str,
+        emit: bool,
+        sources: Vec<(f32, Vec<BezPath>, Vec<Component>)>,
+    ) -> fontir::ir::Glyph {
+        let wght = Tag::new(b"wght");
+        fontir::ir::Glyph::new(
+            GlyphName::new(name),
+            emit,
+            HashSet::new(),
+            sources
+                .into_iter()
+                .map(|(pos, contours, components)| {
+                    let mut loc = NormalizedLocation::new();
+                    loc.insert(wght, NormalizedCoord::new(pos));
+                    (
+                        loc,
+                        GlyphInstance {
+                            contours,
+                            components,
+                            ..Default::default()
+                        },
+                    )
+                })
+                .collect::<HashMap<_, _>>(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn checks_each_source_with_components() {
+        // Not exported, only used as a component
+        let bar = glyph(
+            "_bar",
+            false,
+            vec![
+                (0.0, vec![rect(0.0, 0.0, 100.0, 500.0)], vec![]),
+                (1.0, vec![rect(0.0, 0.0, 300.0, 500.0)], vec![]),
+            ],
+        );
+        let component = |dx| Component {
+            base: GlyphName::new("_bar"),
+            transform: Affine::translate((dx, 0.0)),
+        };
+        let bars = glyph(
+            "bars",
+            true,
+            vec![
+                (0.0, vec![], vec![component(0.0), component(200.0)]),
+                (1.0, vec![], vec![component(0.0), component(200.0)]),
+            ],
+        );
+
+        let results = check_ir_glyphs(
+            Path::new("Bars.designspace"),
+            &[bar, bars],
+            &Config::default(),
+        )
+        .into_iter()
+        .map(|r| r.unwrap())
+        .map(|r| (r.name.as_str().to_string(), r.instance, r.overlap.is_some()))
+        .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ("bars".to_string(), None, false),
+                ("bars".to_string(), Some("wght=1".to_string()), true),
+            ],
+            results
+        );
+    }
+}
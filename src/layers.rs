@@ -0,0 +1,94 @@
+//! Checking a UFO's glyphs against reference outlines in another of its layers.
+
+use std::{collections::HashMap, path::Path};
+
+use rayon::prelude::*;
+
+use crate::{color::ColorLayers, glyph::load_ufo, CheckResult, Config, Error, Glyph};
+
+/// Verdicts for the same glyph in the default layer and a reference layer.
+#[derive(Debug)]
+pub struct LayerComparison {
+    pub foreground: CheckResult,
+    pub reference: CheckResult,
+}
+
+impl LayerComparison {
+    /// Whether the two layers disagree about needing the overlap flag.
+    pub fn differs(&self) -> bool {
+        self.foreground.overlap.is_some() != self.reference.overlap.is_some()
+    }
+}
+
+/// Check every glyph of the default layer of `ufo_dir` that also has an outline in `layer`,
+/// typically `public.background`, and both outlines.
+///
+/// Glyphs only in one of the layers are ignored. Errors loading the reference layer come
+/// first, then results in default layer order.
+pub fn compare_layers(
+    ufo_dir: &Path,
+    layer: &str,
+    config: &Config,
+) -> Vec<Result<LayerComparison, Error>> {
+    let ufo = match load_ufo(ufo_dir) {
+        Ok(ufo) => ufo,
+        Err(e) => return vec![Err(e)],
+    };
+    let reference_layer = ufo
+        .layers
+        .get(layer)
+        .unwrap_or_else(|| panic!("{ufo_dir:?} has no layer named {layer}"));
+    let color = ColorLayers::new(&ufo);
+    let mut results = Vec::new();
+    let mut reference = HashMap::new();
+    for glyph in Glyph::from_layer(ufo_dir, reference_layer, &color) {
+        match glyph {
+            Ok(glyph) => {
+                reference.insert(glyph.name.clone(), glyph);
+            }
+            Err(e) => results.push(Err(e)),
+        }
+    }
+
+    let foreground = Glyph::from_layer(ufo_dir, ufo.default_layer(), &color);
+    let comparisons = foreground
+        .into_par_iter()
+        .filter_map(|fg| match fg {
+            Ok(fg) => {
+                let bg = reference.get(&fg.name)?;
+                Some(fg.check(config).and_then(|foreground| {
+                    Ok(LayerComparison {
+                        foreground,
+                        reference: bg.check(config)?,
+                    })
+                }))
+            }
+            Err(e) => Some(Err(e)),
+        })
+        .collect::<Vec<_>>();
+    results.extend(comparisons);
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::compare_layers;
+    use crate::Config;
+
+    #[test]
+    fn background_as_reference() {
+        let ufo = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/testdata/Layers.ufo");
+        let comparisons = compare_layers(&ufo, "public.background", &Config::default())
+            .into_iter()
+            .map(|c| c.unwrap())
+            .map(|c| (c.foreground.name.as_str().to_string(), c.differs()))
+            .collect::<Vec<_>>();
+        // minus has no background
+        assert_eq!(
+            vec![("bar".to_string(), false), ("plus".to_string(), true)],
+            comparisons
+        );
+    }
+}
@@ -0,0 +1,56 @@
+//! Find glyphs that need the overlap flag by comparing nonzero and evenodd renders.
+
+mod analytic;
+mod annotate;
+mod cache;
+mod check;
+mod color;
+mod designspace;
+mod doctor;
+mod error;
+#[cfg(feature = "freetype")]
+mod freetype;
+mod glyph;
+#[cfg(feature = "raster")]
+pub mod html;
+mod ir;
+mod layers;
+mod matrix;
+#[cfg(feature = "raster")]
+mod pair;
+#[cfg(feature = "raster")]
+mod raster;
+mod reduce;
+mod render;
+pub mod report;
+#[cfg(feature = "raster")]
+mod suggest;
+mod transition;
+mod ttf;
+mod variants;
+mod warning;
+
+pub use annotate::{annotate, annotation_change, AnnotationChange, NEEDS_FLAG_LIB_KEY};
+pub use cache::Cache;
+pub use check::{
+    check_contours, check_with, needs_overlap_flag, CheckResult, Config, Method, Overlap,
+    OverlapKind, Rasterizer,
+};
+pub use doctor::{doctor, Diagnosis};
+pub use error::Error;
+pub use glyph::{Format, Glyph, Selection, Sources, MEMORY_SOURCE};
+pub use ir::check_ir_glyphs;
+pub use layers::{compare_layers, LayerComparison};
+pub use matrix::{MasterMatrix, MatrixRow};
+#[cfg(feature = "raster")]
+pub use pair::{check_pair, Collision};
+pub use reduce::minimal_repro;
+#[cfg(feature = "raster")]
+pub use suggest::{suggest_fix, Suggestion};
+pub use transition::{overlap_status_changed, Transition};
+pub use variants::{compare_variants, VariantComparison};
+pub use warning::Warning;
+
+// Part of our API, so users don't need a matching tiny_skia
+#[cfg(feature = "raster")]
+pub use tiny_skia::{FillRule, Pixmap};
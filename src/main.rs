@@ -1,226 +1,1464 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
 
+use clap::{error::ErrorKind, CommandFactory, Parser, ValueEnum};
 use fontdrasil::types::GlyphName;
-use fontir::ir::GlyphPathBuilder;
-use kurbo::{Affine, BezPath, Rect, Shape};
-use tiny_skia::FillRule;
-use tiny_skia::{Paint, Pixmap, PremultipliedColorU8};
-
-const _SAVE_DEBUG_IMAGES: bool = true;
-
-#[derive(Debug)]
-struct Glyph {
-    name: GlyphName,
-    source: PathBuf,
-    bezpath: BezPath,
-}
-
-impl Glyph {
-    pub fn from_file(file: impl AsRef<Path>) -> Vec<Self> {
-        let file = file.as_ref();
-        match file.extension().and_then(|e| e.to_str()) {
-            Some("designspace") => todo!("load designspace"),
-            Some("ufo") => Self::from_ufo_file(file),
-            Some("glif") => vec![Self::from_glif_file(file)],
-            _ => panic!("No handler for {file:?}"),
-        }
-    }
-
-    fn from_glif(file: &Path, glif: &norad::Glyph) -> Self {
-        // Font units and svg units don't agree on y-up.
-        // It's very disconcerting to see all the glyphs upside down in test renders
-        let mut bezpath = glif.contours.to_bezpath(glif.name().as_str());
-        bezpath.apply_affine(Affine::FLIP_Y);
-        return Self {
-            name: glif.name().as_str().into(),
-            source: file.to_path_buf(),
-            bezpath,
+use glyph_overlaps::{
+    annotate, annotation_change, check_pair, compare_layers, compare_variants, doctor, html,
+    minimal_repro,
+    report::{RegionTally, Report},
+    suggest_fix, Cache, CheckResult, Config, Glyph, MasterMatrix, Rasterizer, Selection, Sources,
+    Warning, NEEDS_FLAG_LIB_KEY,
+};
+use kurbo::{Affine, PathEl};
+use rayon::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// Human readable
+    Text,
+    /// A versioned JSON document
+    Json,
+    /// The JSON document as an XML plist, for Apple tooling
+    Plist,
+    /// A self-contained page of flagged glyphs with diff thumbnails, for review
+    Html,
+    /// Test Anything Protocol, a test per glyph that fails if it needs the overlap flag
+    Tap,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Mode {
+    /// A designspace's masters, or a variable font's default location
+    Masters,
+    /// Only the instances interpolated from a designspace's masters, or a variable
+    /// font's named instances
+    Instances,
+    /// Masters then instances
+    Both,
+}
+
+impl From<Mode> for Sources {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::Masters => Sources::Masters,
+            Mode::Instances => Sources::Instances,
+            Mode::Both => Sources::Both,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SortBy {
+    /// Largest area the fill rules disagree on first, then most regions
+    Area,
+    /// Glyph name, alphabetically
+    Name,
+    /// Most contours involved in the overlap first
+    Contours,
+}
+
+impl SortBy {
+    fn compare(&self, a: &CheckResult, b: &CheckResult) -> std::cmp::Ordering {
+        let area = |r: &CheckResult| r.overlap.as_ref().map_or(0.0, |o| o.area);
+        let regions = |r: &CheckResult| r.overlap.as_ref().map_or(0, |o| o.regions);
+        let contours = |r: &CheckResult| r.overlap.as_ref().map_or(0, |o| o.contours.len());
+        match self {
+            SortBy::Area => area(b)
+                .total_cmp(&area(a))
+                .then(regions(b).cmp(&regions(a))),
+            SortBy::Name => a.name.cmp(&b.name),
+            SortBy::Contours => contours(b).cmp(&contours(a)),
+        }
+    }
+}
+
+/// `items` rearranged so the nth is what was at `order[n]`.
+fn permute<T>(items: Vec<T>, order: &[usize]) -> Vec<T> {
+    let mut items = items.into_iter().map(Some).collect::<Vec<_>>();
+    order
+        .iter()
+        .map(|i| items[*i].take().expect("each index once"))
+        .collect()
+}
+
+#[derive(Parser, Debug)]
+#[command(about = "Find glyphs that need the overlap flag")]
+struct Args {
+    /// Number of threads to check glyphs on, 0 to use all cores
+    #[arg(long, default_value_t = 0)]
+    threads: usize,
+
+    /// How to report results. Results go to stdout, unless --out, so they can be piped;
+    /// progress, warnings, and errors go to stderr.
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Write the report to this file instead, in whatever --format. Progress, warnings,
+    /// and errors still go to stderr.
+    #[arg(long, value_name = "PATH")]
+    out: Option<PathBuf>,
+
+    /// Order results, e.g. by area to see the worst overlaps first, rather than as loaded.
+    /// Ties keep load order.
+    #[arg(long, value_enum)]
+    sort_by: Option<SortBy>,
+
+    /// Only report glyphs that need the overlap flag, in every format. Counts still
+    /// include everything.
+    #[arg(long)]
+    only_problems: bool,
+
+    /// Say why each flagged glyph needs the flag: what kind of overlap it is, how many
+    /// places the fill rules disagree, and roughly over how much area. Text format only,
+    /// JSON always has these.
+    #[arg(long)]
+    explain: bool,
+
+    /// Name each flagged glyph's codepoints too, e.g. U+002B, for cross-referencing with
+    /// text samples. Text format only, JSON and HTML always have them.
+    #[arg(long)]
+    codepoints: bool,
+
+    /// Tally glyphs that need the overlap flag by how many places the fill rules
+    /// disagree, 1, 2, or 3 or more, after the results. Text format only, JSON and plist
+    /// summaries always have the tally.
+    #[arg(long)]
+    by_regions: bool,
+
+    /// Report a matrix of designspace glyphs by master, marking which masters flag each
+    /// glyph, instead of a result per glyph: CSV for --format text, or a table with
+    /// glyphs the masters disagree on highlighted for --format html. Instances are left out.
+    #[arg(long, conflicts_with_all = ["summary_only", "expect", "expect_overlap"])]
+    master_matrix: bool,
+
+    /// Don't check anything, print the outline of the named glyph as SVG path data, y-down,
+    /// exactly as it would be checked
+    #[arg(long, value_name = "GLYPH")]
+    dump_path: Option<String>,
+
+    /// Don't check anything, write every glyph with this name, as it would be checked, as
+    /// a .json input: a small self-contained reproduction to attach to a bug report
+    #[arg(long, value_name = "GLYPH",
+          conflicts_with_all = ["dump_path", "compare_layer", "pair", "suggest_fixes"])]
+    export_repro: Option<String>,
+
+    /// With --export-repro, drop every contour the glyph still needs the overlap flag
+    /// without, one at a time, for a smaller reproduction. Experimental, and as slow as
+    /// checking the glyph once per contour. Glyphs that don't need the flag are exported whole
+    #[arg(long, requires = "export_repro")]
+    minimize: bool,
+
+    /// Check each .ufo against the reference outlines in this layer, e.g. public.background,
+    /// and report only glyphs whose need for the overlap flag differs between the two
+    #[arg(long, value_name = "LAYER", conflicts_with_all = ["dump_path", "annotate", "expect_overlap"])]
+    compare_layer: Option<String>,
+
+    /// Check each glyph that has a variant named with this suffix, e.g. .comp for aacute
+    /// drawn directly and aacute.comp built from components, and report only pairs whose
+    /// need for the overlap flag differs
+    #[arg(long, value_name = "SUFFIX",
+          conflicts_with_all = ["dump_path", "compare_layer", "annotate", "expect_overlap"])]
+    compare_variants: Option<String>,
+
+    /// Render with the system FreeType rather than tiny_skia, to see what a production
+    /// rasterizer would. Glyphs are rendered in one pass, ignoring --tile-size.
+    #[cfg(feature = "freetype")]
+    #[arg(long)]
+    freetype: bool,
+
+    /// Check each glyph rendered with both tiny_skia and FreeType, and report only glyphs
+    /// they disagree about
+    #[cfg(feature = "freetype")]
+    #[arg(long, conflicts_with_all = ["freetype", "dump_path", "compare_layer", "annotate", "expect_overlap"])]
+    compare_rasterizers: bool,
+
+    /// Also report the scale each glyph was checked at, which --scale reproduces, the
+    /// largest pixmap rendered, and roughly how much memory pixmaps need at peak
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Stop after checking this many glyphs, to bound run time. The summary notes if
+    /// any were left unchecked, but not how many, that would mean loading them.
+    #[arg(long, value_name = "N")]
+    max_glyphs: Option<usize>,
+
+    /// Stop checking once this many glyphs need the overlap flag and exit 1, for a quick
+    /// answer on a font that's obviously broken. The summary notes if any were left
+    /// unchecked, without loading them to count them.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    stop_after: Option<u64>,
+
+    /// Exit 1 if more than this percentage of the glyphs checked need the overlap flag,
+    /// to adopt the check gradually on a font with known overlaps
+    #[arg(long, value_name = "P", value_parser = parse_non_negative, conflicts_with_all = ["expect_overlap", "expect"])]
+    fail_threshold_percent: Option<f64>,
+
+    /// Report warnings of this kind as errors and exit 1 if there are any, e.g.
+    /// zero-length-segment to keep duplicate points out of sources. Repeat for more kinds.
+    #[arg(long, value_name = "KIND",
+          value_parser = clap::builder::PossibleValuesParser::new(Warning::KINDS))]
+    warn_as_error: Vec<String>,
+
+    /// Print only the summary counts, in --format, nothing per glyph
+    #[arg(long, conflicts_with_all = ["expect_overlap", "expect"])]
+    summary_only: bool,
+
+    /// Pixels per font unit to render at, either one value or x,y for anisotropic scaling
+    #[arg(long, default_value = "1", value_parser = parse_scale)]
+    scale: (f64, f64),
+
+    /// The largest width or height, in pixels, to render at. Scale is reduced to fit.
+    #[arg(long, default_value_t = Config::default().max_pixmap_size)]
+    max_pixmap_size: u32,
+
+    /// How many pixels the fill rules must disagree on for a glyph to need the flag
+    #[arg(long, default_value_t = Config::default().pixel_threshold)]
+    pixel_threshold: usize,
+
+    /// Recheck glyphs close to the threshold at this many times the scale, 1 to never recheck
+    #[arg(long, default_value_t = Config::default().retry_scale, value_parser = parse_positive)]
+    retry_scale: f64,
+
+    /// Space to leave around each glyph, as a fraction of the larger side of its bounding box
+    #[arg(long, default_value_t = Config::default().margin, value_parser = parse_non_negative)]
+    margin: f64,
+
+    /// Render and compare at most NxN pixels at a time, stopping once the verdict is
+    /// certain, to bound memory and time for huge or very complex glyphs. Overlap details
+    /// then only cover the tiles rendered.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    tile_size: Option<u32>,
+
+    /// Drop contours enclosing less than this many square font units before checking,
+    /// e.g. specks from auto-tracing. Glyphs that lose any get a warning saying how many.
+    #[arg(long, value_name = "A", default_value_t = 0.0, value_parser = parse_non_negative)]
+    min_contour_area: f64,
+
+    /// Ignore places the fill rules disagree that are at most twice this many pixels
+    /// wide, e.g. one pixel slivers along edges contours share
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    edge_tolerance: u32,
+
+    /// Give close calls a second opinion by comparing anti-aliased renders, and warn when
+    /// it disagrees with the aliased verdict, which still decides
+    #[arg(long)]
+    aa_second_opinion: bool,
+
+    /// Only check glyphs of a .ttf with ids in this range, inclusive, e.g. 1000-2000 to
+    /// bisect a large font. Other inputs are checked whole.
+    #[arg(long, value_name = "FIRST-LAST", value_parser = parse_gid_range)]
+    gid_range: Option<RangeInclusive<u16>>,
+
+    /// Only check glyphs whose files differ from this git revision, e.g. main, committed
+    /// or not, or that git doesn't track yet. Instances are checked if their designspace
+    /// or a master of the same glyph changed.
+    #[arg(long, value_name = "REV")]
+    changed_since: Option<String>,
+
+    /// Only check layers of each .ufo whose names match this glob, e.g. 'public.background*'.
+    /// * matches any run of characters, ? any one. Other inputs are checked whole.
+    #[arg(long, value_name = "GLOB")]
+    layers: Option<String>,
+
+    /// Which sources of each .designspace, or variable .ttf, to check. Interpolating
+    /// every instance is expensive, so only masters unless asked.
+    #[arg(long, value_enum, default_value_t = Mode::Masters)]
+    mode: Mode,
+
+    /// Check each .designspace, or variable .ttf, here instead of at its instances:
+    /// user-space values by axis tag, e.g. wght=700,wdth=75, mapped by the designspace's
+    /// axis maps or the font's avar as the font would. Axes left out are at their
+    /// default. Only this location is checked, unless --mode both adds the masters
+    #[arg(long, value_name = "TAG=VALUE", value_delimiter = ',', value_parser = parse_axis_value)]
+    location: Option<Vec<(String, f64)>>,
+
+    /// Don't check glyphs whose source already sets the overlap flag, public.truetype.overlap
+    /// in a UFO glyph's lib or the glyf flags of a .ttf, so the report is only of glyphs
+    /// missing a flag they need
+    #[arg(long)]
+    skip_already_flagged: bool,
+
+    /// Check only glyphs that other glyphs use as components, whose overlaps show up
+    /// everywhere they're used. Every glyph is loaded to find them, --gid-range and
+    /// --layers then narrow what's checked as usual
+    #[arg(long)]
+    components_only: bool,
+
+    /// Apply this affine, a,b,c,d,e,f in font units (y-up), to every glyph after loading,
+    /// e.g. 1,0,0.2,1,0,0 for a synthesized italic
+    #[arg(long, value_name = "AFFINE", value_parser = parse_affine, allow_hyphen_values = true,
+          conflicts_with_all = ["annotate", "compare_layer"])]
+    transform: Option<Affine>,
+
+    /// Lean every glyph after loading by the italic angle its source gives, a UFO's
+    /// italicAngle or a .ttf's post table, to check the oblique derived from it. Glyphs
+    /// whose source doesn't say are checked upright
+    #[arg(long, conflicts_with_all = ["transform", "annotate", "compare_layer"])]
+    slant: bool,
+
+    /// Like --slant, but at this italic angle whatever the source says: degrees
+    /// counter-clockwise from vertical, so e.g. -12 leans right
+    #[arg(long, value_name = "DEGREES", allow_hyphen_values = true,
+          conflicts_with_all = ["slant", "transform", "annotate", "compare_layer"])]
+    italic_angle: Option<f64>,
+
+    /// Render from 0 to the advance width as well as the outline, so overlaps near the
+    /// sidebearings show in debug images
+    #[arg(long)]
+    include_advance: bool,
+
+    /// Only write debug images for glyphs whose evenodd and nonzero renders differ,
+    /// rather than for every glyph
+    #[arg(long)]
+    only_differing_images: bool,
+
+    /// Write one debug image per glyph with the evenodd render, the nonzero render, and
+    /// their difference side by side, rather than three
+    #[arg(long)]
+    side_by_side_images: bool,
+
+    /// Also write the evenodd and nonzero coverage of each glyph, a byte per pixel, as
+    /// NumPy .npy files alongside the debug images, e.g. to analyze in Python
+    #[arg(long)]
+    raw_buffers: bool,
+
+    /// Where to write debug images and raw buffers, created if need be. Defaults to
+    /// $GLYPH_OVERLAPS_OUT_DIR if set, otherwise /tmp
+    #[arg(long, value_name = "DIR")]
+    image_dir: Option<PathBuf>,
+
+    /// Color to fill glyphs with in debug images, as RRGGBB hex
+    #[arg(long, default_value = "808080", value_parser = parse_color)]
+    fill_color: (u8, u8, u8),
+
+    /// Color to fill nonzero renders with in debug images, as RRGGBB hex, so they stand
+    /// out from evenodd ones. --fill-color if not given.
+    #[arg(long, value_name = "RRGGBB", value_parser = parse_color)]
+    nonzero_fill_color: Option<(u8, u8, u8)>,
+
+    /// Round coordinates to integers before checking, to find overlaps that only
+    /// appear in the compiled font
+    #[arg(long)]
+    round_coords: bool,
+
+    /// Reorient contours, outers counter-clockwise and holes clockwise, before checking,
+    /// and warn about glyphs whose verdict that changes
+    #[arg(long)]
+    normalize_directions: bool,
+
+    /// Record whether each glyph needs the overlap flag in the lib of its .glif,
+    /// under com.rsheeter.glyph_overlaps.needsFlag. Outlines are not changed.
+    #[arg(long)]
+    annotate: bool,
+
+    /// With --annotate, list the glyphs whose annotation would be added or changed
+    /// without writing anything
+    #[arg(long, requires = "annotate")]
+    dry_run: bool,
+
+    /// Where to remember verdicts between runs so unchanged glyphs aren't rechecked
+    #[arg(long, default_value = ".glyph_overlaps_cache.json")]
+    cache: PathBuf,
+
+    /// Check every glyph, ignoring and not updating the cache
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Glyphs that are meant to overlap, comma separated. Only these are reported,
+    /// and only if they no longer need the overlap flag.
+    #[arg(long, value_delimiter = ',')]
+    expect_overlap: Vec<String>,
+
+    /// A file naming exactly the glyphs expected to need the overlap flag, one per line,
+    /// # for comments. Exit 1 if the glyphs that need it differ, listing which.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["expect_overlap", "summary_only"])]
+    expect: Option<PathBuf>,
+
+    /// Report glyphs by the names in this file rather than their source names, e.g. the
+    /// production names the rest of a pipeline uses: a source name then the name to report,
+    /// separated by whitespace, one pair per line, # for comments. Unlisted glyphs keep
+    /// their names. --expect and --expect-overlap use the reported names.
+    #[arg(long, value_name = "FILE")]
+    name_map: Option<PathBuf>,
+
+    /// Experimental: don't check glyphs, check whether LEFT,RIGHT collide when set side
+    /// by side with --kern applied. Repeat for more pairs. Exits 1 if any collide.
+    #[arg(long, value_name = "LEFT,RIGHT", value_parser = parse_pair,
+          conflicts_with_all = ["dump_path", "compare_layer", "annotate", "expect_overlap", "expect"])]
+    pair: Vec<(String, String)>,
+
+    /// Font units to add to the advance of the left glyph of each --pair, negative to
+    /// tighten
+    #[arg(
+        long,
+        default_value_t = 0.0,
+        allow_hyphen_values = true,
+        requires = "pair"
+    )]
+    kern: f64,
+
+    /// For each glyph that needs the overlap flag, trace its nonzero render into an
+    /// overlap-free outline and say whether that checks clean. Curves come back
+    /// flattened, raise --scale for a closer fit.
+    #[arg(long, conflicts_with_all = ["dump_path", "compare_layer", "annotate", "expect_overlap", "expect", "pair"])]
+    suggest_fixes: bool,
+
+    /// With --suggest-fixes, write each suggested outline to an SVG in this directory
+    #[arg(long, value_name = "DIR", requires = "suggest_fixes")]
+    svg_dir: Option<PathBuf>,
+
+    /// Don't check anything, look for problems with the files that would stop them being
+    /// checked, e.g. unreadable .glifs, non-finite coordinates, or a missing unitsPerEm
+    #[arg(long, conflicts_with_all = ["dump_path", "compare_layer", "annotate", "expect_overlap"])]
+    doctor: bool,
+
+    /// .glif, .ufo, .designspace, .ttf, or .json files to check
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+}
+
+fn parse_positive(raw: &str) -> Result<f64, String> {
+    match raw.trim().parse::<f64>() {
+        Ok(v) if v > 0.0 && v.is_finite() => Ok(v),
+        _ => Err(format!("{raw:?} is not a positive number")),
+    }
+}
+
+fn parse_non_negative(raw: &str) -> Result<f64, String> {
+    match raw.trim().parse::<f64>() {
+        Ok(v) if v >= 0.0 && v.is_finite() => Ok(v),
+        _ => Err(format!("{raw:?} is not a non-negative number")),
+    }
+}
+
+fn parse_color(raw: &str) -> Result<(u8, u8, u8), String> {
+    let hex = raw.trim().trim_start_matches('#');
+    let channel = |i: usize| {
+        hex.get(i..i + 2)
+            .and_then(|c| u8::from_str_radix(c, 16).ok())
+            .ok_or_else(|| format!("{raw:?} is not an RRGGBB color"))
+    };
+    if hex.len() != 6 {
+        return Err(format!("{raw:?} is not an RRGGBB color"));
+    }
+    Ok((channel(0)?, channel(2)?, channel(4)?))
+}
+
+fn parse_scale(raw: &str) -> Result<(f64, f64), String> {
+    match raw.split_once(',') {
+        Some((x, y)) => Ok((parse_positive(x)?, parse_positive(y)?)),
+        None => parse_positive(raw).map(|v| (v, v)),
+    }
+}
+
+fn parse_axis_value(raw: &str) -> Result<(String, f64), String> {
+    raw.split_once('=')
+        .and_then(|(tag, value)| {
+            let value = value.trim().parse::<f64>().ok().filter(|v| v.is_finite())?;
+            Some((tag.trim().to_string(), value))
+        })
+        .filter(|(tag, _)| (1..=4).contains(&tag.len()))
+        .ok_or_else(|| format!("{raw:?} is not an axis tag and a value, e.g. wght=700"))
+}
+
+fn parse_gid_range(raw: &str) -> Result<RangeInclusive<u16>, String> {
+    let gid = |v: &str| {
+        v.trim()
+            .parse::<u16>()
+            .map_err(|_| format!("{raw:?} is not a glyph id or FIRST-LAST range of them"))
+    };
+    let (first, last) = match raw.split_once('-') {
+        Some((first, last)) => (gid(first)?, gid(last)?),
+        None => (gid(raw)?, gid(raw)?),
+    };
+    if first > last {
+        return Err(format!("{raw:?} ends before it starts"));
+    }
+    Ok(first..=last)
+}
+
+fn parse_pair(raw: &str) -> Result<(String, String), String> {
+    match raw.split_once(',') {
+        Some((left, right)) if !left.trim().is_empty() && !right.trim().is_empty() => {
+            Ok((left.trim().to_string(), right.trim().to_string()))
+        }
+        _ => Err(format!("{raw:?} is not a LEFT,RIGHT pair of glyph names")),
+    }
+}
+
+fn parse_affine(raw: &str) -> Result<Affine, String> {
+    let coeffs = raw
+        .split(',')
+        .map(|v| v.trim().parse::<f64>().ok().filter(|v| v.is_finite()))
+        .collect::<Option<Vec<_>>>()
+        .and_then(|c| <[f64; 6]>::try_from(c).ok())
+        .ok_or_else(|| format!("{raw:?} is not six comma separated numbers"))?;
+    Ok(Affine::new(coeffs))
+}
+
+/// Glyph names from an --expect file: one per line, ignoring blank lines and # comments.
+fn read_expected(file: &std::path::Path) -> HashSet<GlyphName> {
+    std::fs::read_to_string(file)
+        .unwrap_or_else(|e| panic!("Unable to read {file:?}: {e}"))
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|name| !name.is_empty())
+        .map(GlyphName::new)
+        .collect()
+}
+
+/// Source to reported names from a --name-map file.
+fn read_name_map(file: &std::path::Path) -> HashMap<GlyphName, GlyphName> {
+    std::fs::read_to_string(file)
+        .unwrap_or_else(|e| panic!("Unable to read {file:?}: {e}"))
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.split('#').next().unwrap_or_default()))
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(
+            |(i, line)| match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [source, reported] => (GlyphName::new(source), GlyphName::new(reported)),
+                _ => panic!(
+                "Unable to read {file:?}: line {} should be a source name then a name to report",
+                i + 1
+            ),
+            },
+        )
+        .collect()
+}
+
+fn contour_count(glyph: &Glyph) -> usize {
+    glyph
+        .bezpath()
+        .elements()
+        .iter()
+        .filter(|el| matches!(el, PathEl::MoveTo(_)))
+        .count()
+}
+
+/// The names of the glyphs any glyph in `files` uses as a component. Instances use
+/// the components their default master does so the masters are enough.
+fn component_bases(files: &[PathBuf]) -> HashSet<GlyphName> {
+    let selection = Selection {
+        sources: Sources::Masters,
+        ..Default::default()
+    };
+    Glyph::stream_selected(files, selection)
+        .flatten()
+        .flat_map(|glyph| glyph.components().to_vec())
+        .collect()
+}
+
+/// --image-dir, else $GLYPH_OVERLAPS_OUT_DIR, else /tmp, made sure to exist.
+fn image_dir(args: &Args) -> PathBuf {
+    let dir = args
+        .image_dir
+        .clone()
+        .or_else(|| std::env::var_os("GLYPH_OVERLAPS_OUT_DIR").map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from("/tmp"));
+    std::fs::create_dir_all(&dir)
+        .unwrap_or_else(|e| panic!("Unable to create image directory {dir:?}: {e}"));
+    dir
+}
+
+/// Every glyph in the files `args` names, only those it selects, with its --transform,
+/// or --slant, applied if there is one.
+fn load(args: &Args) -> impl Iterator<Item = Result<Glyph, glyph_overlaps::Error>> + '_ {
+    let changed = args
+        .changed_since
+        .as_deref()
+        .map(|rev| changed_since(rev, &args.files));
+    // A --location stands in for the instances, so is checked whatever the --mode
+    let mode = match (&args.location, args.mode) {
+        (Some(_), Mode::Masters) => Mode::Instances,
+        (_, mode) => mode,
+    };
+    // Which masters changed decides which instances to check, so they're loaded anyway
+    // and dropped once they've been seen
+    let drop_masters = mode == Mode::Instances && changed.is_some();
+    let selection = Selection {
+        gids: args.gid_range.clone(),
+        layers: args.layers.clone(),
+        sources: match drop_masters {
+            true => Sources::Both,
+            false => mode.into(),
+        },
+        location: args.location.clone(),
+    };
+    // Masters come before the instances interpolated from them
+    let mut changed_masters = HashSet::new();
+    let transform = args.transform;
+    // Some(None) leans each glyph by its own source's angle
+    let slant = match args.italic_angle {
+        Some(angle) => Some(Some(angle)),
+        None => args.slant.then_some(None),
+    };
+    let skip_already_flagged = args.skip_already_flagged;
+    let bases = args.components_only.then(|| component_bases(&args.files));
+    Glyph::stream_selected(&args.files, selection)
+        .filter(move |glyph| {
+            let (Some(changed), Ok(glyph)) = (&changed, glyph) else {
+                return true;
+            };
+            let key = (
+                glyph.designspace().map(Path::to_path_buf),
+                glyph.name().clone(),
+            );
+            if glyph.instance().is_some() && changed_masters.contains(&key) {
+                return true;
+            }
+            let source_changed = glyph
+                .source()
+                .canonicalize()
+                .is_ok_and(|source| changed.contains(&source));
+            if source_changed && glyph.instance().is_none() {
+                changed_masters.insert(key);
+            }
+            source_changed
+        })
+        .filter(move |glyph| {
+            let (true, Ok(glyph)) = (drop_masters, glyph) else {
+                return true;
+            };
+            let master = glyph.designspace().is_some()
+                || glyph.source().extension().and_then(|e| e.to_str()) == Some("ttf");
+            glyph.instance().is_some() || !master
+        })
+        .filter(move |glyph| {
+            !(skip_already_flagged && glyph.as_ref().is_ok_and(Glyph::has_overlap_flag))
+        })
+        .filter(move |glyph| {
+            let (Some(bases), Ok(glyph)) = (&bases, glyph) else {
+                return true;
+            };
+            bases.contains(glyph.name())
+        })
+        .map(move |glyph| match transform {
+            Some(transform) => glyph.and_then(|g| g.transformed(transform)),
+            None => glyph,
+        })
+        .map(move |glyph| match slant {
+            Some(angle) => glyph.and_then(|g| match angle.or(g.italic_angle()) {
+                Some(angle) => g.slanted(angle),
+                None => Ok(g),
+            }),
+            None => glyph,
+        })
+}
+
+/// Every file in the git repositories holding `inputs` that differs from `rev`,
+/// committed or not, or is untracked, canonicalized.
+fn changed_since(rev: &str, inputs: &[PathBuf]) -> HashSet<PathBuf> {
+    let git = |dir: &Path, args: &[&str]| {
+        let output = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .unwrap_or_else(|e| panic!("Unable to run git: {e}"));
+        if !output.status.success() {
+            panic!(
+                "git {} failed in {dir:?}: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    let mut roots = HashSet::new();
+    for input in inputs {
+        let dir = match input.is_dir() {
+            true => input.as_path(),
+            false => input.parent().unwrap_or(Path::new(".")),
+        };
+        // An empty parent is the current directory
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
         };
+        roots.insert(PathBuf::from(
+            git(dir, &["rev-parse", "--show-toplevel"]).trim(),
+        ));
     }
 
-    fn from_glif_file(file: &Path) -> Self {
-        let glif =
-            norad::Glyph::load(file).unwrap_or_else(|e| panic!("Unable to load {file:?}: {e}"));
-        Self::from_glif(file, &glif)
-    }
-
-    fn from_ufo_file(ufo_dir: &Path) -> Vec<Self> {
-        let ufo =
-            norad::Font::load(ufo_dir).unwrap_or_else(|e| panic!("Error loading {ufo_dir:?}: {e}"));
-        ufo.iter_layers()
-            .flat_map(|l| {
-                l.iter().map(|g| {
-                    (
-                        l.get_path(g.name()).unwrap_or_else(|| {
-                            panic!("No path for {} in layer {}", g.name(), l.name())
-                        }),
-                        g,
-                    )
-                })
-            })
-            .map(|(glif_file, glif)| Self::from_glif(glif_file, glif))
-            .collect()
-    }
-
-    /// Look fill rule problems by rendering evenodd and nonzero and comparing.
-    ///
-    /// You'd think this woefully suboptimal but it gets you all the optimizations
-    /// that have gone into rendering images for free so a naive implementation does OK.
-    fn has_fill_rule_discrepency(self: &Glyph) -> bool {
-        // render without AA, we just want insideness from the pixels
-        let mut evenodd = self.render_no_aa(FillRule::EvenOdd);
-        let nonzero = self.render_no_aa(FillRule::Winding);
-
-        if evenodd.pixels().len() != nonzero.pixels().len() {
-            panic!("Inconsistent pixel count, seems very bad")
-        }
-
-        let pink = PremultipliedColorU8::from_rgba(255, 20, 147, 255).unwrap();
-        let mut discrepency = false;
-        for (evenodd_px, _) in evenodd
-            .pixels_mut()
-            .iter_mut()
-            .zip(nonzero.pixels().iter())
-            .filter(|(a, b)| a != b)
+    let mut changed = HashSet::new();
+    for root in roots {
+        let diff = git(&root, &["diff", "--name-only", "--no-renames", rev, "--"]);
+        let untracked = git(&root, &["ls-files", "--others", "--exclude-standard"]);
+        changed.extend(
+            diff.lines()
+                .chain(untracked.lines())
+                .filter_map(|file| root.join(file).canonicalize().ok()),
+        );
+    }
+    changed
+}
+
+/// Glyphs are loaded and checked this many at a time, then dropped, so memory doesn't
+/// grow with the size of the input.
+const BATCH_SIZE: usize = 256;
+
+/// The largest pixmaps checks need, to estimate peak memory. Each check holds an
+/// evenodd and a nonzero RGBA pixmap at once, one check per thread.
+#[derive(Default)]
+struct PixmapStats {
+    largest: Option<(String, u32, u32)>,
+    /// The biggest footprints, in bytes, one per thread
+    bytes: Vec<u64>,
+    /// The same if they were rechecked at the retry scale
+    finer_bytes: Vec<u64>,
+}
+
+impl PixmapStats {
+    fn add(&mut self, glyph: &Glyph, config: &Config) {
+        let finer = Config {
+            scale_x: config.scale_x * config.retry_scale,
+            scale_y: config.scale_y * config.retry_scale,
+            ..config.clone()
+        };
+        let (width, height) = glyph.pixmap_size(config);
+        let area = |(w, h): (u32, u32)| w as u64 * h as u64;
+        if self
+            .largest
+            .as_ref()
+            .is_none_or(|(_, w, h)| area((*w, *h)) < area((width, height)))
         {
-            discrepency = true;
-            *evenodd_px = pink;
-        }
-
-        if _SAVE_DEBUG_IMAGES {
-            let filename = format!("/tmp/{}.diff.png", self.name,);
-            save_debug_image(&filename, &evenodd);
-        }
-
-        discrepency
-    }
-
-    fn create_path(&self) -> (Rect, tiny_skia::Path) {
-        // move the path to start at 0,0
-        let mut bez = self.bezpath.clone();
-        let bbox = self.bezpath.bounding_box();
-        let margin = bbox.width().max(bbox.height()) * 0.1;
-        bez.apply_affine(Affine::translate((
-            -bbox.min_x() + margin,
-            -bbox.min_y() + margin,
-        )));
-        let bbox = bez.bounding_box(); // bbox just changed
-        let width = bbox.max_x() + margin;
-        let height = bbox.max_y() + margin;
-
-        let mut pb = tiny_skia::PathBuilder::new();
-        for el in bez.iter() {
-            match el {
-                kurbo::PathEl::MoveTo(p) => pb.move_to(p.x as f32, p.y as f32),
-                kurbo::PathEl::LineTo(p) => pb.line_to(p.x as f32, p.y as f32),
-                kurbo::PathEl::QuadTo(c, p) => {
-                    pb.quad_to(c.x as f32, c.y as f32, p.x as f32, p.y as f32)
-                }
-                kurbo::PathEl::CurveTo(c0, c1, p) => pb.cubic_to(
-                    c0.x as f32,
-                    c0.y as f32,
-                    c1.x as f32,
-                    c1.y as f32,
-                    p.x as f32,
-                    p.y as f32,
-                ),
-                kurbo::PathEl::ClosePath => pb.close(),
-            }
-        }
-
-        (
-            Rect::new(0.0, 0.0, width, height),
-            pb.finish()
-                .unwrap_or_else(|| panic!("Unable to create path for {}", self.name)),
-        )
+            self.largest = Some((glyph.describe(), width, height));
+        }
+        let threads = rayon::current_num_threads();
+        for (bytes, size) in [
+            (&mut self.bytes, (width, height)),
+            (&mut self.finer_bytes, glyph.pixmap_size(&finer)),
+        ] {
+            bytes.push(2 * 4 * area(size));
+            bytes.sort_unstable_by(|a, b| b.cmp(a));
+            bytes.truncate(threads);
+        }
     }
 
-    fn render_no_aa(&self, fill_rule: FillRule) -> Pixmap {
-        let (extents, path) = self.create_path();
-        let mut pixmap = Pixmap::new(extents.width() as u32, extents.height() as u32)
-            .unwrap_or_else(|| panic!("Unable to create pixmap"));
-        let mut paint = Paint::default();
-        paint.set_color_rgba8(128, 128, 128, 255); // gray
-        paint.anti_alias = false; // just confuses diffs
-        pixmap.fill_path(
-            &path,
-            &paint,
-            fill_rule,
-            tiny_skia::Transform::identity(),
-            None,
+    fn report(&self) {
+        let Some((largest, width, height)) = &self.largest else {
+            return;
+        };
+        let mib = |bytes: &[u64]| bytes.iter().sum::<u64>() as f64 / (1024.0 * 1024.0);
+        eprintln!("Largest pixmap {width}x{height}, for {largest}");
+        eprintln!(
+            "Pixmaps need about {:.1} MiB at peak on {} thread(s), {:.1} MiB if the largest are all rechecked",
+            mib(&self.bytes),
+            rayon::current_num_threads(),
+            mib(&self.finer_bytes),
         );
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+    if args.master_matrix && args.format == Format::Plist {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--master-matrix can't be written as a plist, plists can't say a master lacks a glyph",
+            )
+            .exit();
+    }
+    if args.format == Format::Tap && (args.master_matrix || args.summary_only) {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--format tap is a test per glyph, it can't be written with --master-matrix or --summary-only",
+            )
+            .exit();
+    }
+
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(args.threads)
+        .build_global()
+        .unwrap_or_else(|e| panic!("Unable to configure thread pool: {e}"));
+
+    let config = Config {
+        scale_x: args.scale.0,
+        scale_y: args.scale.1,
+        max_pixmap_size: args.max_pixmap_size,
+        round_coords: args.round_coords,
+        pixel_threshold: args.pixel_threshold,
+        retry_scale: args.retry_scale,
+        fill_color: args.fill_color,
+        nonzero_fill_color: args.nonzero_fill_color,
+        margin: args.margin,
+        normalize_directions: args.normalize_directions,
+        tile_size: args.tile_size,
+        min_contour_area: args.min_contour_area,
+        anti_aliased_second_opinion: args.aa_second_opinion,
+        only_differing_images: args.only_differing_images,
+        side_by_side_images: args.side_by_side_images,
+        raw_buffers: args.raw_buffers,
+        image_dir: Some(image_dir(&args)),
+        include_advance: args.include_advance,
+        edge_tolerance: args.edge_tolerance,
+        #[cfg(feature = "freetype")]
+        rasterizer: if args.freetype {
+            Rasterizer::FreeType
+        } else {
+            Rasterizer::TinySkia
+        },
+        #[cfg(not(feature = "freetype"))]
+        rasterizer: Rasterizer::TinySkia,
+    };
+
+    if args.doctor {
+        let mut healthy = true;
+        for file in args.files.iter() {
+            let diagnosis = doctor(file);
+            for e in diagnosis.errors.iter() {
+                eprintln!("ERROR: {e}");
+            }
+            for w in diagnosis.warnings.iter() {
+                eprintln!("WARNING: {w}");
+            }
+            if diagnosis.is_healthy() && diagnosis.warnings.is_empty() {
+                eprintln!("{file:?} looks fine");
+            }
+            healthy &= diagnosis.is_healthy();
+        }
+        if !healthy {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Results, of whichever kind, to stdout or --out; everything else to stderr
+    let mut out: Box<dyn Write> = match &args.out {
+        Some(file) => Box::new(
+            std::fs::File::create(file)
+                .unwrap_or_else(|e| panic!("Unable to create {file:?}: {e}")),
+        ),
+        None => Box::new(std::io::stdout()),
+    };
+    let mut report_line = |line: String| {
+        writeln!(out, "{line}").unwrap_or_else(|e| panic!("Unable to write report: {e}"))
+    };
 
-        if _SAVE_DEBUG_IMAGES {
-            let filename = format!(
-                "/tmp/{}.{}.png",
-                self.name,
-                match fill_rule {
-                    FillRule::EvenOdd => "evenodd",
-                    FillRule::Winding => "nonzero",
+    if !args.pair.is_empty() {
+        let mut failed = false;
+        // Masters and default outlines only, instances don't have advances
+        let mut glyphs = HashMap::new();
+        for glyph in load(&args) {
+            match glyph {
+                Ok(glyph) if glyph.instance().is_none() => {
+                    glyphs.entry(glyph.name().clone()).or_insert(glyph);
                 }
+                Ok(_) => (),
+                Err(e) => {
+                    eprintln!("ERROR: {e}");
+                    failed = true;
+                }
+            }
+        }
+        for (left, right) in args.pair.iter() {
+            let (l, r) = (
+                glyphs.get(&GlyphName::new(left)),
+                glyphs.get(&GlyphName::new(right)),
             );
-            save_debug_image(&filename, &pixmap);
+            let (Some(l), Some(r)) = (l, r) else {
+                let missing = if l.is_none() { left } else { right };
+                eprintln!("ERROR: no glyph named {missing}");
+                failed = true;
+                continue;
+            };
+            match check_pair(l, r, args.kern, &config) {
+                Ok(Some(collision)) => {
+                    let b = collision.bbox;
+                    report_line(format!(
+                        "{left} {right} collide at kern {}, about {:.0} square units within ({}, {}) to ({}, {})",
+                        args.kern, collision.area, b.x0, b.y0, b.x1, b.y1
+                    ));
+                    failed = true;
+                }
+                Ok(None) => report_line(format!(
+                    "{left} {right} don't collide at kern {}",
+                    args.kern
+                )),
+                Err(e) => {
+                    eprintln!("ERROR: {e}");
+                    failed = true;
+                }
+            }
         }
-        pixmap
+        if failed {
+            std::process::exit(1);
+        }
+        return;
     }
-}
 
-fn save_debug_image(filename: &str, pixmap: &Pixmap) {
-    std::fs::write(
-        filename,
-        pixmap
-            .encode_png()
-            .unwrap_or_else(|e| panic!("Failed to encode png for {filename}: {e}")),
-    )
-    .unwrap_or_else(|e| panic!("Failed to write {filename}: {e}"));
-    eprintln!("Wrote {filename}");
-}
+    if args.suggest_fixes {
+        let mut failed = false;
+        if let Some(dir) = &args.svg_dir {
+            std::fs::create_dir_all(dir)
+                .unwrap_or_else(|e| panic!("Unable to create {dir:?}: {e}"));
+        }
+        let mut written = HashSet::new();
+        for glyph in load(&args) {
+            let suggestion = glyph.and_then(|glyph| {
+                if glyph.check(&config)?.overlap.is_none() {
+                    return Ok(None);
+                }
+                suggest_fix(&glyph, &config).map(|s| Some((glyph, s)))
+            });
+            let (glyph, suggestion) = match suggestion {
+                Ok(Some(suggestion)) => suggestion,
+                Ok(None) => continue,
+                Err(e) => {
+                    eprintln!("ERROR: {e}");
+                    failed = true;
+                    continue;
+                }
+            };
+            report_line(format!(
+                "{} could be {} contour(s) instead of {}, {}",
+                glyph.describe(),
+                suggestion.contours_after,
+                suggestion.contours_before,
+                if suggestion.clean {
+                    "without overlaps"
+                } else {
+                    "but that still needs the overlap flag"
+                }
+            ));
+            let Some(dir) = &args.svg_dir else {
+                continue;
+            };
+            // Masters share names, number any repeats
+            let mut file = dir.join(format!("{}.svg", glyph.name()));
+            for i in 1.. {
+                if written.insert(file.clone()) {
+                    break;
+                }
+                file = dir.join(format!("{}.{i}.svg", glyph.name()));
+            }
+            std::fs::write(&file, suggestion.to_svg())
+                .unwrap_or_else(|e| panic!("Unable to write {file:?}: {e}"));
+            eprintln!("Wrote {file:?}");
+        }
+        if failed {
+            std::process::exit(1);
+        }
+        return;
+    }
 
-trait ToBezPath {
-    fn to_bezpath(&self, glyph_name: &str) -> BezPath;
-}
+    if let Some(layer) = &args.compare_layer {
+        let mut failed = false;
+        for file in args.files.iter() {
+            if file.extension().and_then(|e| e.to_str()) != Some("ufo") {
+                eprintln!("ERROR: {file:?} isn't a .ufo, it has no layers to compare");
+                failed = true;
+                continue;
+            }
+            for comparison in compare_layers(file, layer, &config) {
+                match comparison {
+                    Ok(c) if c.differs() => {
+                        let (with, without) = match c.foreground.overlap {
+                            Some(_) => ("the default layer", layer.as_str()),
+                            None => (layer.as_str(), "the default layer"),
+                        };
+                        report_line(format!(
+                            "{:?} {} needs the overlap flag in {with} but not in {without}",
+                            file, c.foreground.name
+                        ));
+                        failed = true;
+                    }
+                    Ok(_) => (),
+                    Err(e) => {
+                        eprintln!("ERROR: {e}");
+                        failed = true;
+                    }
+                }
+            }
+        }
+        if failed {
+            std::process::exit(1);
+        }
+        return;
+    }
 
-impl ToBezPath for [norad::Contour] {
-    /// Basically copied from <https://github.com/googlefonts/fontc/blob/9b7a5634dc0487d52af7a1528520306fc2c6941b/ufo2fontir/src/toir.rs#L31C1-L59C2>
-    fn to_bezpath(&self, glyph_name: &str) -> BezPath {
-        let mut path_builder = GlyphPathBuilder::new(glyph_name.into(), 32);
+    if let Some(suffix) = &args.compare_variants {
+        let mut failed = false;
+        let mut glyphs = Vec::new();
+        for glyph in load(&args) {
+            match glyph {
+                Ok(glyph) => glyphs.push(glyph),
+                Err(e) => {
+                    eprintln!("ERROR: {e}");
+                    failed = true;
+                }
+            }
+        }
+        for comparison in compare_variants(&glyphs, suffix, &config) {
+            match comparison {
+                Ok(c) if c.differs() => {
+                    let (with, without) = match c.glyph.overlap {
+                        Some(_) => (&c.glyph.name, &c.variant.name),
+                        None => (&c.variant.name, &c.glyph.name),
+                    };
+                    report_line(format!(
+                        "{} needs the overlap flag as {with} but not as {without}",
+                        c.glyph.describe()
+                    ));
+                    failed = true;
+                }
+                Ok(_) => (),
+                Err(e) => {
+                    eprintln!("ERROR: {e}");
+                    failed = true;
+                }
+            }
+        }
+        if failed {
+            std::process::exit(1);
+        }
+        return;
+    }
 
-        for contour in self {
-            for node in contour.points.iter() {
-                match node.typ {
-                    norad::PointType::Move => path_builder.move_to((node.x, node.y)),
-                    norad::PointType::Line => path_builder.line_to((node.x, node.y)),
-                    norad::PointType::QCurve => path_builder.qcurve_to((node.x, node.y)),
-                    norad::PointType::Curve => path_builder.curve_to((node.x, node.y)),
-                    norad::PointType::OffCurve => path_builder.offcurve((node.x, node.y)),
+    #[cfg(feature = "freetype")]
+    if args.compare_rasterizers {
+        let mut failed = false;
+        let tiny_skia = Config {
+            rasterizer: Rasterizer::TinySkia,
+            ..config.clone()
+        };
+        let freetype = Config {
+            rasterizer: Rasterizer::FreeType,
+            ..config.clone()
+        };
+        for glyph in load(&args) {
+            let verdicts = glyph.and_then(|glyph| {
+                let result = glyph.check(&tiny_skia)?;
+                let freetype = glyph.check(&freetype)?.overlap.is_some();
+                Ok((result, freetype))
+            });
+            match verdicts {
+                Ok((result, freetype)) if result.overlap.is_some() != freetype => {
+                    let (with, without) = match freetype {
+                        true => ("FreeType", "tiny_skia"),
+                        false => ("tiny_skia", "FreeType"),
+                    };
+                    report_line(format!(
+                        "{} needs the overlap flag rendered with {with} but not {without}",
+                        result.describe()
+                    ));
+                    failed = true;
+                }
+                Ok(_) => (),
+                Err(e) => {
+                    eprintln!("ERROR: {e}");
+                    failed = true;
                 }
-                .unwrap_or_else(|e| panic!("Error making BezPath for {glyph_name}: {e}"));
             }
-            path_builder
-                .end_path()
-                .unwrap_or_else(|e| panic!("Error making BezPath for {glyph_name}: {e}"));
         }
+        if failed {
+            std::process::exit(1);
+        }
+        return;
+    }
 
-        path_builder
-            .build()
-            .unwrap_or_else(|e| panic!("Unable to create BezPath for {glyph_name}: {e}"))
+    let mut errors = Vec::new();
+
+    if let Some(name) = &args.dump_path {
+        let mut found = false;
+        for glyph in load(&args) {
+            match glyph {
+                Ok(glyph) if glyph.name().as_str() == name => {
+                    report_line(format!(
+                        "{}: {}",
+                        glyph.describe(),
+                        glyph.bezpath().to_svg()
+                    ));
+                    found = true;
+                }
+                Ok(_) => (),
+                Err(e) => errors.push(e),
+            }
+        }
+        if !found {
+            eprintln!("ERROR: no glyph named {name}");
+        }
+        if !found || !errors.is_empty() {
+            std::process::exit(1);
+        }
+        return;
     }
-}
 
-fn main() {
-    eprintln!("WARNING: we're currently only checking simple glyphs, not components that transitively have problems");
+    if let Some(name) = &args.export_repro {
+        let mut found = Vec::new();
+        for glyph in load(&args) {
+            match glyph {
+                Ok(glyph) if glyph.name().as_str() == name => {
+                    eprintln!("Exporting {}", glyph.describe());
+                    if !args.minimize {
+                        found.push(glyph);
+                        continue;
+                    }
+                    match minimal_repro(&glyph, &config) {
+                        Ok(Some(repro)) => {
+                            eprintln!(
+                                "  {} of {} contour(s) still need the overlap flag",
+                                contour_count(&repro),
+                                contour_count(&glyph)
+                            );
+                            found.push(repro);
+                        }
+                        Ok(None) => found.push(glyph),
+                        Err(e) => errors.push(e),
+                    }
+                }
+                Ok(_) => (),
+                Err(e) => errors.push(e),
+            }
+        }
+        for error in errors.iter() {
+            eprintln!("ERROR: {error}");
+        }
+        if found.is_empty() {
+            eprintln!("ERROR: no glyph named {name}");
+            std::process::exit(1);
+        }
+        report_line(Glyph::export_json(&found));
+        if !errors.is_empty() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Before checking, so a bad path doesn't waste a long run
+    let expected = args.expect.as_deref().map(read_expected);
+
+    let mut cache = (!args.no_cache).then(|| Cache::load(&args.cache));
+    let mut stats = PixmapStats::default();
+    let mut results = Vec::new();
+    // For --format html, in step with results
+    let mut thumbnails = Vec::new();
+    let mut loaded = 0;
+    let mut hits = 0;
+    let mut flagged = 0;
+    // Loaded for the batch that reached --stop-after, but not checked
+    let mut unchecked = 0;
+    let mut glyphs = load(&args);
+    loop {
+        let mut batch_size = match args.max_glyphs {
+            Some(max) => BATCH_SIZE.min(max - loaded),
+            None => BATCH_SIZE,
+        };
+        if args.stop_after.is_some() {
+            // A thread's worth at a time, so little is checked past the last one needed
+            batch_size = batch_size.min(rayon::current_num_threads());
+        }
+        let mut batch = Vec::with_capacity(batch_size);
+        while batch.len() < batch_size {
+            match glyphs.next() {
+                Some(Ok(glyph)) => batch.push(glyph),
+                Some(Err(e)) => errors.push(e),
+                None => break,
+            }
+        }
+        if batch.is_empty() {
+            break;
+        }
+        loaded += batch.len();
+
+        let checked = batch
+            .par_iter()
+            .map(|g| match cache.as_ref().and_then(|c| c.get(g, &config)) {
+                Some(cached) => (true, Ok(cached)),
+                None => (false, g.check(&config)),
+            })
+            .collect::<Vec<_>>();
+        for (i, (glyph, (hit, result))) in batch.iter().zip(checked).enumerate() {
+            if args.stop_after.is_some_and(|n| flagged >= n) {
+                unchecked = batch.len() - i;
+                break;
+            }
+            if hit {
+                hits += 1;
+            } else if args.verbose {
+                stats.add(glyph, &config);
+            }
+            match result {
+                Ok(result) => {
+                    if let Some((x, y)) = result.scale.filter(|_| args.verbose) {
+                        eprintln!("{} checked at scale {x},{y}", result.describe());
+                    }
+                    if let Some(cache) = cache.as_mut() {
+                        cache.insert(glyph, &config, &result);
+                    }
+                    if args.format == Format::Html {
+                        thumbnails.push(
+                            result
+                                .overlap
+                                .as_ref()
+                                .and_then(|_| html::thumbnail(glyph, &config).ok()),
+                        );
+                    }
+                    flagged += result.overlap.is_some() as u64;
+                    results.push(result);
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+        if args.stop_after.is_some_and(|n| flagged >= n) {
+            break;
+        }
+    }
+    let stopped_early = args.stop_after.is_some_and(|n| flagged >= n);
+    eprintln!("Loaded {loaded}");
+    loaded -= unchecked;
+    // Only whether there's more, counting it would mean loading it all, e.g. interpolating
+    // every instance of a designspace, which is what the cap is there to avoid
+    let capped = stopped_early || args.max_glyphs.is_some_and(|max| loaded >= max);
+    let truncated = unchecked > 0 || capped && glyphs.next().is_some();
+    if stopped_early {
+        let rest = if truncated {
+            ", the rest not checked"
+        } else {
+            ""
+        };
+        eprintln!("Stopped early, {flagged} glyphs need the overlap flag{rest}");
+    } else if truncated {
+        eprintln!("WARNING: stopped after {loaded} glyphs, the rest not checked");
+    }
+    if args.verbose {
+        stats.report();
+    }
+    if let Some(cache) = cache.as_ref() {
+        eprintln!("Reused {hits} cached verdicts");
+        if let Err(e) = cache.save(&args.cache) {
+            eprintln!("WARNING: unable to save cache {:?}: {e}", args.cache);
+        }
+    }
+
+    if args.annotate {
+        for result in results.iter() {
+            if result.source.extension().and_then(|e| e.to_str()) != Some("glif") {
+                eprintln!(
+                    "WARNING: {:?} {} isn't from a .glif, not annotating",
+                    result.source, result.name
+                );
+                continue;
+            }
+            if !args.dry_run {
+                if let Err(e) = annotate(result) {
+                    errors.push(e);
+                }
+                continue;
+            }
+            match annotation_change(result) {
+                Ok(Some(change)) => {
+                    let before = change.before.map_or("unset".to_string(), |b| b.to_string());
+                    eprintln!(
+                        "{} would be annotated {NEEDS_FLAG_LIB_KEY}={}, was {before}",
+                        result.describe(),
+                        change.after
+                    );
+                }
+                Ok(None) => (),
+                Err(e) => errors.push(e),
+            }
+        }
+    }
+
+    // .glifs renamed by --name-map, whose names don't go without saying any more
+    let mut renamed_glifs = HashSet::new();
+    if let Some(name_map) = args.name_map.as_deref().map(read_name_map) {
+        for result in results.iter_mut() {
+            if let Some(name) = name_map.get(&result.name) {
+                result.name = name.clone();
+                if result.source.extension().and_then(|e| e.to_str()) == Some("glif") {
+                    renamed_glifs.insert(result.source.clone());
+                }
+            }
+        }
+    }
+
+    if let Some(sort_by) = args.sort_by {
+        let mut order = (0..results.len()).collect::<Vec<_>>();
+        order.sort_by(|a, b| sort_by.compare(&results[*a], &results[*b]));
+        results = permute(results, &order);
+        if !thumbnails.is_empty() {
+            thumbnails = permute(thumbnails, &order);
+        }
+    }
+
+    let too_many_flagged = args.fail_threshold_percent.is_some_and(|max| {
+        let flagged = results.iter().filter(|r| r.overlap.is_some()).count();
+        let percent = 100.0 * flagged as f64 / results.len().max(1) as f64;
+        if percent > max {
+            eprintln!(
+                "ERROR: {percent:.1}% of glyphs checked need the overlap flag, more than {max}%"
+            );
+        }
+        percent > max
+    });
+
+    let is_error = |w: &Warning| args.warn_as_error.iter().any(|k| k == w.kind());
+    let warned_as_error = results.iter().flat_map(|r| r.warnings.iter()).any(is_error);
 
-    let glyphs = std::env::args()
-        .into_iter()
-        .skip(1)
-        .flat_map(|a| Glyph::from_file(&a).into_iter())
-        .collect::<Vec<_>>();
+    if args.summary_only {
+        let report = Report::new(&results, &errors).truncated(truncated);
+        match args.format {
+            Format::Text => {
+                report_line(report.summary().to_string());
+                if args.by_regions {
+                    report_line(RegionTally::new(&results).to_string());
+                }
+            }
+            Format::Json => report_line(report.summary().to_json()),
+            Format::Plist => report_line(report.summary().to_plist()),
+            Format::Html => report_line(html::summary_to_html(&results, &errors, truncated)),
+            Format::Tap => unreachable!("rejected with the other arguments"),
+        }
+        if !errors.is_empty() || warned_as_error || too_many_flagged || stopped_early {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Warnings that fail the run show even with --only-problems
+    for result in results.iter() {
+        for warning in result.warnings.iter() {
+            if is_error(warning) {
+                eprintln!("ERROR: {warning}");
+            } else if !args.only_problems || result.overlap.is_some() {
+                eprintln!("WARNING: {warning}");
+            }
+        }
+    }
+    for error in errors.iter() {
+        eprintln!("ERROR: {error}");
+    }
+    let failed = !errors.is_empty() || warned_as_error || stopped_early;
 
-    eprintln!("Loaded {}", glyphs.len());
+    if !args.expect_overlap.is_empty() {
+        let expected = args
+            .expect_overlap
+            .iter()
+            .map(GlyphName::new)
+            .collect::<HashSet<_>>();
+        let mut regressed = false;
+        for result in results.iter().filter(|r| expected.contains(&r.name)) {
+            if result.overlap.is_none() {
+                report_line(format!(
+                    "{:?} {} was expected to overlap but no longer does",
+                    result.source, result.name
+                ));
+                regressed = true;
+            }
+        }
+        for name in expected
+            .iter()
+            .filter(|n| !results.iter().any(|r| &r.name == *n))
+        {
+            eprintln!("WARNING: {name} was expected to overlap but wasn't loaded");
+        }
+        if regressed || failed {
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    for glyph in &glyphs {
-        if glyph.has_fill_rule_discrepency() {
-            eprintln!("{:?} needs the overlap flag", glyph.source);
+    if let Some(expected) = expected {
+        let flagged = results
+            .iter()
+            .filter(|r| r.overlap.is_some())
+            .map(|r| r.name.clone())
+            .collect::<HashSet<_>>();
+        let mut added = flagged.difference(&expected).collect::<Vec<_>>();
+        let mut removed = expected.difference(&flagged).collect::<Vec<_>>();
+        added.sort();
+        removed.sort();
+        for name in added.iter() {
+            report_line(format!(
+                "{name} needs the overlap flag but isn't expected to"
+            ));
         }
+        for name in removed.iter() {
+            report_line(format!(
+                "{name} was expected to need the overlap flag but doesn't"
+            ));
+        }
+        if !added.is_empty() || !removed.is_empty() || failed {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if args.master_matrix {
+        let mut matrix = MasterMatrix::new(&results);
+        if matrix.masters.is_empty() {
+            eprintln!("WARNING: no designspace masters were checked, the matrix is empty");
+        }
+        if args.only_problems {
+            matrix = matrix.only_problems();
+        }
+        match args.format {
+            Format::Text => report_line(matrix.to_csv().trim_end().to_string()),
+            Format::Json => report_line(matrix.to_json()),
+            Format::Plist | Format::Tap => unreachable!("rejected with the other arguments"),
+            Format::Html => report_line(html::master_matrix_to_html(&matrix)),
+        }
+        if failed || too_many_flagged {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    match args.format {
+        Format::Text => {
+            for result in results.iter() {
+                if let Some(overlap) = &result.overlap {
+                    let mut glyph = result.describe();
+                    if renamed_glifs.contains(&result.source) {
+                        glyph = format!("{glyph} as {}", result.name);
+                    }
+                    if args.codepoints && !result.codepoints.is_empty() {
+                        glyph = format!("{glyph} {}", result.codepoint_labels());
+                    }
+                    report_line(format!("{glyph} needs the overlap flag ({overlap})"));
+                    if args.explain {
+                        let b = overlap.bbox;
+                        report_line(format!(
+                            "  {}: {}",
+                            overlap.kind,
+                            overlap.kind.explanation()
+                        ));
+                        report_line(format!(
+                            "  {} region(s), about {:.0} square units ({:.1}% of the glyph's ink), within ({}, {}) to ({}, {})",
+                            overlap.regions, overlap.area, overlap.area_percent(), b.x0, b.y0, b.x1, b.y1
+                        ));
+                    }
+                }
+            }
+            if args.by_regions {
+                report_line(RegionTally::new(&results).to_string());
+            }
+        }
+        Format::Json | Format::Plist | Format::Tap => {
+            let mut report = Report::new(&results, &errors).truncated(truncated);
+            if args.only_problems {
+                report = report.only_problems();
+            }
+            report_line(match args.format {
+                Format::Plist => report.to_plist(),
+                Format::Tap => report.to_tap(),
+                _ => report.to_json(),
+            });
+        }
+        Format::Html => report_line(html::to_html(&results, &thumbnails, &errors, truncated)),
+    }
+    if failed || too_many_flagged {
+        std::process::exit(1);
     }
 }
@@ -0,0 +1,216 @@
+//! Which masters of a designspace flag each glyph.
+//!
+//! A glyph that needs the overlap flag in only some masters is usually a drawing
+//! mistake in one of them, so those are what the matrix is for.
+
+use std::path::Path;
+
+use fontdrasil::types::GlyphName;
+use serde::Serialize;
+
+use crate::{report::SCHEMA_VERSION, CheckResult};
+
+/// Verdicts for every glyph, rows, in every master, columns.
+#[derive(Debug, Clone, Serialize)]
+pub struct MasterMatrix {
+    schema_version: u32,
+    /// Labels for the columns, the master's UFO relative to its designspace, with the
+    /// layer if it isn't the default
+    pub masters: Vec<String>,
+    pub glyphs: Vec<MatrixRow>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MatrixRow {
+    pub name: GlyphName,
+    /// Whether the glyph needs the overlap flag in each master, in the order of
+    /// [`MasterMatrix::masters`]. None if the master doesn't have the glyph, or it
+    /// couldn't be checked.
+    pub flagged: Vec<Option<bool>>,
+}
+
+impl MatrixRow {
+    /// Whether the masters that have the glyph disagree about the overlap flag.
+    pub fn differs(&self) -> bool {
+        let mut verdicts = self.flagged.iter().flatten();
+        let first = verdicts.next();
+        verdicts.any(|v| Some(v) != first)
+    }
+}
+
+impl MasterMatrix {
+    /// The matrix of the designspace masters among `results`; other results, including
+    /// interpolated instances, are left out. Masters and glyphs are in the order they
+    /// were first seen.
+    pub fn new(results: &[CheckResult]) -> Self {
+        let mut masters: Vec<String> = Vec::new();
+        let mut glyphs: Vec<MatrixRow> = Vec::new();
+        for result in results.iter().filter(|r| r.instance.is_none()) {
+            let Some(designspace) = &result.designspace else {
+                continue;
+            };
+            let master = master_label(designspace, &result.source);
+            let column = match masters.iter().position(|m| *m == master) {
+                Some(column) => column,
+                None => {
+                    masters.push(master);
+                    masters.len() - 1
+                }
+            };
+            let row = match glyphs.iter().position(|g| g.name == result.name) {
+                Some(row) => row,
+                None => {
+                    glyphs.push(MatrixRow {
+                        name: result.name.clone(),
+                        flagged: Vec::new(),
+                    });
+                    glyphs.len() - 1
+                }
+            };
+            let flagged = &mut glyphs[row].flagged;
+            if flagged.len() <= column {
+                flagged.resize(column + 1, None);
+            }
+            flagged[column] = Some(result.overlap.is_some());
+        }
+        for row in glyphs.iter_mut() {
+            row.flagged.resize(masters.len(), None);
+        }
+        Self {
+            schema_version: SCHEMA_VERSION,
+            masters,
+            glyphs,
+        }
+    }
+
+    /// Drop glyphs that no master flags.
+    pub fn only_problems(mut self) -> Self {
+        self.glyphs.retain(|g| g.flagged.contains(&Some(true)));
+        self
+    }
+
+    /// A header row of `glyph` then the masters, then a row per glyph of `flagged`,
+    /// `clean`, or nothing if the master doesn't have it.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("glyph");
+        for master in self.masters.iter() {
+            csv.push(',');
+            csv.push_str(&csv_field(master));
+        }
+        csv.push('\n');
+        for row in self.glyphs.iter() {
+            csv.push_str(&csv_field(row.name.as_str()));
+            for flagged in row.flagged.iter() {
+                csv.push(',');
+                csv.push_str(match flagged {
+                    Some(true) => "flagged",
+                    Some(false) => "clean",
+                    None => "",
+                });
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self)
+            .unwrap_or_else(|e| panic!("Unable to serialize master matrix: {e}"))
+    }
+}
+
+/// `source` is a .glif in a layer of a UFO, name the UFO as the designspace does, and
+/// the layer by its directory less the `glyphs.` every layer directory but the default's
+/// starts with.
+fn master_label(designspace: &Path, source: &Path) -> String {
+    let layer = source.parent().unwrap_or(source);
+    let relative = designspace
+        .parent()
+        .and_then(|dir| layer.strip_prefix(dir).ok())
+        .unwrap_or(layer);
+    let (Some(ufo), Some(layer)) = (relative.parent(), relative.file_name()) else {
+        return relative.to_string_lossy().into_owned();
+    };
+    let ufo = ufo.to_string_lossy();
+    match layer.to_string_lossy().as_ref() {
+        "glyphs" => ufo.into_owned(),
+        layer => format!("{ufo}/{}", layer.strip_prefix("glyphs.").unwrap_or(layer)),
+    }
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{Config, Glyph, Selection, Sources};
+
+    use super::{csv_field, MasterMatrix};
+
+    #[test]
+    fn masters_as_columns() {
+        let file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/testdata/Bars/Bars.designspace");
+        let config = Config::default();
+        let selection = Selection {
+            sources: Sources::Both,
+            ..Default::default()
+        };
+        let results = Glyph::stream_selected([file], selection)
+            .map(|g| g.unwrap().check(&config).unwrap())
+            .collect::<Vec<_>>();
+        let matrix = MasterMatrix::new(&results);
+
+        // Instances are left out, the bars only overlap in bold
+        assert_eq!(
+            "glyph,Bars-Regular.ufo,Bars-Bold.ufo\n\
+             bars,clean,flagged\n\
+             dollar,clean,clean\n\
+             dollar.alt,flagged,flagged\n",
+            matrix.to_csv()
+        );
+        assert_eq!(
+            vec!["bars"],
+            matrix
+                .glyphs
+                .iter()
+                .filter(|g| g.differs())
+                .map(|g| g.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(2, matrix.only_problems().glyphs.len());
+    }
+
+    #[test]
+    fn sparse_master_column() {
+        let file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/testdata/Bars/Sparse.designspace");
+        let config = Config::default();
+        let results = Glyph::from_file(file)
+            .into_iter()
+            .map(|g| g.unwrap().check(&config).unwrap())
+            .collect::<Vec<_>>();
+
+        // The sparse master only has bars, so has nothing to say about the rest
+        assert_eq!(
+            "glyph,Bars-Regular.ufo,Bars-Regular.ufo/wght550,Bars-Bold.ufo\n\
+             bars,clean,clean,flagged\n\
+             dollar,clean,,clean\n\
+             dollar.alt,flagged,,flagged\n",
+            MasterMatrix::new(&results).to_csv()
+        );
+    }
+
+    #[test]
+    fn escaped_fields() {
+        assert_eq!("a", csv_field("a"));
+        assert_eq!("\"a,\"\"b\"\"\"", csv_field("a,\"b\""));
+    }
+}
@@ -0,0 +1,129 @@
+//! Experimental: checking whether two glyphs collide when set side by side.
+//!
+//! Glyphs that are fine alone can run into each other once kerned. Rather than
+//! comparing fill rules we render each glyph of the pair, in the same frame, and
+//! look for pixels both cover.
+
+use kurbo::{Affine, Rect};
+use tiny_skia::FillRule;
+
+use crate::{render::render_tile_no_aa, Config, Error, Glyph};
+
+/// Where two glyphs set side by side cover the same ground.
+#[derive(Debug, Clone)]
+pub struct Collision {
+    /// Bounds of the pixels both glyphs cover, in font units (y-up) with the left
+    /// glyph's origin at 0,0
+    pub bbox: Rect,
+    /// Roughly how much area both glyphs cover, in square font units
+    pub area: f64,
+}
+
+/// Whether `right`, set after `left` with `kern` font units added to the advance of
+/// `left`, overlaps it, by at least [`Config::pixel_threshold`] pixels.
+///
+/// Only the outlines are compared, fill rule problems within either glyph are not
+/// collisions. `left` must have an advance width.
+pub fn check_pair(
+    left: &Glyph,
+    right: &Glyph,
+    kern: f64,
+    config: &Config,
+) -> Result<Option<Collision>, Error> {
+    let Some(advance) = left.advance else {
+        return Err(Error::NoAdvance {
+            file: left.source.clone(),
+            glyph: left.name.clone(),
+        });
+    };
+    // Nothing drawn can't collide, and tiny_skia won't make a path of it
+    if left.bezpath.elements().is_empty() || right.bezpath.elements().is_empty() {
+        return Ok(None);
+    }
+    let mut placed = right.bezpath.clone();
+    placed.apply_affine(Affine::translate((advance + kern, 0.0)));
+    let right = right.with_bezpath(placed);
+
+    // Render both in a frame big enough for the pair
+    let mut both = left.bezpath.clone();
+    both.extend(right.bezpath.iter());
+    let frame = left.with_bezpath(both);
+    let (transform, _) = frame.render_transform(config);
+    let size = frame.pixmap_size(config);
+    let render = |glyph: &Glyph| -> Result<_, Error> {
+        let path = glyph.path_in(transform)?;
+        Ok(render_tile_no_aa(&path, FillRule::Winding, (0, 0), size))
+    };
+    let (left_px, right_px) = (render(left)?, render(&right)?);
+
+    let width = size.0 as usize;
+    let shared = left_px
+        .pixels()
+        .iter()
+        .zip(right_px.pixels().iter())
+        .enumerate()
+        .filter(|(_, (l, r))| l.alpha() > 0 && r.alpha() > 0)
+        .map(|(i, _)| ((i % width) as f64, (i / width) as f64))
+        .collect::<Vec<_>>();
+    if shared.is_empty() || shared.len() < config.pixel_threshold {
+        return Ok(None);
+    }
+
+    let to_font_units = Affine::FLIP_Y * transform.inverse();
+    let bbox = shared
+        .iter()
+        .map(|(x, y)| Rect::new(*x, *y, x + 1.0, y + 1.0))
+        .reduce(|a, b| a.union(b))
+        .map(|r| to_font_units.transform_rect_bbox(r))
+        .unwrap_or_default();
+    Ok(Some(Collision {
+        bbox,
+        area: shared.len() as f64 / transform.determinant().abs(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use fontdrasil::types::GlyphName;
+    use kurbo::{Affine, BezPath};
+
+    use crate::{Config, Error, Glyph};
+
+    use super::check_pair;
+
+    fn square(name: &str) -> Glyph {
+        let mut bezpath = BezPath::from_svg("M0,0 L100,0 L100,100 L0,100 Z").unwrap();
+        bezpath.apply_affine(Affine::FLIP_Y);
+        let mut glyph = Glyph::new(GlyphName::new(name), Path::new("pair.json"), bezpath).unwrap();
+        glyph.advance = Some(100.0);
+        glyph
+    }
+
+    #[test]
+    fn kerned_into_each_other() {
+        let (left, right) = (square("left"), square("right"));
+        let config = Config::default();
+        // Touching isn't colliding
+        assert!(check_pair(&left, &right, 0.0, &config).unwrap().is_none());
+        assert!(check_pair(&left, &right, 10.0, &config).unwrap().is_none());
+
+        let collision = check_pair(&left, &right, -20.0, &config).unwrap().unwrap();
+        assert_eq!(2000.0, collision.area);
+        assert_eq!(
+            (80.0, 100.0),
+            (collision.bbox.min_x(), collision.bbox.max_x())
+        );
+    }
+
+    #[test]
+    fn left_needs_an_advance() {
+        let mut left = square("left");
+        left.advance = None;
+        assert!(matches!(
+            check_pair(&left, &square("right"), 0.0, &Config::default()),
+            Err(Error::NoAdvance { .. })
+        ));
+    }
+}
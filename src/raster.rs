@@ -0,0 +1,465 @@
+//! Deciding whether a glyph needs the overlap flag by rendering it with each fill rule.
+
+use std::collections::{HashMap, HashSet};
+
+use kurbo::{Affine, BezPath, PathEl, Point, Rect, Shape};
+use tiny_skia::FillRule;
+
+use crate::{
+    analytic::{inked_area, overlapping_contours},
+    check::{is_inside, Rasterizer},
+    glyph::contours,
+    render::render_tile_no_aa,
+    CheckResult, Config, Error, Glyph, Method, Overlap, OverlapKind, Warning,
+};
+
+/// What applying the threshold made of a glyph.
+struct Verdict {
+    /// Present if the glyph needs the overlap flag
+    overlap: Option<Overlap>,
+    /// Whether it was a close call
+    borderline: bool,
+    /// Pixels per font unit the verdict was rendered at
+    scale: (f64, f64),
+}
+
+/// Counts within this factor of the threshold, either way, are too close to call.
+const BORDERLINE_FACTOR: usize = 4;
+
+/// Anti-aliased pixels whose coverage differs by less than this, out of 255, between
+/// fill rules are edge noise rather than overlap.
+const COVERAGE_NOISE: u8 = 64;
+
+impl Glyph {
+    /// Check the glyph as it is, no trimming, rounding, or reorienting, by rendering.
+    pub(crate) fn check_raster(
+        &self,
+        config: &Config,
+        outline_hash: String,
+    ) -> Result<CheckResult, Error> {
+        let Verdict {
+            overlap,
+            borderline,
+            scale,
+        } = self.overlap(config)?;
+        let mut warnings = self.warnings.clone();
+        if config.anti_aliased_second_opinion && borderline {
+            let anti_aliased =
+                self.anti_aliased_discrepency(config)? >= config.pixel_threshold as f64;
+            if anti_aliased != overlap.is_some() {
+                warnings.push(Warning::MethodsDisagree {
+                    file: self.source.clone(),
+                    glyph: self.name.clone(),
+                    anti_aliased,
+                });
+            }
+        }
+        Ok(CheckResult {
+            name: self.name.clone(),
+            source: self.source.clone(),
+            instance: self.instance.clone(),
+            designspace: self.designspace.clone(),
+            method: Method::Raster,
+            overlap,
+            warnings,
+            outline_hash,
+            advance: self.advance,
+            codepoints: self.codepoints.clone(),
+            scale: Some(scale),
+        })
+    }
+
+    /// Apply the threshold to the fill rule discrepency, rechecking at a finer scale if
+    /// it's a close call so the verdict doesn't flap with small changes in scale.
+    fn overlap(&self, config: &Config) -> Result<Verdict, Error> {
+        let threshold = config.pixel_threshold;
+        // Past this many pixels the verdict can't change, so tiling can stop
+        let settled = if config.retry_scale <= 1.0 {
+            threshold
+        } else {
+            threshold * BORDERLINE_FACTOR
+        };
+        let (count, overlap) = self.fill_rule_discrepency(config, settled)?;
+        let borderline =
+            count * BORDERLINE_FACTOR > threshold && count < threshold * BORDERLINE_FACTOR;
+        if !borderline || config.retry_scale <= 1.0 {
+            return Ok(Verdict {
+                overlap: overlap.filter(|_| count >= threshold),
+                borderline,
+                scale: self.render_scale(config),
+            });
+        }
+
+        let finer = Config {
+            scale_x: config.scale_x * config.retry_scale,
+            scale_y: config.scale_y * config.retry_scale,
+            retry_scale: 1.0,
+            ..config.clone()
+        };
+        // Compare areas in font units, the pixmap size limit may have stopped us scaling
+        // as far as we asked
+        let px_per_unit = |config: &Config| self.render_transform(config).0.determinant().abs();
+        let threshold_area = threshold as f64 / px_per_unit(config);
+        let finer_settled = (threshold_area * px_per_unit(&finer)).ceil() as usize;
+        let (finer_count, finer_overlap) = self.fill_rule_discrepency(&finer, finer_settled)?;
+        let area = finer_count as f64 / px_per_unit(&finer);
+        Ok(Verdict {
+            overlap: finer_overlap.filter(|_| area >= threshold_area),
+            borderline,
+            scale: self.render_scale(&finer),
+        })
+    }
+
+    /// Look fill rule problems by rendering evenodd and nonzero and comparing.
+    ///
+    /// You'd think this woefully suboptimal but it gets you all the optimizations
+    /// that have gone into rendering images for free so a naive implementation does OK.
+    ///
+    /// With [`Config::tile_size`] rendering may stop once `settled` pixels differ, unless
+    /// there's an [`Config::edge_tolerance`] to erode them by, which could leave fewer.
+    /// Returns how many pixels differ, and what we make of them if any do.
+    fn fill_rule_discrepency(
+        &self,
+        config: &Config,
+        settled: usize,
+    ) -> Result<(usize, Option<Overlap>), Error> {
+        // Nothing drawn, or only lone points, can't overlap, and tiny_skia won't make a
+        // path of it
+        if self
+            .bezpath
+            .elements()
+            .iter()
+            .all(|el| matches!(el, PathEl::MoveTo(_)))
+        {
+            return Ok((0, None));
+        }
+        let (width, height) = self.pixmap_size(config);
+        let pixels = match config.tile_size {
+            Some(tile) if tile < width.max(height) && config.rasterizer == Rasterizer::TinySkia => {
+                let settled = if config.edge_tolerance > 0 {
+                    usize::MAX
+                } else {
+                    settled
+                };
+                self.tiled_discrepent_pixels(config, tile, settled)?
+            }
+            _ => self.discrepent_pixels(config)?,
+        };
+        let pixels = erode(pixels, config.edge_tolerance);
+        let Some(&first) = pixels.first() else {
+            return Ok((0, None));
+        };
+
+        // Find regions in a mask just big enough for the discrepent pixels, which
+        // can be far smaller than the pixmap
+        let (min, max) = pixels.iter().fold((first, first), |(min, max), &(x, y)| {
+            ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y)))
+        });
+        let mask_width = (max.0 - min.0 + 1) as usize;
+        let mut mask = vec![false; mask_width * (max.1 - min.1 + 1) as usize];
+        for (x, y) in pixels.iter() {
+            mask[(y - min.1) as usize * mask_width + (x - min.0) as usize] = true;
+        }
+
+        let (transform, _) = self.render_transform(config);
+        let to_glyph = transform.inverse();
+        let discrepent_pixels = pixels
+            .iter()
+            .map(|(x, y)| to_glyph * Point::new(*x as f64 + 0.5, *y as f64 + 0.5))
+            .collect::<Vec<_>>();
+
+        // Pixels are 1x1 around their centers, grow the bbox by half a pixel (in glyph units)
+        let half_px = to_glyph.transform_rect_bbox(Rect::new(0.0, 0.0, 0.5, 0.5));
+        let bbox = discrepent_pixels
+            .iter()
+            .fold(
+                Rect::from_points(discrepent_pixels[0], discrepent_pixels[0]),
+                |r, p| r.union_pt(*p),
+            )
+            .inflate(half_px.width(), half_px.height());
+        let regions = regions(&mut mask, mask_width);
+        let to_font_units =
+            Affine::FLIP_Y * to_glyph * Affine::translate((min.0 as f64, min.1 as f64));
+        let outlines = regions
+            .iter()
+            .map(|r| to_font_units * trace(r, mask_width))
+            .collect::<Vec<_>>();
+        Ok((
+            discrepent_pixels.len(),
+            Some(Overlap {
+                kind: self.classify(&discrepent_pixels),
+                bbox: Affine::FLIP_Y.transform_rect_bbox(bbox),
+                area: discrepent_pixels.len() as f64 / transform.determinant().abs(),
+                inked_area: inked_area(&self.bezpath),
+                regions: regions.len(),
+                outlines,
+                contours: overlapping_contours(&self.bezpath),
+            }),
+        ))
+    }
+
+    /// The x, y of every pixel the fill rules disagree on, rendering in one pass.
+    fn discrepent_pixels(&self, config: &Config) -> Result<Vec<(u32, u32)>, Error> {
+        // render without AA, we just want insideness from the pixels
+        let evenodd = self.render_no_aa(config, FillRule::EvenOdd)?;
+        let nonzero = self.render_no_aa(config, FillRule::Winding)?;
+
+        // Both come from the same outline and transform, so this is a bug, but one
+        // glyph's worth of bug
+        if (evenodd.width(), evenodd.height()) != (nonzero.width(), nonzero.height()) {
+            return Err(Error::Render {
+                file: self.source.clone(),
+                glyph: self.name.clone(),
+                reason: format!(
+                    "evenodd render is {}x{} but nonzero is {}x{}",
+                    evenodd.width(),
+                    evenodd.height(),
+                    nonzero.width(),
+                    nonzero.height()
+                ),
+            });
+        }
+
+        let width = evenodd.width() as usize;
+        let discrepent = evenodd
+            .pixels()
+            .iter()
+            .zip(nonzero.pixels().iter())
+            .enumerate()
+            .filter(|(_, (a, b))| a.alpha() != b.alpha())
+            .map(|(i, _)| ((i % width) as u32, (i / width) as u32))
+            .collect::<Vec<_>>();
+
+        if let Some(dir) = config.image_dir.as_deref() {
+            if !(config.only_differing_images && discrepent.is_empty()) {
+                self.save_debug_images(config, dir, evenodd, &nonzero, &discrepent);
+            }
+        }
+        Ok(discrepent)
+    }
+
+    /// How many pixels' worth of coverage the fill rules disagree on when rendered with
+    /// anti-aliasing, ignoring differences too small to be anything but edge noise.
+    ///
+    /// Always one pass, only close calls get here so tiling isn't worth it.
+    fn anti_aliased_discrepency(&self, config: &Config) -> Result<f64, Error> {
+        let evenodd = self.render_with(config, FillRule::EvenOdd, true)?;
+        let nonzero = self.render_with(config, FillRule::Winding, true)?;
+        let coverage = evenodd
+            .pixels()
+            .iter()
+            .zip(nonzero.pixels().iter())
+            .map(|(a, b)| a.alpha().abs_diff(b.alpha()))
+            .filter(|d| *d >= COVERAGE_NOISE)
+            .map(|d| d as f64 / 255.0)
+            .sum();
+        Ok(coverage)
+    }
+
+    /// Like [`Self::discrepent_pixels`] a `tile` pixel square at a time, left to right
+    /// then top to bottom, stopping after the tile that takes us to `settled` pixels.
+    ///
+    /// Only two tiles are ever held at once. No debug images, there'd be one per tile.
+    fn tiled_discrepent_pixels(
+        &self,
+        config: &Config,
+        tile: u32,
+        settled: usize,
+    ) -> Result<Vec<(u32, u32)>, Error> {
+        let path = self.create_path(config)?;
+        let (width, height) = self.pixmap_size(config);
+        let tile = tile.max(1);
+        let mut discrepent = Vec::new();
+        for y in (0..height).step_by(tile as usize) {
+            for x in (0..width).step_by(tile as usize) {
+                let size = (tile.min(width - x), tile.min(height - y));
+                let evenodd = render_tile_no_aa(&path, FillRule::EvenOdd, (x, y), size);
+                let nonzero = render_tile_no_aa(&path, FillRule::Winding, (x, y), size);
+                let tile_width = size.0 as usize;
+                discrepent.extend(
+                    evenodd
+                        .pixels()
+                        .iter()
+                        .zip(nonzero.pixels().iter())
+                        .enumerate()
+                        .filter(|(_, (a, b))| a != b)
+                        .map(|(i, _)| (x + (i % tile_width) as u32, y + (i / tile_width) as u32)),
+                );
+                if discrepent.len() >= settled {
+                    return Ok(discrepent);
+                }
+            }
+        }
+        Ok(discrepent)
+    }
+
+    /// Guess why the fill rules disagree at the given points, in glyph space.
+    ///
+    /// Every discrepent point has nonzero, even, winding so it's covered by several
+    /// contours wound the same way (or one contour more than once). If any two of those
+    /// are nested it's a hole that was drawn in the same direction as its outline,
+    /// otherwise it's filled shapes overlapping.
+    fn classify(&self, discrepent: &[Point]) -> OverlapKind {
+        let contours = contours(&self.bezpath);
+
+        // Checking every pixel of a big overlap is slow and tells us nothing new
+        let stride = (discrepent.len() / 256).max(1);
+        for p in discrepent.iter().step_by(stride) {
+            let covering = contours
+                .iter()
+                .filter(|c| c.winding(*p) != 0)
+                .collect::<Vec<_>>();
+            for (i, a) in covering.iter().enumerate() {
+                for b in covering.iter().skip(i + 1) {
+                    if is_inside(a, b) || is_inside(b, a) {
+                        return OverlapKind::Hole;
+                    }
+                }
+            }
+        }
+        OverlapKind::OverlappingFills
+    }
+}
+
+/// Only the `pixels` with every pixel within `by` of them, across or diagonally, also in
+/// `pixels`. Strips up to `2 * by` pixels wide go entirely, anything wider is thinned.
+fn erode(pixels: Vec<(u32, u32)>, by: u32) -> Vec<(u32, u32)> {
+    if by == 0 {
+        return pixels;
+    }
+    let set = pixels.iter().copied().collect::<HashSet<_>>();
+    let by = by as i64;
+    let kept = |&(x, y): &(u32, u32)| {
+        (-by..=by).all(|dy| {
+            (-by..=by).all(|dx| {
+                let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                nx >= 0 && ny >= 0 && set.contains(&(nx as u32, ny as u32))
+            })
+        })
+    };
+    pixels.into_iter().filter(kept).collect()
+}
+
+/// The 8-connected groups of set pixels in a `width` wide mask, as pixel indices.
+/// Clears the mask.
+fn regions(mask: &mut [bool], width: usize) -> Vec<Vec<usize>> {
+    let height = mask.len() / width;
+    let mut regions = Vec::new();
+    let mut todo = Vec::new();
+    for start in 0..mask.len() {
+        if !mask[start] {
+            continue;
+        }
+        let mut region = vec![start];
+        mask[start] = false;
+        todo.push(start);
+        while let Some(i) = todo.pop() {
+            let (x, y) = ((i % width) as isize, (i / width) as isize);
+            for (dx, dy) in (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| (dx, dy))) {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize {
+                    continue;
+                }
+                let n = ny as usize * width + nx as usize;
+                if mask[n] {
+                    mask[n] = false;
+                    region.push(n);
+                    todo.push(n);
+                }
+            }
+        }
+        regions.push(region);
+    }
+    regions
+}
+
+/// The boundary of a region of pixels, by index into a `width` wide pixmap, in pixel
+/// coordinates. Only corners are kept, so each edge is a single line.
+///
+/// Every pixel edge not shared with another pixel of the region is part of the
+/// boundary. Taking them clockwise around each pixel and following one into the next
+/// walks the outside clockwise and any holes counter-clockwise. Where pixels only touch
+/// diagonally two edges leave the same corner; we always take the left turn, which
+/// goes on into the diagonal neighbour, so the region traces as one 8-connected piece.
+fn trace(region: &[usize], width: usize) -> BezPath {
+    let mut region = region.to_vec();
+    region.sort_unstable();
+    let ordered = region
+        .iter()
+        .map(|i| ((i % width) as i64, (i / width) as i64))
+        .collect::<Vec<_>>();
+    let pixels = ordered.iter().copied().collect::<HashSet<_>>();
+    let mut edges: HashMap<(i64, i64), Vec<(i64, i64)>> = HashMap::new();
+    for &(x, y) in ordered.iter() {
+        for (neighbour, from, to) in [
+            ((x, y - 1), (x, y), (x + 1, y)),
+            ((x + 1, y), (x + 1, y), (x + 1, y + 1)),
+            ((x, y + 1), (x + 1, y + 1), (x, y + 1)),
+            ((x - 1, y), (x, y + 1), (x, y)),
+        ] {
+            if !pixels.contains(&neighbour) {
+                edges.entry(from).or_default().push(to);
+            }
+        }
+    }
+
+    let mut path = BezPath::new();
+    // Sorted so the same region always traces the same way
+    let mut starts = edges.keys().copied().collect::<Vec<_>>();
+    starts.sort_unstable();
+    for start in starts {
+        let mut corners: Vec<(i64, i64)> = Vec::new();
+        let mut at = start;
+        while let Some(next) = edges.get_mut(&at).and_then(|e| {
+            // y-down, so the left turn is the most negative cross product
+            let turn = |to: &(i64, i64)| match corners.last() {
+                Some(prev) => (at.0 - prev.0) * (to.1 - at.1) - (at.1 - prev.1) * (to.0 - at.0),
+                None => 0,
+            };
+            let i = (0..e.len()).min_by_key(|i| (turn(&e[*i]), e[*i]))?;
+            Some(e.swap_remove(i))
+        }) {
+            corners.push(at);
+            at = next;
+        }
+        if corners.is_empty() {
+            continue;
+        }
+        let n = corners.len();
+        let straight = |i: usize| {
+            let (prev, p, next) = (corners[(i + n - 1) % n], corners[i], corners[(i + 1) % n]);
+            (p.0 - prev.0) * (next.1 - p.1) == (p.1 - prev.1) * (next.0 - p.0)
+        };
+        let mut corners = (0..n)
+            .filter(|i| !straight(*i))
+            .map(|i| Point::new(corners[i].0 as f64, corners[i].1 as f64));
+        let Some(first) = corners.next() else {
+            continue;
+        };
+        path.move_to(first);
+        corners.for_each(|p| path.line_to(p));
+        path.close_path();
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::trace;
+
+    #[test]
+    fn diagonal_pinch_traced_as_one() {
+        // Two pixels touching only at a corner, in a 2 pixel wide pixmap
+        assert_eq!(
+            "M0,0 L1,0 L1,1 L2,1 L2,2 L1,2 L1,1 L0,1 Z",
+            trace(&[3, 0], 2).to_svg()
+        );
+        // Touching the other way, and whatever order the region comes in
+        for region in [[1, 2], [2, 1]] {
+            assert_eq!(
+                "M0,1 L1,1 L1,0 L2,0 L2,1 L1,1 L1,2 L0,2 Z",
+                trace(&region, 2).to_svg()
+            );
+        }
+    }
+}
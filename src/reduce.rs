@@ -0,0 +1,88 @@
+//! Shrinking a glyph that needs the overlap flag to a few contours that still do.
+//!
+//! Experimental. Contours are dropped one at a time, each for good if the glyph still
+//! needs the flag without it, so no one contour of what's left can go, though some
+//! other, smaller, set might still need the flag.
+
+use kurbo::BezPath;
+
+use crate::{glyph::contours, Config, Error, Glyph};
+
+/// The fewest of `glyph`'s contours we could find that still need the overlap flag at
+/// `config`, as a glyph of the same name, e.g. for [`Glyph::export_json`] to attach to
+/// a bug report. None if `glyph` doesn't need the flag to begin with.
+///
+/// Checks the glyph again for each contour, so expect it to take that many times as
+/// long as [`Glyph::check`].
+pub fn minimal_repro(glyph: &Glyph, config: &Config) -> Result<Option<Glyph>, Error> {
+    if glyph.check(config)?.overlap.is_none() {
+        return Ok(None);
+    }
+    let with_contours = |contours: &[BezPath]| {
+        glyph.with_bezpath(BezPath::from_vec(
+            contours
+                .iter()
+                .flat_map(|c| c.elements().to_vec())
+                .collect(),
+        ))
+    };
+    let mut kept = contours(&glyph.bezpath);
+    let mut i = 0;
+    while i < kept.len() {
+        let mut without = kept.clone();
+        without.remove(i);
+        if with_contours(&without).check(config)?.overlap.is_some() {
+            kept = without;
+        } else {
+            i += 1;
+        }
+    }
+    Ok(Some(with_contours(&kept)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use fontdrasil::types::GlyphName;
+    use kurbo::BezPath;
+
+    use crate::{glyph::contours, Config, Glyph};
+
+    use super::minimal_repro;
+
+    fn glyph(svg: &str) -> Glyph {
+        let bezpath = BezPath::from_svg(svg).unwrap();
+        Glyph::new(GlyphName::new("repro"), Path::new("repro.json"), bezpath).unwrap()
+    }
+
+    #[test]
+    fn only_overlapping_contours_kept() {
+        // A dot, the two bars that overlap, and another dot
+        let glyph = glyph(
+            "M0,200 L50,200 L50,250 L0,250 Z \
+             M0,0 L100,0 L100,50 L0,50 Z \
+             M50,0 L150,0 L150,50 L50,50 Z \
+             M300,0 L350,0 L350,50 L300,50 Z",
+        );
+        let config = Config::default();
+        let repro = minimal_repro(&glyph, &config).unwrap().unwrap();
+        assert_eq!(
+            vec![
+                "M0,0 L100,0 L100,50 L0,50 Z",
+                "M50,0 L150,0 L150,50 L50,50 Z"
+            ],
+            contours(&repro.bezpath)
+                .iter()
+                .map(BezPath::to_svg)
+                .collect::<Vec<_>>()
+        );
+        assert!(repro.check(&config).unwrap().overlap.is_some());
+    }
+
+    #[test]
+    fn clean_glyph_has_no_repro() {
+        let glyph = glyph("M0,0 L100,0 L100,50 L0,50 Z M200,0 L300,0 L300,50 L200,50 Z");
+        assert!(minimal_repro(&glyph, &Config::default()).unwrap().is_none());
+    }
+}
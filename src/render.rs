@@ -0,0 +1,607 @@
+//! Rasterizing glyphs with tiny_skia.
+//!
+//! Where a glyph lands in its pixmap is plain geometry, and available without the
+//! raster feature, e.g. to warn about contours too small to render.
+
+#[cfg(feature = "raster")]
+use std::path::Path;
+
+use kurbo::{Affine, Rect, Shape};
+#[cfg(feature = "raster")]
+use tiny_skia::{FillRule, Paint, Pixmap, PremultipliedColorU8};
+
+#[cfg(feature = "raster")]
+use crate::{check::Rasterizer, glyph::ensure_finite, Error};
+use crate::{Config, Glyph};
+
+/// The least margin, in font units, so a glyph with no width or height still gets a pixmap.
+const MIN_MARGIN: f64 = 1.0;
+
+/// Glyphs from fonts with a smaller em are rendered proportionally larger, so they get
+/// as many pixels as they would with this many units per em.
+const MIN_UNITS_PER_EM: f64 = 1000.0;
+
+impl Glyph {
+    /// The transform from glyph to pixmap space and the extents of the pixmap.
+    ///
+    /// Each axis is scaled independently, enlarged for fonts of fewer than
+    /// [`MIN_UNITS_PER_EM`] units per em, and reduced as needed to fit within
+    /// [`Config::max_pixmap_size`].
+    pub(crate) fn render_transform(&self, config: &Config) -> (Affine, Rect) {
+        // move the path to start at 0,0
+        let mut bbox = self.bezpath.bounding_box();
+        if let Some(advance) = self.advance.filter(|_| config.include_advance) {
+            bbox = bbox.union(Rect::new(0.0, bbox.min_y(), advance, bbox.max_y()));
+        }
+        let margin = (bbox.width().max(bbox.height()) * config.margin).max(MIN_MARGIN);
+        let width = bbox.width() + 2.0 * margin;
+        let height = bbox.height() + 2.0 * margin;
+
+        let max_size = config.max_pixmap_size as f64;
+        let em_scale = self.em_scale();
+        let scale_x = (config.scale_x * em_scale).min(max_size / width);
+        let scale_y = (config.scale_y * em_scale).min(max_size / height);
+
+        let transform = Affine::scale_non_uniform(scale_x, scale_y)
+            * Affine::translate((-bbox.min_x() + margin, -bbox.min_y() + margin));
+        (
+            transform,
+            Rect::new(0.0, 0.0, width * scale_x, height * scale_y),
+        )
+    }
+
+    /// The width and height, in pixels, of the pixmaps checking renders at `config`.
+    pub fn pixmap_size(&self, config: &Config) -> (u32, u32) {
+        let (_, extents) = self.render_transform(config);
+        (extents.width() as u32, extents.height() as u32)
+    }
+
+    /// The scale, x and y, checking renders at `config`: the requested scale reduced as
+    /// needed to fit [`Config::max_pixmap_size`]. It's the scale to ask for to render
+    /// the same again, so for a font with a small em, see [`Glyph::units_per_em`], it's
+    /// less than the pixels per font unit actually rendered at.
+    pub fn render_scale(&self, config: &Config) -> (f64, f64) {
+        let [scale_x, _, _, scale_y, _, _] = self.render_transform(config).0.as_coeffs();
+        let em_scale = self.em_scale();
+        (scale_x / em_scale, scale_y / em_scale)
+    }
+
+    /// How much larger than asked to render, so a small em gets enough pixels.
+    fn em_scale(&self) -> f64 {
+        self.units_per_em
+            .filter(|u| *u > 0.0)
+            .map_or(1.0, |u| (MIN_UNITS_PER_EM / u).max(1.0))
+    }
+}
+
+#[cfg(feature = "raster")]
+impl Glyph {
+    pub(crate) fn create_path(&self, config: &Config) -> Result<tiny_skia::Path, Error> {
+        let (transform, _) = self.render_transform(config);
+        self.path_in(transform)
+    }
+
+    /// The outline as a tiny_skia path, with `transform` from glyph to pixmap space applied.
+    pub(crate) fn path_in(&self, transform: Affine) -> Result<tiny_skia::Path, Error> {
+        let mut bez = self.bezpath.clone();
+        bez.apply_affine(transform);
+        // finite in font units can still overflow once scaled
+        ensure_finite(&self.source, &self.name, &bez)?;
+
+        let mut pb = tiny_skia::PathBuilder::new();
+        for el in bez.iter() {
+            match el {
+                kurbo::PathEl::MoveTo(p) => pb.move_to(p.x as f32, p.y as f32),
+                kurbo::PathEl::LineTo(p) => pb.line_to(p.x as f32, p.y as f32),
+                kurbo::PathEl::QuadTo(c, p) => {
+                    pb.quad_to(c.x as f32, c.y as f32, p.x as f32, p.y as f32)
+                }
+                kurbo::PathEl::CurveTo(c0, c1, p) => pb.cubic_to(
+                    c0.x as f32,
+                    c0.y as f32,
+                    c1.x as f32,
+                    c1.y as f32,
+                    p.x as f32,
+                    p.y as f32,
+                ),
+                kurbo::PathEl::ClosePath => pb.close(),
+            }
+        }
+
+        // Coordinates are finite, so only an outline with nothing but lone points is refused
+        pb.finish().ok_or_else(|| Error::Render {
+            file: self.source.clone(),
+            glyph: self.name.clone(),
+            reason: "nothing to fill, the outline is empty or only lone points".to_string(),
+        })
+    }
+
+    /// Render the glyph in the default gray at `scale` pixels per font unit.
+    ///
+    /// The pixmap covers the glyph's bounds plus a margin. Scale is reduced if need be
+    /// to keep it within the default [`Config::max_pixmap_size`].
+    pub fn render(
+        &self,
+        fill_rule: FillRule,
+        anti_alias: bool,
+        scale: f64,
+    ) -> Result<Pixmap, Error> {
+        let config = Config {
+            scale_x: scale,
+            scale_y: scale,
+            ..Default::default()
+        };
+        self.render_with(&config, fill_rule, anti_alias)
+    }
+
+    pub(crate) fn render_with(
+        &self,
+        config: &Config,
+        fill_rule: FillRule,
+        anti_alias: bool,
+    ) -> Result<Pixmap, Error> {
+        let path = self.create_path(config)?;
+        let (width, height) = self.pixmap_size(config);
+        let mut pixmap =
+            Pixmap::new(width, height).unwrap_or_else(|| panic!("Unable to create pixmap"));
+        let mut paint = Paint::default();
+        let (r, g, b) = config.fill_color_for(fill_rule);
+        paint.set_color_rgba8(r, g, b, 255);
+        paint.anti_alias = anti_alias;
+        pixmap.fill_path(
+            &path,
+            &paint,
+            fill_rule,
+            tiny_skia::Transform::identity(),
+            None,
+        );
+        Ok(pixmap)
+    }
+
+    pub(crate) fn render_no_aa(
+        &self,
+        config: &Config,
+        fill_rule: FillRule,
+    ) -> Result<Pixmap, Error> {
+        match config.rasterizer {
+            Rasterizer::TinySkia => self.render_with(config, fill_rule, false), // AA just confuses diffs
+            #[cfg(feature = "freetype")]
+            Rasterizer::FreeType => self.render_freetype(config, fill_rule),
+        }
+    }
+
+    /// Write the renders compared, and where they differ highlighted over evenodd, to
+    /// `dir`, the [`Config::image_dir`]: three images, or one with them side by side if
+    /// [`Config::side_by_side_images`].
+    pub(crate) fn save_debug_images(
+        &self,
+        config: &Config,
+        dir: &Path,
+        evenodd: Pixmap,
+        nonzero: &Pixmap,
+        discrepent: &[(u32, u32)],
+    ) {
+        let mut diff = evenodd.clone();
+        let width = diff.width();
+        let pixels = diff.pixels_mut();
+        for (x, y) in discrepent {
+            pixels[(y * width + x) as usize] = highlight();
+        }
+        let stem = self.debug_file_stem();
+        let file = |suffix: &str| dir.join(format!("{stem}.{suffix}")).display().to_string();
+        if config.raw_buffers {
+            save_npy(&file("evenodd.npy"), &evenodd);
+            save_npy(&file("nonzero.npy"), nonzero);
+        }
+        if config.side_by_side_images {
+            let composite = side_by_side(&[&evenodd, nonzero, &diff]);
+            save_debug_image(&file("side_by_side.png"), &composite);
+            return;
+        }
+        save_debug_image(&file("evenodd.png"), &evenodd);
+        save_debug_image(&file("nonzero.png"), nonzero);
+        save_debug_image(&file("diff.png"), &diff);
+    }
+
+    /// What debug files for the glyph start with: its name, then the instance or master
+    /// it came from if it was loaded through a designspace, where each has a glyph of
+    /// that name and they're checked at the same time.
+    fn debug_file_stem(&self) -> String {
+        let from = match (&self.instance, &self.designspace) {
+            (Some(instance), _) => Some(instance.clone()),
+            (None, Some(_)) => self
+                .source
+                .ancestors()
+                .find(|p| p.extension().and_then(|e| e.to_str()) == Some("ufo"))
+                .and_then(|ufo| ufo.file_stem())
+                .map(|stem| stem.to_string_lossy().into_owned()),
+            (None, None) => None,
+        };
+        match from {
+            Some(from) => format!("{}.{}", self.name, from.replace(['/', '\\'], "_")),
+            None => self.name.to_string(),
+        }
+    }
+
+    /// The evenodd render with the pixels nonzero disagrees on highlighted, like the
+    /// diff debug image but without writing anything.
+    pub fn diff_image(&self, config: &Config) -> Result<Pixmap, Error> {
+        self.diff(config).map(|(diff, _)| diff)
+    }
+
+    /// [`Glyph::diff_image`], but only if the fill rules disagree on any pixel, e.g. for
+    /// an editor to show while the glyph is drawn. Unlike the verdict this doesn't
+    /// discount differences under [`Config::pixel_threshold`] or recheck close calls.
+    pub fn diff_pixmap(&self, config: &Config) -> Result<Option<Pixmap>, Error> {
+        self.diff(config)
+            .map(|(diff, differs)| differs.then_some(diff))
+    }
+
+    /// The diff image and whether anything in it is highlighted.
+    fn diff(&self, config: &Config) -> Result<(Pixmap, bool), Error> {
+        let mut evenodd = self.render_no_aa(config, FillRule::EvenOdd)?;
+        let nonzero = self.render_no_aa(config, FillRule::Winding)?;
+        let mut differs = false;
+        for (e, n) in evenodd.pixels_mut().iter_mut().zip(nonzero.pixels()) {
+            if e.alpha() != n.alpha() {
+                *e = highlight();
+                differs = true;
+            }
+        }
+        Ok((evenodd, differs))
+    }
+
+    /// Evenodd on the left, nonzero in the middle, and [`Glyph::diff_image`] on the right,
+    /// in one image to compare at a glance.
+    pub fn side_by_side_image(&self, config: &Config) -> Result<Pixmap, Error> {
+        let evenodd = self.render_no_aa(config, FillRule::EvenOdd)?;
+        let nonzero = self.render_no_aa(config, FillRule::Winding)?;
+        let diff = self.diff_image(config)?;
+        Ok(side_by_side(&[&evenodd, &nonzero, &diff]))
+    }
+}
+
+#[cfg(feature = "raster")]
+/// Transparent pixels between the images of a side by side composite.
+const SIDE_BY_SIDE_GAP: u32 = 8;
+
+#[cfg(feature = "raster")]
+/// `images`, all the same size, left to right with a gap between each.
+fn side_by_side(images: &[&Pixmap]) -> Pixmap {
+    let (width, height) = (images[0].width(), images[0].height());
+    let total_width = images.len() as u32 * (width + SIDE_BY_SIDE_GAP) - SIDE_BY_SIDE_GAP;
+    let mut composite =
+        Pixmap::new(total_width, height).unwrap_or_else(|| panic!("Unable to create pixmap"));
+    for (i, image) in images.iter().enumerate() {
+        composite.draw_pixmap(
+            (i as u32 * (width + SIDE_BY_SIDE_GAP)) as i32,
+            0,
+            image.as_ref(),
+            &tiny_skia::PixmapPaint::default(),
+            tiny_skia::Transform::identity(),
+            None,
+        );
+    }
+    composite
+}
+
+#[cfg(feature = "raster")]
+impl Config {
+    /// What to fill a render with `fill_rule` with.
+    pub(crate) fn fill_color_for(&self, fill_rule: FillRule) -> (u8, u8, u8) {
+        match fill_rule {
+            FillRule::Winding => self.nonzero_fill_color.unwrap_or(self.fill_color),
+            FillRule::EvenOdd => self.fill_color,
+        }
+    }
+}
+
+#[cfg(feature = "raster")]
+/// The color of pixels the fill rules disagree on in diff images.
+fn highlight() -> PremultipliedColorU8 {
+    PremultipliedColorU8::from_rgba(255, 20, 147, 255).unwrap()
+}
+
+#[cfg(feature = "raster")]
+/// Fill `path`, already in pixmap space, without anti-aliasing, keeping only the
+/// `width` by `height` pixels whose top left is at `x`, `y`.
+pub(crate) fn render_tile_no_aa(
+    path: &tiny_skia::Path,
+    fill_rule: FillRule,
+    (x, y): (u32, u32),
+    (width, height): (u32, u32),
+) -> Pixmap {
+    let mut pixmap =
+        Pixmap::new(width, height).unwrap_or_else(|| panic!("Unable to create pixmap"));
+    // Color doesn't matter, we only compare coverage
+    let paint = Paint {
+        anti_alias: false,
+        ..Default::default()
+    };
+    pixmap.fill_path(
+        path,
+        &paint,
+        fill_rule,
+        tiny_skia::Transform::from_translate(-(x as f32), -(y as f32)),
+        None,
+    );
+    pixmap
+}
+
+#[cfg(feature = "raster")]
+pub(crate) fn save_debug_image(filename: &str, pixmap: &Pixmap) {
+    std::fs::write(
+        filename,
+        pixmap
+            .encode_png()
+            .unwrap_or_else(|e| panic!("Failed to encode png for {filename}: {e}")),
+    )
+    .unwrap_or_else(|e| panic!("Failed to write {filename}: {e}"));
+    eprintln!("Wrote {filename}");
+}
+
+#[cfg(feature = "raster")]
+fn save_npy(filename: &str, pixmap: &Pixmap) {
+    std::fs::write(filename, to_npy(pixmap))
+        .unwrap_or_else(|e| panic!("Failed to write {filename}: {e}"));
+    eprintln!("Wrote {filename}");
+}
+
+/// The coverage, alpha, of each pixel of `pixmap` as a version 1.0 .npy: a height x
+/// width array of unsigned bytes, row by row from the top.
+#[cfg(feature = "raster")]
+fn to_npy(pixmap: &Pixmap) -> Vec<u8> {
+    let mut header = format!(
+        "{{'descr': '|u1', 'fortran_order': False, 'shape': ({}, {}), }}",
+        pixmap.height(),
+        pixmap.width()
+    );
+    // Magic, version, and header length take 10 bytes, the data starts 64 byte aligned
+    let padded = (10 + header.len() + 1).div_ceil(64) * 64 - 10;
+    header.push_str(&" ".repeat(padded - header.len() - 1));
+    header.push('\n');
+
+    let mut npy = b"\x93NUMPY\x01\x00".to_vec();
+    npy.extend((header.len() as u16).to_le_bytes());
+    npy.extend(header.as_bytes());
+    npy.extend(pixmap.pixels().iter().map(|p| p.alpha()));
+    npy
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use fontdrasil::types::GlyphName;
+    use kurbo::BezPath;
+
+    use crate::{Config, Glyph};
+
+    #[test]
+    fn zero_size_bbox_still_has_a_margin() {
+        let bezpath = BezPath::from_svg("M100,100 L100,100 Z").unwrap();
+        let glyph = Glyph::new(GlyphName::new("dot"), Path::new("dot.json"), bezpath).unwrap();
+        let (width, height) = glyph.pixmap_size(&Config::default());
+        assert!(width > 0 && height > 0, "{width}x{height}");
+    }
+
+    #[test]
+    fn margin_is_configurable() {
+        let bezpath = BezPath::from_svg("M0,0 L100,0 L100,50 L0,50 Z").unwrap();
+        let glyph = Glyph::new(GlyphName::new("bar"), Path::new("bar.json"), bezpath).unwrap();
+        let config = Config {
+            margin: 0.5,
+            ..Default::default()
+        };
+        assert_eq!((200, 150), glyph.pixmap_size(&config));
+    }
+
+    #[test]
+    fn advance_included_on_request() {
+        let bezpath = BezPath::from_svg("M100,0 L200,0 L200,100 L100,100 Z").unwrap();
+        let mut glyph = Glyph::new(GlyphName::new("bar"), Path::new("bar.json"), bezpath).unwrap();
+        glyph.advance = Some(400.0);
+        let config = Config {
+            margin: 0.0,
+            ..Default::default()
+        };
+        assert_eq!((102, 102), glyph.pixmap_size(&config));
+        let config = Config {
+            include_advance: true,
+            ..config
+        };
+        // 0 to 400 plus the least margin on each side
+        assert_eq!((402, 102), glyph.pixmap_size(&config));
+    }
+
+    #[test]
+    fn scale_reduced_to_fit() {
+        let bezpath = BezPath::from_svg("M0,0 L1000,0 L1000,100 L0,100 Z").unwrap();
+        let glyph = Glyph::new(GlyphName::new("bar"), Path::new("bar.json"), bezpath).unwrap();
+        let config = Config {
+            scale_x: 2.0,
+            scale_y: 2.0,
+            max_pixmap_size: 1200,
+            margin: 0.0,
+            ..Default::default()
+        };
+        let (scale_x, scale_y) = glyph.render_scale(&config);
+        // 1002 font units wide with the least margin doesn't fit at 2
+        assert_eq!((1200.0 / 1002.0, 2.0), (scale_x, scale_y));
+    }
+
+    #[test]
+    fn small_em_rendered_larger() {
+        let bezpath = BezPath::from_svg("M0,0 L10,0 L10,10 L0,10 Z").unwrap();
+        let mut glyph = Glyph::new(GlyphName::new("box"), Path::new("box.json"), bezpath).unwrap();
+        let config = Config {
+            margin: 0.0,
+            ..Default::default()
+        };
+        assert_eq!((12, 12), glyph.pixmap_size(&config));
+        // As many pixels as at 1000 units per em, and the same scale to reproduce it
+        glyph.units_per_em = Some(16.0);
+        assert_eq!((750, 750), glyph.pixmap_size(&config));
+        assert_eq!((1.0, 1.0), glyph.render_scale(&config));
+        glyph.units_per_em = Some(2048.0);
+        assert_eq!((12, 12), glyph.pixmap_size(&config));
+    }
+
+    #[cfg(feature = "raster")]
+    #[test]
+    fn images_written_to_image_dir() {
+        let bezpath =
+            BezPath::from_svg("M0,0 L100,0 L100,50 L0,50 Z M50,0 L150,0 L150,50 L50,50 Z").unwrap();
+        let glyph =
+            Glyph::new(GlyphName::new("image_dir"), Path::new("bars.json"), bezpath).unwrap();
+        let dir = std::env::temp_dir().join("glyph_overlaps_image_dir");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        // Nothing is written unless asked for
+        glyph.check(&Config::default()).unwrap();
+        assert_eq!(0, std::fs::read_dir(&dir).unwrap().count());
+
+        let config = Config {
+            image_dir: Some(dir.clone()),
+            ..Default::default()
+        };
+        glyph.check(&config).unwrap();
+        for image in ["evenodd", "nonzero", "diff"] {
+            let file = dir.join(format!("image_dir.{image}.png"));
+            assert!(file.is_file(), "{file:?}");
+        }
+    }
+
+    #[cfg(feature = "raster")]
+    #[test]
+    fn images_named_by_master_and_instance() {
+        let file =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/testdata/Bars/Bars.designspace");
+        let dir = std::env::temp_dir().join("glyph_overlaps_designspace_images");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = Config {
+            image_dir: Some(dir.clone()),
+            ..Default::default()
+        };
+        let selection = crate::Selection {
+            sources: crate::Sources::Both,
+            ..Default::default()
+        };
+        for glyph in Glyph::stream_selected([file], selection) {
+            glyph.unwrap().check(&config).unwrap();
+        }
+        // A file each, rather than each master and instance writing over the last
+        for from in ["Bars-Regular", "Bars-Bold", "Bars Book", "Bars SemiBold"] {
+            let file = dir.join(format!("bars.{from}.diff.png"));
+            assert!(file.is_file(), "{file:?}");
+        }
+    }
+
+    #[cfg(feature = "raster")]
+    #[test]
+    fn diff_pixmap_only_if_rules_disagree() {
+        let config = Config::default();
+        let overlapping =
+            BezPath::from_svg("M0,0 L100,0 L100,50 L0,50 Z M50,0 L150,0 L150,50 L50,50 Z").unwrap();
+        let glyph =
+            Glyph::new(GlyphName::new("bars"), Path::new("bars.json"), overlapping).unwrap();
+        let diff = glyph.diff_pixmap(&config).unwrap().unwrap();
+        assert_eq!(glyph.diff_image(&config).unwrap(), diff);
+        assert!(diff.pixels().contains(&super::highlight()));
+
+        let apart =
+            BezPath::from_svg("M0,0 L100,0 L100,50 L0,50 Z M200,0 L300,0 L300,50 L200,50 Z")
+                .unwrap();
+        let glyph = Glyph::new(GlyphName::new("bars"), Path::new("bars.json"), apart).unwrap();
+        assert!(glyph.diff_pixmap(&config).unwrap().is_none());
+    }
+
+    #[cfg(feature = "raster")]
+    #[test]
+    fn side_by_side_image() {
+        let bezpath =
+            BezPath::from_svg("M0,0 L100,0 L100,50 L0,50 Z M50,0 L150,0 L150,50 L50,50 Z").unwrap();
+        let glyph = Glyph::new(GlyphName::new("bars"), Path::new("bars.json"), bezpath).unwrap();
+        let config = Config::default();
+        let (width, height) = glyph.pixmap_size(&config);
+        let image = glyph.side_by_side_image(&config).unwrap();
+        assert_eq!(
+            (3 * width + 2 * super::SIDE_BY_SIDE_GAP, height),
+            (image.width(), image.height())
+        );
+        // The diff is on the right
+        let diff = glyph.diff_image(&config).unwrap();
+        let right = image
+            .pixels()
+            .chunks(image.width() as usize)
+            .flat_map(|row| &row[row.len() - width as usize..])
+            .copied()
+            .collect::<Vec<_>>();
+        assert_eq!(diff.pixels(), right.as_slice());
+    }
+
+    #[cfg(feature = "raster")]
+    #[test]
+    fn nothing_to_fill() {
+        use tiny_skia::FillRule;
+
+        let bezpath = BezPath::from_svg("M0,0").unwrap();
+        let glyph = Glyph::new(GlyphName::new("dot"), Path::new("dot.json"), bezpath).unwrap();
+        let Err(crate::Error::Render { reason, .. }) = glyph.render(FillRule::Winding, false, 1.0)
+        else {
+            panic!("a lone point shouldn't render");
+        };
+        assert!(reason.contains("lone points"), "{reason}");
+    }
+
+    #[cfg(feature = "raster")]
+    #[test]
+    fn npy_coverage() {
+        use tiny_skia::FillRule;
+
+        let bezpath = BezPath::from_svg("M0,0 L100,0 L100,50 L0,50 Z").unwrap();
+        let glyph = Glyph::new(GlyphName::new("bar"), Path::new("bar.json"), bezpath).unwrap();
+        let pixmap = glyph
+            .render_no_aa(&Config::default(), FillRule::Winding)
+            .unwrap();
+        let npy = super::to_npy(&pixmap);
+
+        assert_eq!(b"\x93NUMPY\x01\x00", &npy[..8]);
+        let header_len = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+        assert_eq!(0, (10 + header_len) % 64);
+        let header = std::str::from_utf8(&npy[10..10 + header_len]).unwrap();
+        let shape = format!("'shape': ({}, {})", pixmap.height(), pixmap.width());
+        assert!(header.contains(&shape), "{header}");
+        assert!(header.ends_with('\n'));
+        let coverage = &npy[10 + header_len..];
+        assert_eq!(pixmap.pixels().len(), coverage.len());
+        assert!(coverage.iter().all(|c| *c == 0 || *c == 255));
+        assert!(coverage.contains(&255));
+    }
+
+    #[cfg(feature = "raster")]
+    #[test]
+    fn fill_color_per_fill_rule() {
+        use tiny_skia::FillRule;
+
+        let bezpath =
+            BezPath::from_svg("M0,0 L100,0 L100,50 L0,50 Z M50,0 L150,0 L150,50 L50,50 Z").unwrap();
+        let glyph = Glyph::new(GlyphName::new("bars"), Path::new("bars.json"), bezpath).unwrap();
+        let config = Config {
+            fill_color: (0, 0, 255),
+            nonzero_fill_color: Some((255, 0, 0)),
+            ..Default::default()
+        };
+        let color = |fill_rule| {
+            let pixmap = glyph.render_no_aa(&config, fill_rule).unwrap();
+            let pixel = pixmap.pixels().iter().find(|p| p.alpha() > 0).copied();
+            pixel.map(|p| (p.red(), p.green(), p.blue()))
+        };
+        assert_eq!(Some((0, 0, 255)), color(FillRule::EvenOdd));
+        assert_eq!(Some((255, 0, 0)), color(FillRule::Winding));
+        // Compared by coverage, so the verdict doesn't change
+        let overlap = |config: &Config| glyph.check(config).unwrap().overlap.map(|o| o.area);
+        assert_eq!(overlap(&Config::default()), overlap(&config));
+    }
+}
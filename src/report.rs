@@ -0,0 +1,426 @@
+//! Machine readable results.
+//!
+//! Reports are JSON, or an XML plist with the same layout for Apple tooling. The
+//! layout is versioned by [`SCHEMA_VERSION`]. Bump it for any change that
+//! could break a consumer, such as removing, renaming, or changing the meaning of
+//! a field. Adding a field doesn't need a bump.
+
+use std::path::Path;
+
+use kurbo::Rect;
+use serde::Serialize;
+
+use crate::{CheckResult, Error, Method, OverlapKind, Warning};
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Everything we learned in a run.
+#[derive(Debug, Serialize)]
+pub struct Report<'a> {
+    schema_version: u32,
+    summary: Summary,
+    results: Vec<GlyphReport<'a>>,
+    errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Summary {
+    /// Glyphs we tried to check, including those that errored
+    total: usize,
+    /// Glyphs that need the overlap flag
+    flagged: usize,
+    errored: usize,
+    warnings: usize,
+    /// Whether the run was capped with glyphs left unchecked, not included in total
+    truncated: bool,
+    /// Contours dropped for being smaller than the minimum area, across all glyphs
+    dropped_contours: usize,
+    by_regions: RegionTally,
+}
+
+/// Glyphs that need the overlap flag by how many separate places the fill rules
+/// disagree. One region is often intended, many often means messy contours.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RegionTally {
+    pub one: usize,
+    pub two: usize,
+    pub three_or_more: usize,
+}
+
+impl RegionTally {
+    pub fn new(results: &[CheckResult]) -> Self {
+        let mut tally = Self::default();
+        for overlap in results.iter().filter_map(|r| r.overlap.as_ref()) {
+            match overlap.regions {
+                ..=1 => tally.one += 1,
+                2 => tally.two += 1,
+                _ => tally.three_or_more += 1,
+            }
+        }
+        tally
+    }
+}
+
+impl std::fmt::Display for RegionTally {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "1 region: {} glyph(s)", self.one)?;
+        writeln!(f, "2 regions: {} glyph(s)", self.two)?;
+        write!(f, "3+ regions: {} glyph(s)", self.three_or_more)
+    }
+}
+
+/// Just the counts from a [`Report`], for dashboards and the like.
+#[derive(Debug, Serialize)]
+pub struct SummaryReport<'a> {
+    schema_version: u32,
+    summary: &'a Summary,
+}
+
+#[derive(Debug, Serialize)]
+struct GlyphReport<'a> {
+    name: &'a str,
+    source: &'a Path,
+    /// Present if the outline was interpolated for a designspace instance
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instance: Option<&'a str>,
+    /// Present if the glyph was loaded through a designspace, master or instance
+    #[serde(skip_serializing_if = "Option::is_none")]
+    designspace: Option<&'a Path>,
+    /// Present if the glyph was loaded through a designspace or is at a variable font's
+    /// named instance
+    #[serde(skip_serializing_if = "Option::is_none")]
+    origin: Option<Origin>,
+    method: Method,
+    /// See [`crate::Glyph::outline_hash`]
+    outline_hash: &'a str,
+    /// Font units, present if the source gives one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    advance_width: Option<f64>,
+    /// Unicode codepoints mapped to the glyph, as integers
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    codepoints: Vec<u32>,
+    /// Pixels per font unit the verdict was rendered at, if it was rendered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scale: Option<ScaleReport>,
+    needs_overlap_flag: bool,
+    /// Present if the glyph needs the overlap flag
+    #[serde(skip_serializing_if = "Option::is_none")]
+    overlap: Option<OverlapReport<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<WarningReport>,
+}
+
+/// Whether a result is for a master as drawn or an instance interpolated from them.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Origin {
+    Master,
+    Instance,
+}
+
+#[derive(Debug, Serialize)]
+struct WarningReport {
+    kind: &'static str,
+    message: String,
+}
+
+#[derive(Debug, Serialize)]
+struct OverlapReport<'a> {
+    kind: OverlapKind,
+    /// Where the fill rules disagree
+    bbox: BoundingBox,
+    /// Indices of the contours involved, components flattened in
+    contours: &'a [usize],
+    /// Roughly how much area the fill rules disagree on, in square font units
+    area: f64,
+    /// Roughly how much area the glyph fills, in square font units
+    inked_area: f64,
+    /// area as a percentage of inked_area
+    area_percent: f64,
+    /// How many separate places the fill rules disagree
+    regions: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct ScaleReport {
+    x: f64,
+    y: f64,
+}
+
+/// A rectangle in font units, y-up.
+#[derive(Debug, Serialize)]
+struct BoundingBox {
+    x_min: f64,
+    y_min: f64,
+    x_max: f64,
+    y_max: f64,
+}
+
+impl From<Rect> for BoundingBox {
+    fn from(rect: Rect) -> Self {
+        Self {
+            x_min: rect.min_x(),
+            y_min: rect.min_y(),
+            x_max: rect.max_x(),
+            y_max: rect.max_y(),
+        }
+    }
+}
+
+impl<'a> Report<'a> {
+    pub fn new(results: &'a [CheckResult], errors: &[Error]) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            summary: Summary {
+                total: results.len() + errors.len(),
+                flagged: results.iter().filter(|r| r.overlap.is_some()).count(),
+                errored: errors.len(),
+                warnings: results.iter().map(|r| r.warnings.len()).sum(),
+                truncated: false,
+                dropped_contours: results
+                    .iter()
+                    .flat_map(|r| r.warnings.iter())
+                    .map(|w| match w {
+                        Warning::SmallContourDropped { count, .. } => *count,
+                        _ => 0,
+                    })
+                    .sum(),
+                by_regions: RegionTally::new(results),
+            },
+            results: results
+                .iter()
+                .map(|r| GlyphReport {
+                    name: r.name.as_str(),
+                    source: &r.source,
+                    instance: r.instance.as_deref(),
+                    designspace: r.designspace.as_deref(),
+                    origin: match (&r.instance, &r.designspace) {
+                        (Some(_), _) => Some(Origin::Instance),
+                        (None, Some(_)) => Some(Origin::Master),
+                        (None, None) => None,
+                    },
+                    method: r.method,
+                    outline_hash: &r.outline_hash,
+                    advance_width: r.advance,
+                    codepoints: r.codepoints.iter().map(|c| *c as u32).collect(),
+                    scale: r.scale.map(|(x, y)| ScaleReport { x, y }),
+                    needs_overlap_flag: r.overlap.is_some(),
+                    overlap: r.overlap.as_ref().map(|o| OverlapReport {
+                        kind: o.kind,
+                        bbox: o.bbox.into(),
+                        contours: &o.contours,
+                        area: o.area,
+                        inked_area: o.inked_area,
+                        area_percent: o.area_percent(),
+                        regions: o.regions,
+                    }),
+                    warnings: r
+                        .warnings
+                        .iter()
+                        .map(|w| WarningReport {
+                            kind: w.kind(),
+                            message: w.to_string(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+            errors: errors.iter().map(|e| e.to_string()).collect(),
+        }
+    }
+
+    /// Drop results for glyphs that don't need the overlap flag. The summary still counts them.
+    pub fn only_problems(mut self) -> Self {
+        self.results.retain(|r| r.needs_overlap_flag);
+        self
+    }
+
+    /// Record whether some glyphs were never checked, e.g. because of a cap on run size.
+    pub fn truncated(mut self, truncated: bool) -> Self {
+        self.summary.truncated = truncated;
+        self
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self)
+            .unwrap_or_else(|e| panic!("Unable to serialize report: {e}"))
+    }
+
+    /// The report as an XML plist, laid out as [`Report::to_json`] is.
+    pub fn to_plist(&self) -> String {
+        to_plist(self)
+    }
+
+    /// The results as TAP, the Test Anything Protocol: a test per glyph, failing if the
+    /// glyph needs the overlap flag, then a failing test per error.
+    pub fn to_tap(&self) -> String {
+        let mut tap = vec![
+            "TAP version 13".to_string(),
+            format!("1..{}", self.results.len() + self.errors.len()),
+        ];
+        for (i, result) in self.results.iter().enumerate() {
+            let mut description = result.name.to_string();
+            if let Some(instance) = result.instance {
+                description.push_str(&format!(" at {instance}"));
+            }
+            let Some(overlap) = &result.overlap else {
+                tap.push(format!("ok {} - {}", i + 1, tap_escape(&description)));
+                continue;
+            };
+            tap.push(format!("not ok {} - {}", i + 1, tap_escape(&description)));
+            tap.extend([
+                "  ---".to_string(),
+                "  message: needs the overlap flag".to_string(),
+                format!("  source: {:?}", result.source),
+                format!("  kind: {}", overlap.kind),
+                format!("  regions: {}", overlap.regions),
+                format!("  area: {:.0}", overlap.area),
+                "  ...".to_string(),
+            ]);
+        }
+        for (i, error) in self.errors.iter().enumerate() {
+            tap.push(format!(
+                "not ok {} - {}",
+                self.results.len() + i + 1,
+                tap_escape(error)
+            ));
+        }
+        tap.join("\n")
+    }
+
+    pub fn summary(&self) -> SummaryReport<'_> {
+        SummaryReport {
+            schema_version: self.schema_version,
+            summary: &self.summary,
+        }
+    }
+}
+
+impl SummaryReport<'_> {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self)
+            .unwrap_or_else(|e| panic!("Unable to serialize summary: {e}"))
+    }
+
+    pub fn to_plist(&self) -> String {
+        to_plist(self)
+    }
+}
+
+impl std::fmt::Display for SummaryReport<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} glyphs, {} need the overlap flag, {} errors, {} warnings",
+            self.summary.total, self.summary.flagged, self.summary.errored, self.summary.warnings
+        )?;
+        if self.summary.truncated {
+            write!(f, ", truncated with the rest not checked")?;
+        }
+        Ok(())
+    }
+}
+
+/// `description` fit for a TAP test line: one line, with # escaped so it doesn't start
+/// a directive.
+fn tap_escape(description: &str) -> String {
+    description.replace('\n', " ").replace('#', "\\#")
+}
+
+/// Plists have no null, so every optional field must be skipped when absent.
+fn to_plist(value: &impl Serialize) -> String {
+    let mut plist = Vec::new();
+    plist::to_writer_xml(&mut plist, value)
+        .unwrap_or_else(|e| panic!("Unable to serialize plist: {e}"));
+    String::from_utf8(plist).expect("plist XML is UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{Config, Glyph, Selection, Sources};
+
+    use super::{RegionTally, Report};
+
+    #[test]
+    fn plist_mirrors_json() {
+        let file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/testdata/Overlaps.ufo");
+        let config = Config::default();
+        let results = Glyph::from_file(file)
+            .into_iter()
+            .map(|g| g.unwrap().check(&config).unwrap())
+            .collect::<Vec<_>>();
+        let report = Report::new(&results, &[]);
+
+        let json: plist::Value = serde_json::from_str(&report.to_json()).unwrap();
+        let plist = plist::Value::from_reader_xml(report.to_plist().as_bytes()).unwrap();
+        assert_eq!(json, plist);
+        let summary = plist::Value::from_reader_xml(report.summary().to_plist().as_bytes());
+        assert!(summary
+            .unwrap()
+            .as_dictionary()
+            .unwrap()
+            .contains_key("summary"));
+    }
+
+    #[test]
+    fn tally_by_regions() {
+        let file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/testdata/Overlaps.ufo");
+        let config = Config::default();
+        let results = Glyph::from_file(file)
+            .into_iter()
+            .map(|g| g.unwrap().check(&config).unwrap())
+            .collect::<Vec<_>>();
+        let tally = RegionTally::new(&results);
+        assert_eq!(
+            results.iter().filter(|r| r.overlap.is_some()).count(),
+            tally.one + tally.two + tally.three_or_more
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&Report::new(&results, &[]).to_json()).unwrap();
+        assert_eq!(tally.one, json["summary"]["by_regions"]["one"]);
+    }
+
+    #[test]
+    fn tap() {
+        let file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/testdata/Overlaps.ufo");
+        let config = Config::default();
+        let results = Glyph::from_file(file)
+            .into_iter()
+            .map(|g| g.unwrap().check(&config).unwrap())
+            .collect::<Vec<_>>();
+        let tap = Report::new(&results, &[]).to_tap();
+        let lines = tap.lines().collect::<Vec<_>>();
+        assert_eq!(["TAP version 13", "1..7", "ok 1 - .notdef"], lines[..3]);
+        assert!(lines.contains(&"not ok 3 - plus"), "{tap}");
+        assert!(lines.contains(&"ok 6 - square"), "{tap}");
+        // Only failures get details
+        assert_eq!(5, lines.iter().filter(|l| **l == "  ---").count());
+    }
+
+    #[test]
+    fn origins_labelled() {
+        let file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/testdata/Bars/Bars.designspace");
+        let config = Config::default();
+        let selection = Selection {
+            sources: Sources::Both,
+            ..Default::default()
+        };
+        let results = Glyph::stream_selected([file], selection)
+            .map(|g| g.unwrap().check(&config).unwrap())
+            .collect::<Vec<_>>();
+        let json: serde_json::Value =
+            serde_json::from_str(&Report::new(&results, &[]).to_json()).unwrap();
+        for result in json["results"].as_array().unwrap() {
+            let expected = match result.get("instance") {
+                Some(_) => "instance",
+                None => "master",
+            };
+            assert_eq!(expected, result["origin"], "{result}");
+        }
+    }
+}
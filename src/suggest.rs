@@ -0,0 +1,267 @@
+//! Suggesting an outline that doesn't need the overlap flag.
+//!
+//! We have no boolean path operations to hand, but we do have the nonzero render,
+//! which is exactly the union of the contours. Tracing its edge back into contours,
+//! and simplifying the staircase that leaves, gives an overlap-free outline that
+//! matches the original to within about a pixel. Curves come back flattened, so this
+//! is a starting point for a fix rather than a fix.
+
+use std::collections::BTreeMap;
+
+use kurbo::{BezPath, Point, Rect, Shape};
+use tiny_skia::FillRule;
+
+use crate::{glyph::contours, Config, Error, Glyph};
+
+/// How far, in pixels, simplified contours may stray from the traced edge.
+const TOLERANCE: f64 = 0.75;
+
+/// An overlap-free outline for a glyph, traced from its nonzero render.
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    /// The suggested outline, y-down like [`Glyph::bezpath`], all straight lines
+    pub bezpath: BezPath,
+    /// How many contours the glyph had
+    pub contours_before: usize,
+    /// How many contours the suggestion has
+    pub contours_after: usize,
+    /// Whether the suggestion itself doesn't need the overlap flag
+    pub clean: bool,
+}
+
+impl Suggestion {
+    /// The suggestion as a standalone SVG document, in font units with y-down.
+    pub fn to_svg(&self) -> String {
+        let bbox = if self.bezpath.elements().is_empty() {
+            Rect::ZERO
+        } else {
+            self.bezpath.bounding_box()
+        };
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n\
+             <path d=\"{}\"/>\n</svg>\n",
+            bbox.x0,
+            bbox.y0,
+            bbox.width(),
+            bbox.height(),
+            self.bezpath.to_svg()
+        )
+    }
+}
+
+/// Trace the nonzero render of `glyph`, at `config`, into an outline with no overlaps.
+///
+/// The suggestion is only as precise as the render, raise [`Config::scale_x`] and
+/// [`Config::scale_y`] for a closer fit. It is checked at `config` to say whether it
+/// is [`Suggestion::clean`].
+pub fn suggest_fix(glyph: &Glyph, config: &Config) -> Result<Suggestion, Error> {
+    let contours_before = contours(&glyph.bezpath).len();
+    if glyph.bezpath.elements().is_empty() {
+        return Ok(Suggestion {
+            bezpath: BezPath::new(),
+            contours_before,
+            contours_after: 0,
+            clean: true,
+        });
+    }
+    let (transform, _) = glyph.render_transform(config);
+    let nonzero = glyph.render_no_aa(config, FillRule::Winding)?;
+    let width = nonzero.width() as i64;
+    let height = nonzero.height() as i64;
+    let pixels = nonzero.pixels();
+    let filled = |x: i64, y: i64| {
+        (0..width).contains(&x) && (0..height).contains(&y) && {
+            pixels[(y * width + x) as usize].alpha() > 0
+        }
+    };
+
+    let to_glyph = transform.inverse();
+    let mut bezpath = BezPath::new();
+    let mut contours_after = 0;
+    for traced in trace(width, height, filled) {
+        let simplified = simplify(&traced);
+        if simplified.len() < 3 {
+            continue;
+        }
+        contours_after += 1;
+        bezpath.move_to(to_glyph * simplified[0]);
+        for p in simplified[1..].iter() {
+            bezpath.line_to(to_glyph * *p);
+        }
+        bezpath.close_path();
+    }
+
+    let clean = bezpath.elements().is_empty()
+        || glyph
+            .with_bezpath(bezpath.clone())
+            .check(config)?
+            .overlap
+            .is_none();
+    Ok(Suggestion {
+        bezpath,
+        contours_before,
+        contours_after,
+        clean,
+    })
+}
+
+/// The edges between filled and empty pixels, joined into closed polygons of pixel
+/// corners. Filled pixels are always on the same side, so outers and holes run in
+/// opposite directions and nothing overlaps.
+fn trace(width: i64, height: i64, filled: impl Fn(i64, i64) -> bool) -> Vec<Vec<Point>> {
+    // Clockwise around each filled pixel, y-down, where the neighbor across is empty
+    let mut edges: BTreeMap<(i64, i64), Vec<(i64, i64)>> = BTreeMap::new();
+    for y in 0..height {
+        for x in 0..width {
+            if !filled(x, y) {
+                continue;
+            }
+            for (empty, from, to) in [
+                (!filled(x, y - 1), (x, y), (x + 1, y)),
+                (!filled(x + 1, y), (x + 1, y), (x + 1, y + 1)),
+                (!filled(x, y + 1), (x + 1, y + 1), (x, y + 1)),
+                (!filled(x - 1, y), (x, y + 1), (x, y)),
+            ] {
+                if empty {
+                    edges.entry(from).or_default().push(to);
+                }
+            }
+        }
+    }
+
+    // The least remaining point is the top left of whatever polygon it's on, always a
+    // corner, so simplifying keeps it
+    let mut polygons = Vec::new();
+    while let Some(&start) = edges.keys().next() {
+        let mut polygon = vec![start];
+        let mut at = start;
+        while let Some(next) = edges.get_mut(&at).and_then(|e| e.pop()) {
+            if edges.get(&at).is_some_and(|e| e.is_empty()) {
+                edges.remove(&at);
+            }
+            if next == start {
+                break;
+            }
+            polygon.push(next);
+            at = next;
+        }
+        polygons.push(
+            polygon
+                .into_iter()
+                .map(|(x, y)| Point::new(x as f64, y as f64))
+                .collect(),
+        );
+    }
+    polygons
+}
+
+/// `polygon`, closed, with points that don't move it more than [`TOLERANCE`] dropped.
+fn simplify(polygon: &[Point]) -> Vec<Point> {
+    if polygon.len() < 4 {
+        return polygon.to_vec();
+    }
+    // Split at the point furthest from the first so each half is an open run
+    let far = (1..polygon.len())
+        .max_by(|a, b| {
+            let (a, b) = (polygon[*a], polygon[*b]);
+            (a - polygon[0])
+                .hypot2()
+                .total_cmp(&(b - polygon[0]).hypot2())
+        })
+        .unwrap_or(1);
+    let mut closed = polygon.to_vec();
+    closed.push(polygon[0]);
+    let mut simplified = douglas_peucker(&closed[..=far]);
+    simplified.pop();
+    simplified.extend(douglas_peucker(&closed[far..]));
+    simplified.pop();
+    simplified
+}
+
+/// The Ramer-Douglas-Peucker simplification of the open run `points`, ends included.
+fn douglas_peucker(points: &[Point]) -> Vec<Point> {
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let furthest = points[1..points.len() - 1]
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (i + 1, distance_to_line(*p, first, last)))
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    match furthest {
+        Some((i, distance)) if distance > TOLERANCE => {
+            let mut simplified = douglas_peucker(&points[..=i]);
+            simplified.pop();
+            simplified.extend(douglas_peucker(&points[i..]));
+            simplified
+        }
+        _ => vec![first, last],
+    }
+}
+
+fn distance_to_line(p: Point, a: Point, b: Point) -> f64 {
+    let line = b - a;
+    let length = line.hypot();
+    if length == 0.0 {
+        return (p - a).hypot();
+    }
+    line.cross(p - a).abs() / length
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use fontdrasil::types::GlyphName;
+    use kurbo::BezPath;
+
+    use crate::{Config, Glyph};
+
+    use super::suggest_fix;
+
+    fn glyph(name: &str) -> Glyph {
+        let file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/testdata/Overlaps.ufo/glyphs")
+            .join(format!("{name}.glif"));
+        Glyph::from_file(file).pop().unwrap().unwrap()
+    }
+
+    #[test]
+    fn overlapping_fills_merged() {
+        let config = Config::default();
+        let plus = glyph("plus");
+        assert!(plus.check(&config).unwrap().overlap.is_some());
+
+        let suggestion = suggest_fix(&plus, &config).unwrap();
+        assert!(suggestion.clean, "{}", suggestion.bezpath.to_svg());
+        assert_eq!(
+            (2, 1),
+            (suggestion.contours_before, suggestion.contours_after)
+        );
+        // Every corner of the plus, nothing else
+        assert_eq!(
+            "M0,-350 L250,-350 L250,-600 L350,-600 L350,-350 L600,-350 L600,-250 L350,-250 \
+             L350,0 L250,0 L250,-250 L0,-250 Z",
+            suggestion.bezpath.to_svg()
+        );
+    }
+
+    #[test]
+    fn holes_kept() {
+        // Overlapping squares, one with a correctly wound hole
+        let bezpath = BezPath::from_svg(
+            "M0,0 L100,0 L100,100 L0,100 Z M25,25 L25,75 L75,75 L75,25 Z \
+             M50,50 L150,50 L150,150 L50,150 Z",
+        )
+        .unwrap();
+        let glyph = Glyph::new(GlyphName::new("holey"), Path::new("holey.json"), bezpath).unwrap();
+        let config = Config::default();
+        assert!(glyph.check(&config).unwrap().overlap.is_some());
+
+        let suggestion = suggest_fix(&glyph, &config).unwrap();
+        assert!(suggestion.clean, "{}", suggestion.bezpath.to_svg());
+        assert_eq!(
+            (3, 2),
+            (suggestion.contours_before, suggestion.contours_after)
+        );
+        assert!(suggestion.to_svg().starts_with("<svg"));
+    }
+}
@@ -0,0 +1,66 @@
+//! Comparing two versions of an outline, e.g. before and after a design change.
+
+use std::path::Path;
+
+use fontdrasil::types::GlyphName;
+use kurbo::{Affine, BezPath};
+
+use crate::{Config, Error, Glyph};
+
+/// How the need for the overlap flag changed between two versions of an outline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    /// Both need the flag, or neither does
+    Unchanged,
+    /// Only the new version needs the flag
+    Introduced,
+    /// Only the old version needs the flag
+    Removed,
+}
+
+/// Whether `new` differs from `old` in needing the overlap flag. Both in font units, y-up.
+pub fn overlap_status_changed(
+    old: &BezPath,
+    new: &BezPath,
+    config: &Config,
+) -> Result<Transition, Error> {
+    let needs_flag = |path: &BezPath, name: &str| -> Result<bool, Error> {
+        let mut bezpath = path.clone();
+        // Font units and svg units don't agree on y-up
+        bezpath.apply_affine(Affine::FLIP_Y);
+        let glyph = Glyph::new(GlyphName::new(name), Path::new(name), bezpath)?;
+        Ok(glyph.check(config)?.overlap.is_some())
+    };
+    Ok(match (needs_flag(old, "old")?, needs_flag(new, "new")?) {
+        (false, true) => Transition::Introduced,
+        (true, false) => Transition::Removed,
+        _ => Transition::Unchanged,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use kurbo::BezPath;
+
+    use super::{overlap_status_changed, Transition};
+    use crate::Config;
+
+    fn path(svg: &str) -> BezPath {
+        BezPath::from_svg(svg).unwrap()
+    }
+
+    #[test]
+    fn transitions() {
+        let plus = path("M250,0 L350,0 L350,600 L250,600 Z M0,250 L600,250 L600,350 L0,350 Z");
+        let merged = path(
+            "M250,0 L350,0 L350,250 L600,250 L600,350 L350,350 L350,600 L250,600 \
+             L250,350 L0,350 L0,250 L250,250 Z",
+        );
+        let config = Config::default();
+        let transition = |old, new| overlap_status_changed(old, new, &config).unwrap();
+        assert_eq!(Transition::Removed, transition(&plus, &merged));
+        assert_eq!(Transition::Introduced, transition(&merged, &plus));
+        assert_eq!(Transition::Unchanged, transition(&plus, &plus));
+        assert_eq!(Transition::Unchanged, transition(&merged, &merged));
+    }
+}
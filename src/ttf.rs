@@ -0,0 +1,832 @@
+//! Loading glyph outlines from binary TrueType fonts, variable ones at each named instance.
+//!
+//! Outlines are the points in glyf as drawn. Hinting instructions are never run, so
+//! verdicts are about the same geometry as the sources, not how it snaps to the pixel
+//! grid at some size.
+
+use std::{collections::HashSet, ops::RangeInclusive, path::Path};
+
+use fontdrasil::types::GlyphName;
+use fontir::ir::GlyphPathBuilder;
+use kurbo::{Affine, BezPath, Point, Vec2};
+use read_fonts::{
+    tables::{
+        avar::Avar,
+        cmap::CmapSubtable,
+        colr::{Colr, Paint},
+        fvar::VariationAxisRecord,
+        glyf::{self, Anchor, CompositeGlyphFlags},
+        gvar::Gvar,
+        loca::Loca,
+    },
+    types::{F2Dot14, Fixed, GlyphId},
+    FontRef, TableProvider,
+};
+
+use crate::{designspace::location_label, Error, Glyph, Sources};
+
+/// Loads every glyph in the glyf table, composites flattened, one at a time as the
+/// iterator is advanced.
+///
+/// In a color font only the monochrome outlines of base glyphs are checked, glyphs
+/// drawn solely as COLR layers are skipped.
+///
+/// A variable font is checked at its default location, then with gvar deltas applied
+/// at each fvar named instance that isn't the default, glyphs there carrying the
+/// instance name. `sources` can leave out either, the default location being the
+/// master.
+///
+/// With `gids` only glyphs whose ids are in range are loaded. With a user-space
+/// location `at`, see [`crate::Selection::location`], a variable font is checked there
+/// instead of at its named instances, and a static font is an error.
+pub(crate) fn load(
+    file: &Path,
+    gids: Option<RangeInclusive<u16>>,
+    sources: Sources,
+    at: Option<&[(String, f64)]>,
+) -> impl Iterator<Item = Result<Glyph, Error>> {
+    let data = std::fs::read(file).unwrap_or_else(|e| panic!("Unable to read {file:?}: {e}"));
+    load_data(file, data, gids, sources, at).unwrap_or_else(|reason| panic!("{file:?} {reason}"))
+}
+
+/// Like [`load`] for a font already in memory, `file` only naming where it came from.
+///
+/// Says why if `data` isn't a font we can load glyphs from.
+pub(crate) fn load_data(
+    file: &Path,
+    data: Vec<u8>,
+    gids: Option<RangeInclusive<u16>>,
+    sources: Sources,
+    at: Option<&[(String, f64)]>,
+) -> Result<impl Iterator<Item = Result<Glyph, Error>>, String> {
+    let font = FontRef::new(&data).map_err(|e| format!("isn't a font: {e}"))?;
+    let (Ok(_), Ok(_)) = (font.loca(None), font.glyf()) else {
+        return Err("has no glyf table, only TrueType outlines are supported".to_string());
+    };
+    let num_glyphs = font
+        .maxp()
+        .map_err(|e| format!("has no readable maxp table: {e}"))?
+        .num_glyphs();
+    let post = font.post().ok();
+    let italic_angle = post.as_ref().map(|p| p.italic_angle().to_f64());
+    let units_per_em = font.head().ok().map(|h| h.units_per_em() as f64);
+    let names = (0..num_glyphs)
+        .map(|gid| {
+            post.as_ref()
+                .and_then(|p| p.glyph_name(GlyphId::new(gid)))
+                .map(GlyphName::new)
+                .unwrap_or_else(|| GlyphName::new(format!("glyph{gid:05}")))
+        })
+        .collect::<Vec<_>>();
+    let codepoints = codepoints(&font, num_glyphs);
+    let color_layers = font.colr().map(|c| color_layers(&c)).unwrap_or_default();
+    // Checking the default outlines instead would pass a font nothing was checked in
+    if at.is_some() && (font.fvar().is_err() || font.gvar().is_err()) {
+        return Err("has no variation axes, --location can't apply".to_string());
+    }
+    // The default location, then the named instances or the location asked for
+    let mut locations = Vec::new();
+    if sources.masters() {
+        locations.push((None, Vec::new()));
+    }
+    if sources.instances() && font.gvar().is_ok() {
+        match at {
+            Some(at) => locations.push((Some(location_label(at)), user_location(&font, at)?)),
+            None => locations.extend(
+                named_instances(&font)
+                    .into_iter()
+                    .map(|(name, coords)| (Some(name), coords)),
+            ),
+        }
+    }
+
+    let file = file.to_path_buf();
+    Ok((0..locations.len())
+        .flat_map(move |l| (0..num_glyphs).map(move |gid| (l, gid)))
+        .filter(move |(_, gid)| !color_layers.contains(gid))
+        .filter(move |(_, gid)| gids.as_ref().is_none_or(|r| r.contains(gid)))
+        .map(move |(l, gid)| {
+            // Only the bytes are kept between glyphs, the tables are cheap to find again
+            let font = FontRef::new(&data).expect("parsed above");
+            let (instance, coords) = &locations[l];
+            let outlines = Outlines {
+                file: &file,
+                loca: font.loca(None).expect("read above"),
+                glyf: font.glyf().expect("read above"),
+                gvar: font.gvar().ok(),
+                coords,
+                names: &names,
+            };
+            let mut bezpath = outlines.draw(gid, &mut Vec::new())?;
+            // Same y-up font units as a glif
+            bezpath.apply_affine(Affine::FLIP_Y);
+            let mut glyph = Glyph::new(names[gid as usize].clone(), &file, bezpath)?;
+            glyph.instance = instance.clone();
+            glyph.codepoints = codepoints[gid as usize].clone();
+            glyph.overlap_flag = outlines.overlap_flag(gid);
+            glyph.components = outlines.components(gid);
+            glyph.italic_angle = italic_angle;
+            glyph.set_units_per_em(units_per_em);
+            // Advances aren't varied, HVAR and phantom point deltas are ignored
+            if instance.is_none() {
+                glyph.advance = advance(&font, gid);
+            }
+            Ok(glyph)
+        }))
+}
+
+/// The codepoints cmap maps to each glyph, ascending, from its format 4 and 12 subtables.
+fn codepoints(font: &FontRef, num_glyphs: u16) -> Vec<Vec<char>> {
+    let mut codepoints = vec![Vec::new(); num_glyphs as usize];
+    let Ok(cmap) = font.cmap() else {
+        return codepoints;
+    };
+    for record in cmap.encoding_records() {
+        let mappings: Box<dyn Iterator<Item = (u32, GlyphId)>> =
+            match record.subtable(cmap.offset_data()) {
+                Ok(CmapSubtable::Format4(format4)) => Box::new(format4.iter()),
+                Ok(CmapSubtable::Format12(format12)) => Box::new(format12.iter()),
+                _ => continue,
+            };
+        for (codepoint, gid) in mappings {
+            let (Some(c), Some(mapped)) = (
+                char::from_u32(codepoint),
+                codepoints.get_mut(gid.to_u16() as usize),
+            ) else {
+                continue;
+            };
+            if !mapped.contains(&c) {
+                mapped.push(c);
+            }
+        }
+    }
+    for mapped in codepoints.iter_mut() {
+        mapped.sort();
+    }
+    codepoints
+}
+
+/// The advance width of `gid` from hmtx, which repeats the last advance for glyphs
+/// past its long metrics.
+fn advance(font: &FontRef, gid: u16) -> Option<f64> {
+    let hmtx = font.hmtx().ok()?;
+    let metrics = hmtx.h_metrics();
+    let metric = metrics.get(gid as usize).or(metrics.last())?;
+    Some(metric.advance() as f64)
+}
+
+/// The fvar named instances away from the default location, by subfamily name, as
+/// normalized coordinates with any avar mapping applied.
+fn named_instances(font: &FontRef) -> Vec<(String, Vec<F2Dot14>)> {
+    let Ok(fvar) = font.fvar() else {
+        return Vec::new();
+    };
+    let (Ok(axes), Ok(instances)) = (fvar.axes(), fvar.instances()) else {
+        return Vec::new();
+    };
+    let avar = font.avar().ok();
+    let name = font.name().ok();
+    let instance_name = |id| {
+        let name = name.as_ref()?;
+        name.name_record()
+            .iter()
+            .filter(|r| r.name_id() == id)
+            .find_map(|r| r.string(name.string_data()).ok())
+            .map(|s| s.chars().collect::<String>())
+    };
+
+    instances
+        .iter()
+        .enumerate()
+        .filter_map(|(i, instance)| {
+            let instance = instance.ok()?;
+            let coords = axes
+                .iter()
+                .zip(instance.coordinates)
+                .enumerate()
+                .map(|(a, (axis, coord))| normalize(axis, avar.as_ref(), a, coord.get()))
+                .collect::<Vec<_>>();
+            // The default outlines are checked already
+            if coords.iter().all(|c| *c == F2Dot14::ZERO) {
+                return None;
+            }
+            let name = instance_name(instance.subfamily_name_id)
+                .unwrap_or_else(|| format!("instance {i}"));
+            Some((name, coords))
+        })
+        .collect()
+}
+
+/// The normalized coordinates of user-space coordinates `at`, by axis tag, with any
+/// avar mapping applied. Axes that aren't mentioned are at their default.
+fn user_location(font: &FontRef, at: &[(String, f64)]) -> Result<Vec<F2Dot14>, String> {
+    let axes = font
+        .fvar()
+        .and_then(|fvar| fvar.axes())
+        .map_err(|e| format!("has no readable fvar axes: {e}"))?;
+    for (tag, value) in at {
+        let Some(axis) = axes.iter().find(|a| a.axis_tag().to_string() == *tag) else {
+            return Err(format!("has no {tag} axis"));
+        };
+        let (min, max) = (axis.min_value().to_f64(), axis.max_value().to_f64());
+        if !(min..=max).contains(value) {
+            return Err(format!(
+                "{tag} axis runs from {min} to {max}, {tag}={value} is outside it"
+            ));
+        }
+    }
+    let avar = font.avar().ok();
+    Ok(axes
+        .iter()
+        .enumerate()
+        .map(|(a, axis)| {
+            let user = at
+                .iter()
+                .find(|(tag, _)| axis.axis_tag().to_string() == *tag)
+                .map_or(axis.default_value(), |(_, value)| Fixed::from_f64(*value));
+            normalize(axis, avar.as_ref(), a, user)
+        })
+        .collect())
+}
+
+/// The user-space `coord` on the `a`th axis, normalized and mapped through avar.
+fn normalize(axis: &VariationAxisRecord, avar: Option<&Avar>, a: usize, coord: Fixed) -> F2Dot14 {
+    let mut coord = axis.normalize(coord);
+    if let Some(map) =
+        avar.and_then(|avar| avar.axis_segment_maps().iter().nth(a).and_then(|m| m.ok()))
+    {
+        coord = map.apply(coord);
+    }
+    coord.to_f2dot14()
+}
+
+/// Glyphs COLR draws as layers of a color glyph that aren't color glyphs themselves.
+///
+/// COLRv1 paint graphs are followed through transforms and composites but not into
+/// other color glyphs, which are base glyphs in their own right.
+fn color_layers(colr: &Colr) -> HashSet<u16> {
+    let mut layers = HashSet::new();
+    let mut bases = HashSet::new();
+    if let Some(Ok(records)) = colr.base_glyph_records() {
+        bases.extend(records.iter().map(|r| r.glyph_id().to_u16()));
+    }
+    if let Some(Ok(records)) = colr.layer_records() {
+        layers.extend(records.iter().map(|r| r.glyph_id().to_u16()));
+    }
+    if let Some(Ok(list)) = colr.base_glyph_list() {
+        for record in list.base_glyph_paint_records() {
+            bases.insert(record.glyph_id().to_u16());
+            if let Ok(paint) = record.paint(list.offset_data()) {
+                paint_glyphs(&paint, &mut layers, 0);
+            }
+        }
+    }
+    if let Some(Ok(list)) = colr.layer_list() {
+        for paint in list.paints().iter().flatten() {
+            paint_glyphs(&paint, &mut layers, 0);
+        }
+    }
+    layers.retain(|gid| !bases.contains(gid));
+    layers
+}
+
+/// Adds the glyphs `paint` draws to `glyphs`. Offsets can loop in a corrupt font,
+/// so we give up rather than recurse without limit.
+fn paint_glyphs(paint: &Paint, glyphs: &mut HashSet<u16>, depth: usize) {
+    const MAX_DEPTH: usize = 64;
+    if depth > MAX_DEPTH {
+        return;
+    }
+    let child = match paint {
+        Paint::Glyph(p) => {
+            glyphs.insert(p.glyph_id().to_u16());
+            return;
+        }
+        Paint::Composite(p) => {
+            if let Ok(backdrop) = p.backdrop_paint() {
+                paint_glyphs(&backdrop, glyphs, depth + 1);
+            }
+            p.source_paint()
+        }
+        Paint::Transform(p) => p.paint(),
+        Paint::VarTransform(p) => p.paint(),
+        Paint::Translate(p) => p.paint(),
+        Paint::VarTranslate(p) => p.paint(),
+        Paint::Scale(p) => p.paint(),
+        Paint::VarScale(p) => p.paint(),
+        Paint::ScaleAroundCenter(p) => p.paint(),
+        Paint::VarScaleAroundCenter(p) => p.paint(),
+        Paint::ScaleUniform(p) => p.paint(),
+        Paint::VarScaleUniform(p) => p.paint(),
+        Paint::ScaleUniformAroundCenter(p) => p.paint(),
+        Paint::VarScaleUniformAroundCenter(p) => p.paint(),
+        Paint::Rotate(p) => p.paint(),
+        Paint::VarRotate(p) => p.paint(),
+        Paint::RotateAroundCenter(p) => p.paint(),
+        Paint::VarRotateAroundCenter(p) => p.paint(),
+        Paint::Skew(p) => p.paint(),
+        Paint::VarSkew(p) => p.paint(),
+        Paint::SkewAroundCenter(p) => p.paint(),
+        Paint::VarSkewAroundCenter(p) => p.paint(),
+        // Layers are all in the layer list, color glyphs are bases, the rest are fills
+        _ => return,
+    };
+    if let Ok(child) = child {
+        paint_glyphs(&child, glyphs, depth + 1);
+    }
+}
+
+struct Outlines<'a> {
+    file: &'a Path,
+    loca: Loca<'a>,
+    glyf: glyf::Glyf<'a>,
+    gvar: Option<Gvar<'a>>,
+    /// Where to draw, normalized. Empty for the default location.
+    coords: &'a [F2Dot14],
+    names: &'a [GlyphName],
+}
+
+impl Outlines<'_> {
+    /// Whether the glyph sets OVERLAP_SIMPLE, or any of its components OVERLAP_COMPOUND.
+    fn overlap_flag(&self, gid: u16) -> bool {
+        match self.loca.get_glyf(GlyphId::new(gid), &self.glyf) {
+            Ok(Some(glyf::Glyph::Simple(simple))) => simple.has_overlapping_contours(),
+            Ok(Some(glyf::Glyph::Composite(composite))) => composite
+                .components()
+                .any(|c| c.flags.contains(CompositeGlyphFlags::OVERLAP_COMPOUND)),
+            _ => false,
+        }
+    }
+
+    /// The names of the glyphs a composite uses, empty for a simple glyph.
+    fn components(&self, gid: u16) -> Vec<GlyphName> {
+        let Ok(Some(glyf::Glyph::Composite(composite))) =
+            self.loca.get_glyf(GlyphId::new(gid), &self.glyf)
+        else {
+            return Vec::new();
+        };
+        composite
+            .components()
+            .map(|c| {
+                let base = c.glyph.to_u16();
+                self.names
+                    .get(base as usize)
+                    .cloned()
+                    .unwrap_or_else(|| GlyphName::new(format!("glyph{base:05}")))
+            })
+            .collect()
+    }
+
+    /// The outline of `gid` at [`Self::coords`] with components, recursively, drawn in
+    /// place. Font units, y-up.
+    ///
+    /// `visiting` is the chain of glyphs whose components led us here, to catch cycles.
+    fn draw(&self, gid: u16, visiting: &mut Vec<u16>) -> Result<BezPath, Error> {
+        let name = &self.names[gid as usize];
+        let bad_outline = |reason: String| Error::Outline {
+            file: self.file.to_path_buf(),
+            glyph: name.clone(),
+            reason,
+        };
+        let glyph = match self.loca.get_glyf(GlyphId::new(gid), &self.glyf) {
+            Ok(Some(glyph)) => glyph,
+            // No outline, e.g. a space
+            Ok(None) => return Ok(BezPath::new()),
+            Err(e) => return Err(bad_outline(e.to_string())),
+        };
+        let composite = match glyph {
+            glyf::Glyph::Simple(simple) => {
+                let mut points = simple
+                    .points()
+                    .map(|p| (Point::new(p.x as f64, p.y as f64), p.on_curve))
+                    .collect::<Vec<_>>();
+                let ends = simple
+                    .end_pts_of_contours()
+                    .iter()
+                    .map(|e| e.get() as usize)
+                    .collect::<Vec<_>>();
+                let original = points.iter().map(|(p, _)| *p).collect::<Vec<_>>();
+                for ((p, _), delta) in
+                    points
+                        .iter_mut()
+                        .zip(self.deltas(gid, &original, Some(&ends)))
+                {
+                    *p += delta;
+                }
+                return simple_path(name, &points, &ends).map_err(bad_outline);
+            }
+            glyf::Glyph::Composite(composite) => composite,
+        };
+
+        if visiting.contains(&gid) {
+            return Err(Error::ComponentCycle {
+                file: self.file.to_path_buf(),
+                glyph: name.clone(),
+            });
+        }
+        visiting.push(gid);
+        let mut bezpath = BezPath::new();
+        // Deltas move components by their offsets
+        let offsets = composite
+            .components()
+            .map(|c| match c.anchor {
+                Anchor::Offset { x, y } => Point::new(x as f64, y as f64),
+                Anchor::Point { .. } => Point::ORIGIN,
+            })
+            .collect::<Vec<_>>();
+        let deltas = self.deltas(gid, &offsets, None);
+        for (component, delta) in composite.components().zip(deltas) {
+            let base = component.glyph.to_u16();
+            let Some(base_name) = self.names.get(base as usize) else {
+                return Err(Error::MissingComponent {
+                    file: self.file.to_path_buf(),
+                    glyph: name.clone(),
+                    base: GlyphName::new(format!("glyph{base:05}")),
+                });
+            };
+            let Anchor::Offset { x, y } = component.anchor else {
+                return Err(bad_outline(format!(
+                    "{base_name} is positioned by matching points, which isn't supported"
+                )));
+            };
+            let offset = Point::new(x as f64, y as f64) + delta;
+            let t = component.transform;
+            let mut component_path = self.draw(base, visiting)?;
+            component_path.apply_affine(Affine::new([
+                t.xx.to_f32() as f64,
+                t.yx.to_f32() as f64,
+                t.xy.to_f32() as f64,
+                t.yy.to_f32() as f64,
+                offset.x,
+                offset.y,
+            ]));
+            bezpath.extend(component_path);
+        }
+        visiting.pop();
+        Ok(bezpath)
+    }
+
+    /// How far gvar moves each of the `points` of `gid` at [`Self::coords`], phantom
+    /// points left out.
+    ///
+    /// For a simple glyph pass its `contour_ends` so the points a tuple leaves out are
+    /// interpolated from those it moves. A composite's points are its component offsets,
+    /// and those a tuple leaves out don't move.
+    fn deltas(&self, gid: u16, points: &[Point], contour_ends: Option<&[usize]>) -> Vec<Vec2> {
+        let mut deltas = vec![Vec2::ZERO; points.len()];
+        let Some(gvar) = self.gvar.as_ref().filter(|_| !self.coords.is_empty()) else {
+            return deltas;
+        };
+        // Glyphs that don't vary have no data at all
+        let Ok(variations) = gvar.glyph_variation_data(GlyphId::new(gid)) else {
+            return deltas;
+        };
+        for tuple in variations.tuples() {
+            let Some(scalar) = tuple.compute_scalar(self.coords) else {
+                continue;
+            };
+            let mut explicit = vec![None; points.len()];
+            for delta in tuple.deltas() {
+                if let Some(d) = explicit.get_mut(delta.position as usize) {
+                    *d = Some(Vec2::new(delta.x_delta as f64, delta.y_delta as f64));
+                }
+            }
+            let tuple_deltas = match contour_ends {
+                Some(ends) if !tuple.has_deltas_for_all_points() => {
+                    interpolate_untouched(points, ends, &explicit)
+                }
+                _ => explicit.iter().map(|d| d.unwrap_or_default()).collect(),
+            };
+            let scalar = Fixed::to_f64(scalar);
+            for (delta, tuple_delta) in deltas.iter_mut().zip(tuple_deltas) {
+                *delta += tuple_delta * scalar;
+            }
+        }
+        deltas
+    }
+}
+
+/// Deltas for every point of a simple glyph from those a gvar tuple gives explicitly,
+/// inferring the rest as TrueType does (IUP): a point left out moves like its
+/// neighbours on the contour if it lies between them, by whichever is nearer if not.
+/// Contours with no explicit deltas don't move.
+fn interpolate_untouched(
+    points: &[Point],
+    contour_ends: &[usize],
+    explicit: &[Option<Vec2>],
+) -> Vec<Vec2> {
+    let mut deltas = vec![Vec2::ZERO; points.len()];
+    let mut start = 0;
+    for &end in contour_ends {
+        if start >= points.len() {
+            break;
+        }
+        let contour = start..=end.min(points.len() - 1);
+        let touched = contour
+            .clone()
+            .filter(|i| explicit[*i].is_some())
+            .collect::<Vec<_>>();
+        start = end + 1;
+        if touched.is_empty() {
+            continue;
+        }
+        for &i in touched.iter() {
+            deltas[i] = explicit[i].unwrap();
+        }
+        let len = contour.clone().count();
+        let first = *contour.start();
+        // Each run of untouched points between consecutive touched ones, wrapping around
+        for (n, &prev) in touched.iter().enumerate() {
+            let next = touched[(n + 1) % touched.len()];
+            let mut i = first + (prev - first + 1) % len;
+            while i != next {
+                let along = |axis: fn(Point) -> f64, delta: fn(Vec2) -> f64| {
+                    let (mut a, mut b) = (axis(points[prev]), axis(points[next]));
+                    let (mut da, mut db) = (delta(deltas[prev]), delta(deltas[next]));
+                    let x = axis(points[i]);
+                    if a == b {
+                        return if da == db { da } else { 0.0 };
+                    }
+                    if a > b {
+                        std::mem::swap(&mut a, &mut b);
+                        std::mem::swap(&mut da, &mut db);
+                    }
+                    if x <= a {
+                        da
+                    } else if x >= b {
+                        db
+                    } else {
+                        da + (x - a) * (db - da) / (b - a)
+                    }
+                };
+                deltas[i] = Vec2::new(along(|p| p.x, |d| d.x), along(|p| p.y, |d| d.y));
+                i = first + (i - first + 1) % len;
+            }
+        }
+    }
+    deltas
+}
+
+/// The outline of a simple glyph from its points, on-curve or not, and contour ends.
+fn simple_path(
+    name: &GlyphName,
+    points: &[(Point, bool)],
+    contour_ends: &[usize],
+) -> Result<BezPath, String> {
+    let mut path_builder = GlyphPathBuilder::new(name.clone(), points.len());
+    let mut start = 0;
+    for &end in contour_ends {
+        let contour = points
+            .get(start..=end)
+            .ok_or_else(|| format!("contour ends at point {end} of {}", points.len()))?;
+        let contour = with_implied_oncurves(contour);
+        for (i, (p, on_curve)) in contour.iter().enumerate() {
+            // Contours are closed, the first point follows on from the last
+            let prev_on_curve = contour[(i + contour.len() - 1) % contour.len()].1;
+            match (on_curve, prev_on_curve) {
+                (false, _) => path_builder.offcurve(*p),
+                (true, true) => path_builder.line_to(*p),
+                (true, false) => path_builder.qcurve_to(*p),
+            }
+            .map_err(|e| e.to_string())?;
+        }
+        path_builder.end_path().map_err(|e| e.to_string())?;
+        start = end + 1;
+    }
+    path_builder.build().map_err(|e| e.to_string())
+}
+
+/// The points of a closed TrueType contour, with the on-curve point TrueType lets
+/// you leave out midway between consecutive off-curves put back, so every quadratic
+/// segment has exactly one off-curve.
+fn with_implied_oncurves(contour: &[(Point, bool)]) -> Vec<(Point, bool)> {
+    let mut points = Vec::with_capacity(contour.len() * 2);
+    for (i, &(p, on_curve)) in contour.iter().enumerate() {
+        points.push((p, on_curve));
+        let (next, next_on_curve) = contour[(i + 1) % contour.len()];
+        if !on_curve && !next_on_curve {
+            points.push((p.midpoint(next), true));
+        }
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use kurbo::{Point, Vec2};
+    use read_fonts::{tables::glyf, types::GlyphId, FontRef, TableProvider};
+
+    use crate::{Config, Glyph, Selection, Sources};
+
+    use super::{interpolate_untouched, with_implied_oncurves};
+
+    fn verdicts(file: &str) -> Vec<(String, Option<crate::OverlapKind>)> {
+        let file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/testdata")
+            .join(file);
+        let selection = Selection {
+            sources: Sources::Both,
+            ..Default::default()
+        };
+        let mut verdicts = Glyph::stream_selected([file], selection)
+            .map(|g| {
+                let result = g.unwrap().check(&Config::default()).unwrap();
+                let name = match &result.instance {
+                    Some(instance) => format!("{} at {instance}", result.name),
+                    None => result.name.as_str().to_string(),
+                };
+                (name, result.overlap.map(|o| o.kind))
+            })
+            .collect::<Vec<_>>();
+        verdicts.sort_by(|a, b| a.0.cmp(&b.0));
+        verdicts
+    }
+
+    #[test]
+    fn implied_oncurves() {
+        let points = with_implied_oncurves(&[
+            ((0.0, 0.0).into(), false),
+            ((10.0, 0.0).into(), true),
+            ((10.0, 10.0).into(), false),
+            ((0.0, 10.0).into(), false),
+        ]);
+        assert_eq!(
+            vec![
+                ((0.0, 0.0).into(), false),
+                ((10.0, 0.0).into(), true),
+                ((10.0, 10.0).into(), false),
+                ((5.0, 10.0).into(), true),
+                ((0.0, 10.0).into(), false),
+                ((0.0, 5.0).into(), true),
+            ],
+            points
+        );
+    }
+
+    #[test]
+    fn untouched_points_interpolated() {
+        // A square whose right side moves 100 right, the top right point left out,
+        // and a second contour left out entirely
+        let points = [
+            (0.0, 0.0),
+            (100.0, 0.0),
+            (100.0, 100.0),
+            (0.0, 100.0),
+            (200.0, 0.0),
+        ]
+        .map(Point::from);
+        let moved = Some(Vec2::new(100.0, 0.0));
+        let deltas = interpolate_untouched(
+            &points,
+            &[3, 4],
+            &[Some(Vec2::ZERO), moved, None, Some(Vec2::ZERO), None],
+        );
+        assert_eq!(
+            vec![
+                Vec2::ZERO,
+                Vec2::new(100.0, 0.0),
+                Vec2::new(100.0, 0.0),
+                Vec2::ZERO,
+                Vec2::ZERO
+            ],
+            deltas
+        );
+    }
+
+    /// Bars.ttf is compiled from Bars.designspace by build_bars_ttf.py
+    #[test]
+    fn named_instances() {
+        let flagged = verdicts("Bars.ttf")
+            .into_iter()
+            .filter(|(_, overlap)| overlap.is_some())
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "bars at Bold",
+                "bars at SemiBold",
+                "dollar.alt",
+                "dollar.alt at Bold",
+                "dollar.alt at Book",
+                "dollar.alt at SemiBold",
+            ],
+            flagged
+        );
+    }
+
+    #[test]
+    fn gid_range() {
+        let file = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/testdata/Bars.ttf");
+        let selection = Selection {
+            gids: Some(1..=2),
+            sources: Sources::Both,
+            ..Default::default()
+        };
+        let names = Glyph::stream_selected([file], selection)
+            .map(|g| g.unwrap().describe())
+            .collect::<Vec<_>>();
+        // At the default and the three other named instances, but only glyphs 1 and 2
+        assert_eq!(8, names.len(), "{names:?}");
+        assert!(names
+            .iter()
+            .all(|n| n.contains("bars") || n.contains("dollar")));
+        assert!(!names
+            .iter()
+            .any(|n| n.contains("notdef") || n.contains("alt")));
+    }
+
+    #[test]
+    fn named_instances_only() {
+        let file = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/testdata/Bars.ttf");
+        let instances = |sources| {
+            let selection = Selection {
+                gids: Some(1..=1),
+                sources,
+                ..Default::default()
+            };
+            Glyph::stream_selected([&file], selection)
+                .map(|g| g.unwrap().instance().map(str::to_string))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec![None], instances(Sources::Masters));
+        assert_eq!(
+            vec![
+                Some("Book".to_string()),
+                Some("SemiBold".to_string()),
+                Some("Bold".to_string())
+            ],
+            instances(Sources::Instances)
+        );
+    }
+
+    #[test]
+    fn user_location() {
+        let file = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/testdata/Bars.ttf");
+        let outlines = |location| {
+            let selection = Selection {
+                gids: Some(1..=1),
+                sources: Sources::Instances,
+                location,
+                ..Default::default()
+            };
+            Glyph::stream_selected([&file], selection)
+                .map(|g| {
+                    let g = g.unwrap();
+                    (g.instance().map(str::to_string), g.bezpath().to_svg())
+                })
+                .collect::<Vec<_>>()
+        };
+        let named = outlines(None);
+        let [(instance, at_600)] = outlines(Some(vec![("wght".to_string(), 600.0)]))
+            .try_into()
+            .unwrap();
+        assert_eq!(Some("wght=600"), instance.as_deref());
+        assert!(named.iter().any(
+            |(instance, semibold)| instance.as_deref() == Some("SemiBold") && *semibold == at_600
+        ));
+    }
+
+    #[test]
+    fn user_location_needs_variations() {
+        let file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/testdata/Overlaps.ttf");
+        let data = std::fs::read(&file).unwrap();
+        let at = [("wght".to_string(), 400.0)];
+        let Err(reason) = super::load_data(&file, data, None, Sources::Instances, Some(&at)) else {
+            panic!("Overlaps.ttf is static, there's nowhere to check wght=400");
+        };
+        assert_eq!("has no variation axes, --location can't apply", reason);
+    }
+
+    /// Overlaps.ttf is compiled from Overlaps.ufo by build_overlaps_ttf.py
+    #[test]
+    fn same_verdicts_as_ufo() {
+        assert_eq!(verdicts("Overlaps.ufo"), verdicts("Overlaps.ttf"));
+    }
+
+    /// Every simple glyph in Overlaps.ttf is hinted to round a point to the grid
+    #[test]
+    fn hinting_ignored() {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/testdata");
+        let data = std::fs::read(dir.join("Overlaps.ttf")).unwrap();
+        let font = FontRef::new(&data).unwrap();
+        let (loca, glyf) = (font.loca(None).unwrap(), font.glyf().unwrap());
+        // .notdef, o, then plus
+        let Ok(Some(glyf::Glyph::Simple(plus))) = loca.get_glyf(GlyphId::new(2), &glyf) else {
+            panic!("plus should be a simple glyph");
+        };
+        assert!(!plus.instructions().is_empty());
+
+        let plus = |file: &str| {
+            Glyph::from_file(dir.join(file))
+                .into_iter()
+                .map(|g| g.unwrap())
+                .find(|g| g.name().as_str() == "plus")
+                .unwrap()
+                .outline_hash()
+        };
+        assert_eq!(plus("Overlaps.ufo"), plus("Overlaps.ttf"));
+    }
+}
@@ -0,0 +1,113 @@
+//! Checking glyphs drawn directly against variants built from components, or the reverse.
+
+use std::{collections::HashMap, path::Path};
+
+use fontdrasil::types::GlyphName;
+use rayon::prelude::*;
+
+use crate::{CheckResult, Config, Error, Glyph};
+
+/// Verdicts for a glyph and its variant, e.g. `aacute` and `aacute.comp`.
+#[derive(Debug)]
+pub struct VariantComparison {
+    pub glyph: CheckResult,
+    pub variant: CheckResult,
+}
+
+impl VariantComparison {
+    /// Whether the two representations disagree about needing the overlap flag.
+    pub fn differs(&self) -> bool {
+        self.glyph.overlap.is_some() != self.variant.overlap.is_some()
+    }
+}
+
+/// Check every glyph in `glyphs` that has a variant named with `suffix` appended, e.g.
+/// `aacute` drawn directly and `aacute.comp` built from components, and the variant.
+///
+/// A glyph and its variant must come from the same layer or font, at the same instance.
+/// Glyphs without a variant, and variants without a glyph, are ignored. Results are in
+/// the order of the glyphs in `glyphs`.
+pub fn compare_variants(
+    glyphs: &[Glyph],
+    suffix: &str,
+    config: &Config,
+) -> Vec<Result<VariantComparison, Error>> {
+    let by_name = glyphs
+        .iter()
+        .map(|g| ((g.name.clone(), origin(g)), g))
+        .collect::<HashMap<_, _>>();
+    glyphs
+        .par_iter()
+        .filter_map(|glyph| {
+            let variant_name = GlyphName::new(format!("{}{suffix}", glyph.name));
+            let variant = by_name.get(&(variant_name, origin(glyph)))?;
+            Some(glyph.check(config).and_then(|glyph| {
+                Ok(VariantComparison {
+                    glyph,
+                    variant: variant.check(config)?,
+                })
+            }))
+        })
+        .collect()
+}
+
+/// Where a glyph came from, less its own file: the layer of a .glif, otherwise the
+/// file, and the instance.
+fn origin(glyph: &Glyph) -> (&Path, Option<&str>) {
+    let container = match glyph.source.extension().and_then(|e| e.to_str()) {
+        Some("glif") => glyph.source.parent().unwrap_or(&glyph.source),
+        _ => &glyph.source,
+    };
+    (container, glyph.instance.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use fontdrasil::types::GlyphName;
+    use kurbo::BezPath;
+
+    use super::compare_variants;
+    use crate::{Config, Glyph};
+
+    #[test]
+    fn components_match_drawing() {
+        let ufo = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/testdata/Overlaps.ufo");
+        let glyphs = Glyph::from_file(ufo)
+            .into_iter()
+            .map(|g| g.unwrap())
+            .collect::<Vec<_>>();
+        let comparisons = compare_variants(&glyphs, ".comp", &Config::default())
+            .into_iter()
+            .map(|c| c.unwrap())
+            .map(|c| (c.variant.name.as_str().to_string(), c.differs()))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![("plus.comp".to_string(), false)], comparisons);
+    }
+
+    #[test]
+    fn mismatch_reported() {
+        let glyph = |name: &str, path: &str| {
+            let bezpath = BezPath::from_svg(path).unwrap();
+            Glyph::new(GlyphName::new(name), Path::new("bars.json"), bezpath).unwrap()
+        };
+        let glyphs = [
+            glyph(
+                "bars",
+                "M0,0 L100,0 L100,500 L0,500 Z M50,0 L150,0 L150,500 L50,500 Z",
+            ),
+            glyph("bars.comp", "M0,0 L100,0 L100,500 L0,500 Z"),
+            // Elsewhere, so not a variant
+            Glyph::new(
+                GlyphName::new("bars.comp"),
+                Path::new("other.json"),
+                BezPath::from_svg("M0,0 L100,0 L100,500 L0,500 Z").unwrap(),
+            )
+            .unwrap(),
+        ];
+        let comparisons = compare_variants(&glyphs, ".comp", &Config::default());
+        assert_eq!(1, comparisons.len());
+        assert!(comparisons[0].as_ref().unwrap().differs());
+    }
+}
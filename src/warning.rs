@@ -0,0 +1,119 @@
+use std::path::PathBuf;
+
+use fontdrasil::types::GlyphName;
+use kurbo::Point;
+use thiserror::Error;
+
+/// Something suspicious about a single glyph that doesn't stop us checking it.
+#[derive(Debug, Clone, Error)]
+pub enum Warning {
+    #[error("{file:?} {glyph} has {count} zero-length segment(s) from duplicate points, the first at {point:?}")]
+    ZeroLengthSegment {
+        file: PathBuf,
+        glyph: GlyphName,
+        count: usize,
+        /// Font units, y-up
+        point: Point,
+    },
+    #[error("{file:?} {glyph} has {count} contour(s) with only off-curve points, drawn as quadratics with implied on-curves")]
+    OffCurveOnlyContour {
+        file: PathBuf,
+        glyph: GlyphName,
+        count: usize,
+    },
+    #[error("{file:?} {glyph} gets a different verdict once contour directions are normalized, check its winding")]
+    DirectionDependent {
+        file: PathBuf,
+        glyph: GlyphName,
+        /// Whether the glyph needed the flag before normalizing
+        as_drawn: bool,
+    },
+    #[error("{file:?} {glyph} has {count} contour(s) covering less than a pixel at the scale checked, overlaps involving them may be missed; try a larger scale")]
+    SubPixelContour {
+        file: PathBuf,
+        glyph: GlyphName,
+        count: usize,
+    },
+    #[error("{file:?} {glyph} had {count} contour(s) smaller than {min_area} square units dropped before checking")]
+    SmallContourDropped {
+        file: PathBuf,
+        glyph: GlyphName,
+        count: usize,
+        min_area: f64,
+    },
+    #[error("{file:?} {glyph} is a close call and comparing anti-aliased renders disagrees about whether it needs the overlap flag; the aliased verdict stands")]
+    MethodsDisagree {
+        file: PathBuf,
+        glyph: GlyphName,
+        /// Whether the anti-aliased comparison says the glyph needs the flag
+        anti_aliased: bool,
+    },
+    #[error(
+        "{file:?} {glyph} has no contours, fine for a space but often a glyph that was never drawn"
+    )]
+    NoContours { file: PathBuf, glyph: GlyphName },
+    #[error("{file:?} {glyph} has {count} contours whose areas cancel out to nothing, which the fill rules treat differently; almost always a shape drawn then subtracted by mistake")]
+    ZeroArea {
+        file: PathBuf,
+        glyph: GlyphName,
+        count: usize,
+    },
+    #[error("{file:?} {glyph} is from a font with {units_per_em} units per em, most fonts have 1000 or 2048; is it right?")]
+    UnusualUnitsPerEm {
+        file: PathBuf,
+        glyph: GlyphName,
+        units_per_em: f64,
+    },
+}
+
+impl Warning {
+    /// Every [`Warning::kind`].
+    pub const KINDS: &'static [&'static str] = &[
+        "zero-length-segment",
+        "off-curve-only-contour",
+        "direction-dependent",
+        "sub-pixel-contour",
+        "small-contour-dropped",
+        "methods-disagree",
+        "no-contours",
+        "zero-area",
+        "unusual-units-per-em",
+    ];
+
+    /// A stable name for the kind of warning, for reports and command line flags.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Warning::ZeroLengthSegment { .. } => "zero-length-segment",
+            Warning::OffCurveOnlyContour { .. } => "off-curve-only-contour",
+            Warning::DirectionDependent { .. } => "direction-dependent",
+            Warning::SubPixelContour { .. } => "sub-pixel-contour",
+            Warning::SmallContourDropped { .. } => "small-contour-dropped",
+            Warning::MethodsDisagree { .. } => "methods-disagree",
+            Warning::NoContours { .. } => "no-contours",
+            Warning::ZeroArea { .. } => "zero-area",
+            Warning::UnusualUnitsPerEm { .. } => "unusual-units-per-em",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use fontdrasil::types::GlyphName;
+
+    use super::Warning;
+
+    #[test]
+    fn kinds_listed() {
+        let warning = Warning::NoContours {
+            file: PathBuf::from("space.glif"),
+            glyph: GlyphName::new("space"),
+        };
+        assert!(Warning::KINDS.contains(&warning.kind()));
+        let mut kinds = Warning::KINDS.to_vec();
+        kinds.sort();
+        kinds.dedup();
+        assert_eq!(Warning::KINDS.len(), kinds.len());
+    }
+}